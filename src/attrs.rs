@@ -1,4 +1,4 @@
-use colored::Colorize;
+use crate::colors::Colorize;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use string_template_plus::{Render, RenderOptions, Template};
@@ -24,6 +24,23 @@ pub trait HasAttributes {
     fn attr(&self, name: &str) -> Option<&Attribute> {
         self.attr_map().get(name)
     }
+    /// Get the attribute using a dotted path (e.g. `rates.-1.value`),
+    /// the segments after the first are keys for [`Attribute::Table`]
+    /// and indices for [`Attribute::Array`], see [`Attribute::get_dot`]
+    fn attr_dot(&self, name: &str) -> Result<Option<&Attribute>, String> {
+        let mut parts = name.split('.');
+        let mut attr = match parts.next().and_then(|n| self.attr(n)) {
+            Some(a) => a,
+            None => return Ok(None),
+        };
+        for p in parts {
+            attr = match attr.get_dot(p)? {
+                Some(a) => a,
+                None => return Ok(None),
+            };
+        }
+        Ok(Some(attr))
+    }
     fn del_attr(&mut self, name: &str) -> Option<Attribute> {
         self.attr_map_mut().remove(name.into()).into()
     }
@@ -49,9 +66,25 @@ pub trait HasAttributes {
     }
 
     fn render(&self, template: &Template) -> anyhow::Result<String> {
+        self.render_with(template, &HashMap::new())
+    }
+
+    /// Render the template with extra variables on top of the
+    /// attributes, the extra variables take precedence over the
+    /// attributes with the same name
+    fn render_with(
+        &self,
+        template: &Template,
+        extra: &HashMap<String, String>,
+    ) -> anyhow::Result<String> {
         let mut op = RenderOptions::default();
+        op.variables
+            .extend(extra.iter().map(|(k, v)| (k.clone(), v.clone())));
         let used_vars = template.parts().iter().flat_map(|p| p.variables());
         for var in used_vars {
+            if extra.contains_key(var) {
+                continue;
+            }
             if let Some(val) = self.attr(var) {
                 op.variables.insert(var.to_string(), val.to_string());
             }
@@ -77,6 +110,8 @@ pub enum Attribute {
     DateTime(DateTime),
     Array(RVec<Attribute>),
     Table(AttrMap),
+    /// Explicitly empty value, written as `null`
+    Null,
 }
 
 impl Default for Attribute {
@@ -85,18 +120,44 @@ impl Default for Attribute {
     }
 }
 
+/// Float in the syntax of the attribute files, the infinity and nan
+/// have a sign as the bare `inf` and `nan` are variable names
+fn float_to_string(v: f64) -> String {
+    if v.is_nan() {
+        "+nan".to_string()
+    } else if v == f64::INFINITY {
+        "+inf".to_string()
+    } else {
+        format!("{v:?}")
+    }
+}
+
 impl ToString for Attribute {
     fn to_string(&self) -> String {
         match self {
             Self::Bool(v) => format!("{v:?}"),
             Self::String(v) => format!("{v:?}"),
             Self::Integer(v) => format!("{v:?}"),
-            Self::Float(v) => format!("{v:?}"),
+            Self::Float(v) => float_to_string(*v),
             Self::Date(v) => v.to_string(),
             Self::Time(v) => v.to_string(),
             Self::DateTime(v) => v.to_string(),
-            Self::Array(v) => format!("{v:?}"),
-            Self::Table(v) => format!("{v:?}"),
+            Self::Array(v) => format!(
+                "[{}]",
+                v.iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Self::Table(v) => format!(
+                "{{{}}}",
+                sorted_attrs(v)
+                    .into_iter()
+                    .map(|(k, v)| format!("{}={}", table_key(k), v.to_string()))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Self::Null => "null".to_string(),
         }
     }
 }
@@ -107,7 +168,7 @@ impl Attribute {
             Self::Bool(v) => format!("{v:?}").magenta().to_string(),
             Self::String(v) => format!("{v:?}").green().to_string(),
             Self::Integer(v) => format!("{v:?}").red().to_string(),
-            Self::Float(v) => format!("{v:?}").yellow().to_string(),
+            Self::Float(v) => float_to_string(*v).yellow().to_string(),
             Self::Date(v) => v.to_string().blue().to_string(),
             Self::Time(v) => v.to_string().blue().to_string(),
             Self::DateTime(v) => v.to_string().blue().to_string(),
@@ -120,16 +181,81 @@ impl Attribute {
             ),
             Self::Table(v) => format!(
                 "{{{}}}",
-                v.iter()
-                    .map(|Tuple2(k, v)| format!(
-                        "{}={}",
-                        k.to_string().blue(),
-                        v.to_colored_string()
-                    ))
+                sorted_attrs(v)
+                    .into_iter()
+                    .map(|(k, v)| format!("{}={}", table_key(k).blue(), v.to_colored_string()))
                     .collect::<Vec<String>>()
                     .join(", ")
             )
             .to_string(),
+            Self::Null => "null".magenta().to_string(),
+        }
+    }
+
+    /// Multi-line string with nested Array/Table values indented by
+    /// `indent` spaces per level, scalar values are kept inline
+    pub fn to_pretty_string(&self, indent: usize) -> String {
+        self.pretty_string(indent, 0, false)
+    }
+
+    /// Colored version of [`Attribute::to_pretty_string`]
+    pub fn to_pretty_colored_string(&self, indent: usize) -> String {
+        self.pretty_string(indent, 0, true)
+    }
+
+    fn pretty_string(&self, indent: usize, level: usize, colored: bool) -> String {
+        let pad = " ".repeat(indent * (level + 1));
+        let end_pad = " ".repeat(indent * level);
+        match self {
+            Self::Array(v) if !v.is_empty() => format!(
+                "[\n{}\n{end_pad}]",
+                v.iter()
+                    .map(|a| format!("{pad}{}", a.pretty_string(indent, level + 1, colored)))
+                    .collect::<Vec<String>>()
+                    .join(",\n")
+            ),
+            Self::Table(v) if !v.is_empty() => format!(
+                "{{\n{}\n{end_pad}}}",
+                sorted_attrs(v)
+                    .into_iter()
+                    .map(|(k, a)| {
+                        let k = table_key(k);
+                        format!(
+                            "{pad}{}={}",
+                            if colored { k.blue().to_string() } else { k },
+                            a.pretty_string(indent, level + 1, colored)
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join(",\n")
+            ),
+            a if colored => a.to_colored_string(),
+            a => a.to_string(),
+        }
+    }
+
+    /// Approximate number of bytes this value owns on the heap
+    ///
+    /// Strings and arrays count their allocated capacity, arrays also
+    /// add the heap size of each element. Tables are counted from the
+    /// number of entries (see [`attrs_heap_size`]), as the hashmap
+    /// internals are not exposed. The size of the [`Attribute`] itself
+    /// (`size_of::<Attribute>()`) is not included.
+    ///
+    /// ```
+    /// # use nadi_core::attrs::Attribute;
+    /// assert_eq!(Attribute::Integer(12).heap_size(), 0);
+    /// assert_eq!(Attribute::String("abc".into()).heap_size(), 3);
+    /// ```
+    pub fn heap_size(&self) -> usize {
+        match self {
+            Self::String(s) => s.capacity(),
+            Self::Array(v) => {
+                v.capacity() * std::mem::size_of::<Attribute>()
+                    + v.iter().map(|a| a.heap_size()).sum::<usize>()
+            }
+            Self::Table(m) => attrs_heap_size(m),
+            _ => 0,
         }
     }
 
@@ -144,7 +270,201 @@ impl Attribute {
             Self::DateTime(_) => "DateTime",
             Self::Array(_) => "Array",
             Self::Table(_) => "Table",
+            Self::Null => "Null",
+        }
+    }
+
+    /// Cast the attribute to the type named at runtime
+    ///
+    /// The `target` is one of `bool`, `int` (or `integer`), `float`,
+    /// `string` (or `str`), `date`, `time` and `datetime`. On top of
+    /// the relaxed conversions, floats are truncated to integers,
+    /// any value can be cast to a string, and strings are parsed
+    /// into the numbers, bools (`true`/`false`), and the date/time
+    /// types (only with the `parser` feature).
+    ///
+    /// ```
+    /// # use nadi_core::attrs::Attribute;
+    /// let v = Attribute::String("12".into());
+    /// assert_eq!(v.coerce("int"), Ok(Attribute::Integer(12)));
+    /// assert_eq!(Attribute::Float(2.7).coerce("int"), Ok(Attribute::Integer(2)));
+    /// assert!(Attribute::String("x".into()).coerce("float").is_err());
+    /// ```
+    pub fn coerce(&self, target: &str) -> Result<Attribute, String> {
+        const TYPES: [&str; 9] = [
+            "bool", "int", "integer", "float", "string", "str", "date", "time", "datetime",
+        ];
+        if !TYPES.contains(&target) {
+            return Err(format!(
+                "Unknown type {target:?}, should be one of {}",
+                TYPES.join(", ")
+            ));
         }
+        let err = || format!("Cannot cast `{}` to `{target}`", self.type_name());
+        let val = match (target, self) {
+            ("string" | "str", Self::String(_)) => self.clone(),
+            ("string" | "str", v) => Self::String(v.to_string().into()),
+            ("bool", Self::String(s)) => match s.as_str() {
+                "true" => Self::Bool(true),
+                "false" => Self::Bool(false),
+                _ => return Err(err()),
+            },
+            ("bool", v) => Self::Bool(bool::try_from_attr_relaxed(v)?),
+            ("int" | "integer", Self::Float(f)) if f.is_finite() => Self::Integer(f.trunc() as i64),
+            ("int" | "integer", Self::String(s)) => {
+                Self::Integer(s.trim().parse().map_err(|e| format!("{}: {e}", err()))?)
+            }
+            ("int" | "integer", v) => Self::Integer(i64::try_from_attr_relaxed(v)?),
+            ("float", Self::String(s)) => {
+                Self::Float(s.trim().parse().map_err(|e| format!("{}: {e}", err()))?)
+            }
+            ("float", v) => Self::Float(f64::try_from_attr_relaxed(v)?),
+            ("date", Self::Date(_)) | ("time", Self::Time(_)) | ("datetime", Self::DateTime(_)) => {
+                self.clone()
+            }
+            ("date", Self::DateTime(dt)) => Self::Date(dt.date.clone()),
+            ("time", Self::DateTime(dt)) => Self::Time(dt.time.clone()),
+            ("datetime", Self::Date(d)) => {
+                Self::DateTime(DateTime::new(d.clone(), Time::default(), None))
+            }
+            #[cfg(feature = "parser")]
+            ("date", Self::String(s)) => {
+                Self::Date(s.trim().parse().map_err(|e| format!("{}: {e}", err()))?)
+            }
+            #[cfg(feature = "parser")]
+            ("time", Self::String(s)) => {
+                Self::Time(s.trim().parse().map_err(|e| format!("{}: {e}", err()))?)
+            }
+            #[cfg(feature = "parser")]
+            ("datetime", Self::String(s)) => {
+                Self::DateTime(s.trim().parse().map_err(|e| format!("{}: {e}", err()))?)
+            }
+            _ => return Err(err()),
+        };
+        Ok(val)
+    }
+
+    /// Array of the values from the iterator
+    ///
+    /// ```
+    /// # use nadi_core::attrs::Attribute;
+    /// let arr = Attribute::array_of(1..4i64);
+    /// assert_eq!(arr.to_string(), "[1, 2, 3]");
+    /// assert!(arr.is_homogeneous());
+    /// ```
+    pub fn array_of<T: Into<Attribute>>(iter: impl IntoIterator<Item = T>) -> Self {
+        Self::Array(iter.into_iter().map(Into::into).collect())
+    }
+
+    /// Array of [`Attribute::Float`] values
+    ///
+    /// ```
+    /// # use nadi_core::attrs::Attribute;
+    /// let arr = Attribute::float_array(vec![1.0, 2.5]);
+    /// assert_eq!(arr.to_string(), "[1.0, 2.5]");
+    /// ```
+    pub fn float_array(values: Vec<f64>) -> Self {
+        Self::array_of(values)
+    }
+
+    /// Array of [`Attribute::Integer`] values
+    ///
+    /// ```
+    /// # use nadi_core::attrs::Attribute;
+    /// let arr = Attribute::int_array(vec![1, 2]);
+    /// assert_eq!(arr.to_string(), "[1, 2]");
+    /// ```
+    pub fn int_array(values: Vec<i64>) -> Self {
+        Self::array_of(values)
+    }
+
+    /// Array of [`Attribute::String`] values
+    ///
+    /// ```
+    /// # use nadi_core::attrs::Attribute;
+    /// let arr = Attribute::string_array(vec!["a".to_string(), "b".to_string()]);
+    /// assert_eq!(arr.to_string(), "[\"a\", \"b\"]");
+    /// ```
+    pub fn string_array(values: Vec<String>) -> Self {
+        Self::array_of(values)
+    }
+
+    /// Whether the value is an array with all the elements of the
+    /// same type, empty array is homogeneous
+    ///
+    /// ```
+    /// # use nadi_core::attrs::Attribute;
+    /// let arr = Attribute::Array(vec![Attribute::Integer(1), Attribute::Float(1.0)].into());
+    /// assert!(!arr.is_homogeneous());
+    /// assert!(!Attribute::Integer(1).is_homogeneous());
+    /// ```
+    pub fn is_homogeneous(&self) -> bool {
+        match self {
+            Self::Array(v) => v.windows(2).all(|w| w[0].type_name() == w[1].type_name()),
+            _ => false,
+        }
+    }
+
+    /// Equality that considers the floats within the relative
+    /// tolerance `rel_tol` as equal, useful for comparing the results
+    /// of computations like `0.1 + 0.2` with `0.3`
+    ///
+    /// The floats `a` and `b` are equal if `|a - b| <= rel_tol *
+    /// max(|a|, |b|)`, the integers are compared as floats with the
+    /// floats, while the arrays and tables are compared element-wise.
+    /// Other values use the exact equality, same as a `rel_tol` of
+    /// `0.0` does for the floats.
+    ///
+    /// ```
+    /// # use nadi_core::attrs::Attribute;
+    /// let a = Attribute::Float(0.1 + 0.2);
+    /// assert!(a != Attribute::Float(0.3));
+    /// assert!(a.approx_eq(&Attribute::Float(0.3), 1e-9));
+    /// ```
+    pub fn approx_eq(&self, other: &Self, rel_tol: f64) -> bool {
+        let float_eq = |a: f64, b: f64| a == b || (a - b).abs() <= rel_tol * a.abs().max(b.abs());
+        match (self, other) {
+            (Self::Float(a), Self::Float(b)) => float_eq(*a, *b),
+            (Self::Float(a), Self::Integer(b)) => float_eq(*a, *b as f64),
+            (Self::Integer(a), Self::Float(b)) => float_eq(*a as f64, *b),
+            (Self::Array(a), Self::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.approx_eq(b, rel_tol))
+            }
+            (Self::Table(a), Self::Table(b)) => {
+                a.len() == b.len()
+                    && a.iter().all(|Tuple2(k, v)| {
+                        b.get(k).map(|o| v.approx_eq(o, rel_tol)).unwrap_or(false)
+                    })
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    /// Copy of the table as a std [`HashMap`], `None` if it's not an
+    /// [`Attribute::Table`]
+    ///
+    /// ```
+    /// # use nadi_core::attrs::Attribute;
+    /// # use std::collections::HashMap;
+    /// let tbl = Attribute::from_std_table(HashMap::from([("x".to_string(), Attribute::Integer(1))]));
+    /// let map = tbl.as_std_table().unwrap();
+    /// assert_eq!(map["x"], Attribute::Integer(1));
+    /// assert!(Attribute::Integer(1).as_std_table().is_none());
+    /// ```
+    pub fn as_std_table(&self) -> Option<HashMap<String, Attribute>> {
+        self.get_table().map(attrmap_to_std)
+    }
+
+    /// [`Attribute::Table`] from a std [`HashMap`]
+    ///
+    /// ```
+    /// # use nadi_core::attrs::Attribute;
+    /// # use std::collections::HashMap;
+    /// let tbl = Attribute::from_std_table(HashMap::from([("x".to_string(), Attribute::Integer(1))]));
+    /// assert_eq!(tbl.to_string(), "{x=1}");
+    /// ```
+    pub fn from_std_table(map: HashMap<String, Attribute>) -> Self {
+        Self::Table(attrmap_from_std(map))
     }
 
     pub fn get_string(&self) -> Option<RStr> {
@@ -167,6 +487,33 @@ impl Attribute {
             _ => None,
         }
     }
+
+    /// Get a single segment of the dotted path: key for the Table,
+    /// and index for the Array. Negative indices count from the end
+    /// of the Array like in python (`-1` is the last element).
+    pub fn get_dot(&self, key: &str) -> Result<Option<&Attribute>, String> {
+        match self {
+            Self::Table(t) => Ok(t.get(key)),
+            Self::Array(a) => {
+                let ind: i64 = key
+                    .parse()
+                    .map_err(|_| format!("Invalid index `{key}` for Array"))?;
+                let len = a.len();
+                let i = if ind < 0 {
+                    len.checked_sub(ind.unsigned_abs() as usize)
+                } else {
+                    Some(ind as usize).filter(|i| *i < len)
+                };
+                match i {
+                    Some(i) => Ok(a.get(i)),
+                    None => Err(format!(
+                        "Index {ind} out of bounds for Array of length {len}"
+                    )),
+                }
+            }
+            a => Err(format!("Cannot index `{key}` into {}", a.type_name())),
+        }
+    }
 }
 
 pub trait FromAttribute: Sized {
@@ -229,33 +576,75 @@ macro_rules! impl_from_attr {
     };
 }
 
+/// Convert all the attributes to `T`, the error has the position
+/// (starting from 1) and the type of the first one that fails
+///
+/// ```
+/// # use nadi_core::attrs::{attrs_as, Attribute};
+/// let attrs = [Attribute::Integer(1), Attribute::Bool(true)];
+/// assert_eq!(attrs_as::<i64>(&attrs[..1]), Ok(vec![1]));
+/// assert!(attrs_as::<i64>(&attrs).unwrap_err().starts_with("Argument 2 [i64]"));
+/// ```
+pub fn attrs_as<T: FromAttribute>(attrs: &[Attribute]) -> Result<Vec<T>, String> {
+    attrs
+        .iter()
+        .enumerate()
+        .map(|(i, a)| {
+            T::try_from_attr(a)
+                .map_err(|e| format!("Argument {} [{}]: {e}", i + 1, type_name::<T>()))
+        })
+        .collect()
+}
+
+/// Same as [`attrs_as`] but with the [`FromAttributeRelaxed`] conversion
+pub fn attrs_as_relaxed<T: FromAttributeRelaxed>(attrs: &[Attribute]) -> Result<Vec<T>, String> {
+    attrs
+        .iter()
+        .enumerate()
+        .map(|(i, a)| {
+            T::try_from_attr_relaxed(a)
+                .map_err(|e| format!("Argument {} [{}]: {e}", i + 1, type_name::<T>()))
+        })
+        .collect()
+}
+
 /// Get String representation of different types
+///
+/// Only the last segment of each path in the full type name is used
+/// (e.g. `alloc::vec::Vec<alloc::string::String>` becomes
+/// `Vec<String>`), everything else is kept as it is.
 pub fn type_name<P>() -> String {
-    // function returns the full path, but we'll only use the last
     let org = std::any::type_name::<P>();
-    let parts = org.split(&[',', '(', ')', '<', '>']);
-    let mut name = String::new();
-    let mut offset = 0;
-    for part in parts {
-        name.push_str(part.split("::").last().unwrap_or("_"));
-        offset += part.len();
-        if offset < org.len() {
-            // this part is to reinsert the char we used to split at
-            // this location
-            name.push_str(&org[offset..(offset + 1)]);
-            offset += 1;
+    let mut name = String::with_capacity(org.len());
+    // start of the current path segment in the name
+    let mut seg_start = 0;
+    let mut chars = org.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            name.truncate(seg_start);
+        } else {
+            name.push(c);
+            if !(c.is_alphanumeric() || c == '_') {
+                seg_start = name.len();
+            }
         }
     }
     name
 }
 
 // impls for standard types used in enum
+
+// truthiness for relaxed bool: non-zero numbers and non-empty
+// string/array/table are true, null is false, dates and times are
+// not converted
 impl_from_attr!(bool, Attribute::Bool,
 		Attribute::Integer(v) => *v != 0,
 		Attribute::Float(v) => *v != 0.0,
 		Attribute::String(s) => !s.is_empty(),
 		Attribute::Array(s) => !s.is_empty(),
-		Attribute::Table(s) => !s.is_empty());
+		Attribute::Table(s) => !s.is_empty(),
+		Attribute::Null => false);
 impl_from_attr!(RString, Attribute::String,);
 impl_from_attr!(i64, Attribute::Integer,
 		Attribute::Bool(v) => *v as i64);
@@ -406,6 +795,38 @@ where
     }
 }
 
+/// [`Attribute::Null`] is `None`, any other value is converted to `T`
+impl<T> FromAttribute for Option<T>
+where
+    T: FromAttribute,
+{
+    fn from_attr(value: &Attribute) -> Option<Option<T>> {
+        match value {
+            Attribute::Null => Some(None),
+            v => T::from_attr(v).map(Some),
+        }
+    }
+
+    fn try_from_attr(value: &Attribute) -> Result<Option<T>, String> {
+        match value {
+            Attribute::Null => Ok(None),
+            v => T::try_from_attr(v).map(Some),
+        }
+    }
+}
+
+impl<T> FromAttributeRelaxed for Option<T>
+where
+    T: FromAttributeRelaxed,
+{
+    fn try_from_attr_relaxed(value: &Attribute) -> Result<Option<T>, String> {
+        match value {
+            Attribute::Null => Ok(None),
+            v => T::try_from_attr_relaxed(v).map(Some),
+        }
+    }
+}
+
 impl<T> FromAttribute for Vec<T>
 where
     T: FromAttribute,
@@ -489,8 +910,352 @@ where
 }
 
 pub type AttrSlice<'a> = RSlice<'a, Attribute>;
+
+/// Map of attribute names to their values.
+///
+/// The insertion order of the keys is not preserved, but all the
+/// display and serialization paths ([`Attribute::to_string`],
+/// [`Attribute::to_colored_string`], etc.) sort the keys so that the
+/// output is deterministic. Use [`sorted_attrs`] to do the same.
 pub type AttrMap = RHashMap<RString, Attribute>;
 
+/// Approximate heap bytes used by the attribute map, each entry
+/// counts the size of the key/value pair, the capacity of the key
+/// and the [`Attribute::heap_size`] of the value; unused buckets of
+/// the hashmap are not counted
+pub fn attrs_heap_size(attrs: &AttrMap) -> usize {
+    attrs
+        .iter()
+        .map(|Tuple2(k, v)| {
+            std::mem::size_of::<(RString, Attribute)>() + k.capacity() + v.heap_size()
+        })
+        .sum()
+}
+
+/// Attribute where the comments are saved while loading attribute
+/// files with comments, it's a table of the dotted key path to the
+/// comment text
+pub const COMMENTS_ATTR: &str = "_comments";
+
+/// Key in the attribute files that names other attribute files (a
+/// string or an array of strings) to include, see
+/// `NodeInner::load_attr_with_includes`
+pub const INCLUDE_ATTR: &str = "include";
+
+/// Copy of the [`AttrMap`] as a std [`HashMap`] with [`String`] keys
+///
+/// ```
+/// # use nadi_core::attrs::{attrmap_to_std, AttrMap, Attribute};
+/// let mut map = AttrMap::new();
+/// map.insert("x".into(), Attribute::Bool(true));
+/// assert_eq!(attrmap_to_std(&map)["x"], Attribute::Bool(true));
+/// ```
+pub fn attrmap_to_std(map: &AttrMap) -> HashMap<String, Attribute> {
+    map.iter()
+        .map(|Tuple2(k, v)| (k.to_string(), v.clone()))
+        .collect()
+}
+
+/// [`AttrMap`] from a std [`HashMap`] with [`String`] keys
+///
+/// ```
+/// # use nadi_core::attrs::{attrmap_from_std, Attribute};
+/// # use std::collections::HashMap;
+/// let map = attrmap_from_std(HashMap::from([("x".to_string(), Attribute::Bool(true))]));
+/// assert_eq!(map.get("x"), Some(&Attribute::Bool(true)));
+/// ```
+pub fn attrmap_from_std(map: HashMap<String, Attribute>) -> AttrMap {
+    map.into_iter()
+        .map(|(k, v)| (RString::from(k), v))
+        .collect()
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Value> for Attribute {
+    fn from(value: serde_json::Value) -> Self {
+        use serde_json::Value;
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(b) => Self::Bool(b),
+            Value::Number(n) => match n.as_i64() {
+                Some(i) => Self::Integer(i),
+                None => Self::Float(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            Value::String(s) => Self::String(s.into()),
+            Value::Array(v) => Self::Array(v.into_iter().map(Self::from).collect()),
+            Value::Object(m) => Self::Table(
+                m.into_iter()
+                    .map(|(k, v)| (RString::from(k), Self::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// JSON value of the attribute, the date/time values are converted to
+/// strings, and the non-finite floats (`NaN`, `inf`) to `null`
+#[cfg(feature = "serde")]
+impl From<&Attribute> for serde_json::Value {
+    fn from(value: &Attribute) -> Self {
+        use serde_json::Value;
+        match value {
+            Attribute::Null => Value::Null,
+            Attribute::Bool(b) => Value::Bool(*b),
+            Attribute::Integer(i) => Value::from(*i),
+            Attribute::Float(f) => serde_json::Number::from_f64(*f)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            Attribute::String(s) => Value::String(s.to_string()),
+            Attribute::Date(_) | Attribute::Time(_) | Attribute::DateTime(_) => {
+                Value::String(value.to_string())
+            }
+            Attribute::Array(v) => Value::Array(v.iter().map(Value::from).collect()),
+            Attribute::Table(m) => Value::Object(
+                m.iter()
+                    .map(|Tuple2(k, v)| (k.to_string(), Value::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Parse the [`AttrMap`] from a JSON object, the error message has
+/// the line and column of the invalid JSON
+///
+/// ```
+/// # use nadi_core::attrs::{attrmap_from_json, Attribute};
+/// let map = attrmap_from_json(r#"{"x": 1, "y": [true, null]}"#).unwrap();
+/// assert_eq!(map.get("x"), Some(&Attribute::Integer(1)));
+/// assert!(attrmap_from_json("[1, 2]").is_err());
+/// ```
+#[cfg(feature = "serde")]
+pub fn attrmap_from_json(txt: &str) -> Result<AttrMap, String> {
+    let value: serde_json::Value = serde_json::from_str(txt).map_err(|e| e.to_string())?;
+    match Attribute::from(value) {
+        Attribute::Table(t) => Ok(t),
+        a => Err(format!(
+            "JSON attributes should be an object, not {}",
+            a.type_name()
+        )),
+    }
+}
+
+/// Key value pairs of the [`AttrMap`] sorted by the key
+pub fn sorted_attrs(map: &AttrMap) -> Vec<(&RString, &Attribute)> {
+    let mut items: Vec<(&RString, &Attribute)> = map.iter().map(|Tuple2(k, v)| (k, v)).collect();
+    items.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+    items
+}
+
+/// How to merge the attributes when the key is present in both maps,
+/// the keys only present in the source are always added
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum MergePolicy {
+    /// Keep the existing value
+    #[default]
+    Keep,
+    /// Replace with the value from the source
+    Overwrite,
+    /// Add the numeric values (result is float if either is float),
+    /// other types keep the existing value
+    Sum,
+    /// Error if the values are different, same values are kept
+    Error,
+}
+
+impl std::str::FromStr for MergePolicy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "overwrite" => Ok(Self::Overwrite),
+            "sum" => Ok(Self::Sum),
+            "error" => Ok(Self::Error),
+            _ => Err(format!(
+                "Invalid merge policy {s:?}, should be one of keep, overwrite, sum or error"
+            )),
+        }
+    }
+}
+
+/// Merge the attributes from `source` into `target` based on the [`MergePolicy`]
+///
+/// # Errors
+/// Errors on the first conflicting key with [`MergePolicy::Error`],
+/// `target` is not modified in that case.
+pub fn merge_attrs(
+    target: &mut AttrMap,
+    source: &AttrMap,
+    policy: MergePolicy,
+) -> Result<(), String> {
+    if policy == MergePolicy::Error {
+        let mut keys: Vec<&RString> = source.keys().collect();
+        keys.sort();
+        for k in keys {
+            match (target.get(k), source.get(k)) {
+                (Some(a), Some(b)) if a != b => {
+                    return Err(format!(
+                        "Conflicting values for attribute {k}: {} and {}",
+                        a.to_string(),
+                        b.to_string()
+                    ))
+                }
+                _ => (),
+            }
+        }
+    }
+    for Tuple2(k, v) in source.iter() {
+        let existing = match target.get_mut(k) {
+            Some(e) => e,
+            None => {
+                target.insert(k.clone(), v.clone());
+                continue;
+            }
+        };
+        match (policy, &*existing, v) {
+            (MergePolicy::Keep, _, _) => (),
+            (MergePolicy::Overwrite, _, _) => *existing = v.clone(),
+            (MergePolicy::Sum, Attribute::Integer(a), Attribute::Integer(b)) => {
+                *existing = Attribute::Integer(a + b)
+            }
+            (MergePolicy::Sum, Attribute::Integer(a), Attribute::Float(b)) => {
+                *existing = Attribute::Float(*a as f64 + b)
+            }
+            (MergePolicy::Sum, Attribute::Float(a), Attribute::Integer(b)) => {
+                *existing = Attribute::Float(a + *b as f64)
+            }
+            (MergePolicy::Sum, Attribute::Float(a), Attribute::Float(b)) => {
+                *existing = Attribute::Float(a + b)
+            }
+            (MergePolicy::Sum, _, _) | (MergePolicy::Error, _, _) => (),
+        }
+    }
+    Ok(())
+}
+
+/// Change in an attribute between two [`AttrMap`]s, see [`attr_diff`]
+///
+/// The `key` of the attributes inside nested tables is the path of
+/// keys joined with a `.`
+#[derive(Clone, PartialEq, Debug)]
+pub enum AttrChange {
+    Added {
+        key: String,
+        val: Attribute,
+    },
+    Removed {
+        key: String,
+        val: Attribute,
+    },
+    Changed {
+        key: String,
+        old: Attribute,
+        new: Attribute,
+    },
+}
+
+impl AttrChange {
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Added { key, .. } | Self::Removed { key, .. } | Self::Changed { key, .. } => key,
+        }
+    }
+}
+
+impl std::fmt::Display for AttrChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Added { key, val } => write!(f, "+{key}={}", val.to_string()),
+            Self::Removed { key, val } => write!(f, "-{key}={}", val.to_string()),
+            Self::Changed { key, old, new } => {
+                write!(f, "{key}={} -> {}", old.to_string(), new.to_string())
+            }
+        }
+    }
+}
+
+/// Changes in the attributes from `before` to `after`, sorted by key
+///
+/// Tables present in both maps are compared recursively, so only the
+/// changed values inside them are reported.
+pub fn attr_diff(before: &AttrMap, after: &AttrMap) -> Vec<AttrChange> {
+    let mut changes = vec![];
+    diff_into("", before, after, &mut changes);
+    changes
+}
+
+fn diff_into(prefix: &str, before: &AttrMap, after: &AttrMap, changes: &mut Vec<AttrChange>) {
+    let mut keys: Vec<&RString> = before.keys().chain(after.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for k in keys {
+        let key = format!("{prefix}{k}");
+        match (before.get(k), after.get(k)) {
+            (Some(Attribute::Table(a)), Some(Attribute::Table(b))) => {
+                diff_into(&format!("{key}."), a, b, changes)
+            }
+            (Some(a), Some(b)) if a != b => changes.push(AttrChange::Changed {
+                key,
+                old: a.clone(),
+                new: b.clone(),
+            }),
+            (Some(_), Some(_)) => (),
+            (Some(a), None) => changes.push(AttrChange::Removed {
+                key,
+                val: a.clone(),
+            }),
+            (None, Some(b)) => changes.push(AttrChange::Added {
+                key,
+                val: b.clone(),
+            }),
+            (None, None) => unreachable!("key is from one of the maps"),
+        }
+    }
+}
+
+/// Write the attributes as `key = value` lines sorted by the keys,
+/// in the format of the attribute files; the comments saved in
+/// [`COMMENTS_ATTR`] are written before their keys instead of as an
+/// attribute
+pub fn write_attrs<W: std::io::Write>(w: &mut W, attrs: &AttrMap) -> std::io::Result<()> {
+    let comments = match attrs.get(COMMENTS_ATTR) {
+        Some(Attribute::Table(t)) => Some(t),
+        _ => None,
+    };
+    for (k, v) in sorted_attrs(attrs) {
+        if k.as_str() == COMMENTS_ATTR {
+            continue;
+        }
+        if let Some(Attribute::String(c)) = comments.and_then(|c| c.get(k)) {
+            for line in c.lines() {
+                writeln!(w, "# {line}")?;
+            }
+        }
+        writeln!(w, "{} = {}", table_key(k), v.to_string())?;
+    }
+    Ok(())
+}
+
+/// Key of the table as written in the table literal, quoted if it
+/// is not a simple identifier
+pub(crate) fn table_key(key: &str) -> String {
+    let mut chars = key.chars();
+    let simple = chars
+        .next()
+        .map(|c| c.is_ascii_alphabetic() || c == '_')
+        .unwrap_or(false)
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !matches!(
+            key,
+            "node" | "network" | "net" | "env" | "exit" | "help" | "true" | "false" | "null"
+        );
+    if simple {
+        key.to_string()
+    } else {
+        format!("{key:?}")
+    }
+}
+
 #[repr(C)]
 #[derive(StableAbi, Default, Clone, PartialEq, Debug)]
 pub struct DateTime {
@@ -557,6 +1322,16 @@ impl DateTime {
             offset: offset.into(),
         }
     }
+
+    /// Current date and time in the local timezone, or in UTC
+    #[cfg(feature = "chrono")]
+    pub fn now(utc: bool) -> Self {
+        if utc {
+            chrono::Utc::now().fixed_offset().into()
+        } else {
+            chrono::Local::now().fixed_offset().into()
+        }
+    }
 }
 
 #[repr(C)]
@@ -594,6 +1369,16 @@ impl Date {
         Self { year, month, day }
     }
 
+    /// Current date in the local timezone, or in UTC
+    #[cfg(feature = "chrono")]
+    pub fn today(utc: bool) -> Self {
+        if utc {
+            chrono::Utc::now().date_naive().into()
+        } else {
+            chrono::Local::now().date_naive().into()
+        }
+    }
+
     pub fn with_time(self, time: Time) -> DateTime {
         DateTime {
             date: self,
@@ -738,6 +1523,34 @@ mod tests {
     use super::*;
     use rstest::rstest;
 
+    #[rstest]
+    #[case(0.1 + 0.2, 0.3, 0.0, false)]
+    #[case(0.1 + 0.2, 0.3, 1e-9, true)]
+    #[case(1.0, 1.001, 1e-3, true)]
+    #[case(1.0, 1.001, 1e-4, false)]
+    #[case(-1.0, 1.0, 1.0, false)]
+    fn approx_eq_test(#[case] a: f64, #[case] b: f64, #[case] tol: f64, #[case] eq: bool) {
+        assert_eq!(Attribute::Float(a).approx_eq(&Attribute::Float(b), tol), eq);
+        let arr = |v| Attribute::Array(vec![Attribute::Integer(1), Attribute::Float(v)].into());
+        assert_eq!(arr(a).approx_eq(&arr(b), tol), eq);
+    }
+
+    #[rstest]
+    fn render_with_test() {
+        let mut node = crate::node::NodeInner::new(0, "a");
+        node.set_attr("x", Attribute::Integer(1));
+        node.set_attr("basin", Attribute::String("Ohio".into()));
+        let templ = Template::parse_template("{x} {_basin} {y}").unwrap();
+        let mut extra = HashMap::new();
+        extra.insert("y".to_string(), "2".to_string());
+        assert_eq!(node.render_with(&templ, &extra).unwrap(), "1 Ohio 2");
+        // extra variables take precedence over the attributes
+        extra.insert("x".to_string(), "5".to_string());
+        assert_eq!(node.render_with(&templ, &extra).unwrap(), "5 Ohio 2");
+        let templ = Template::parse_template("{x} {_basin}").unwrap();
+        assert_eq!(node.render(&templ).unwrap(), "1 Ohio");
+    }
+
     #[rstest]
     fn from_attr_test() {
         let val: bool = FromAttribute::from_attr(&Attribute::Bool(true)).unwrap();
@@ -756,6 +1569,29 @@ mod tests {
         assert_eq!(val, (2, true));
     }
 
+    #[rstest]
+    #[case(Attribute::Bool(true), true)]
+    #[case(Attribute::Bool(false), false)]
+    #[case(Attribute::Integer(0), false)]
+    #[case(Attribute::Integer(-1), true)]
+    #[case(Attribute::Float(0.0), false)]
+    #[case(Attribute::Float(0.5), true)]
+    #[case(Attribute::String("".into()), false)]
+    #[case(Attribute::String("false".into()), true)]
+    #[case(Attribute::Array(RVec::new()), false)]
+    #[case(Attribute::Array(vec![Attribute::Bool(false)].into()), true)]
+    #[case(Attribute::Table(AttrMap::new()), false)]
+    #[case(Attribute::Null, false)]
+    #[case(Attribute::Table(vec![(RString::from("a"), Attribute::Integer(0))].into_iter().collect()), true)]
+    fn relaxed_bool_test(#[case] attr: Attribute, #[case] value: bool) {
+        assert_eq!(bool::try_from_attr_relaxed(&attr), Ok(value));
+    }
+
+    #[rstest]
+    fn relaxed_bool_date_test() {
+        assert!(bool::try_from_attr_relaxed(&Attribute::Date(Date::new(2020, 1, 1))).is_err());
+    }
+
     #[rstest]
     fn try_from_attr_test() {
         let val: bool = FromAttribute::try_from_attr(&Attribute::Bool(true)).unwrap();
@@ -807,4 +1643,272 @@ mod tests {
         .unwrap();
         assert_eq!(val, (2, true));
     }
+
+    #[rstest]
+    fn table_to_string_test() {
+        let mut tbl = AttrMap::new();
+        tbl.insert("b".into(), Attribute::Integer(2));
+        tbl.insert("a".into(), Attribute::Float(1.0));
+        tbl.insert("some key".into(), Attribute::String("x".into()));
+        tbl.insert(
+            "arr".into(),
+            Attribute::Array(vec![Attribute::Bool(true), Attribute::Integer(1)].into()),
+        );
+        assert_eq!(
+            Attribute::Table(tbl).to_string(),
+            r#"{a=1.0, arr=[true, 1], b=2, "some key"="x"}"#
+        );
+    }
+
+    #[cfg(feature = "parser")]
+    #[rstest]
+    fn table_key_round_trip_test() {
+        let mut tbl = AttrMap::new();
+        for key in ["null", "true", "false", "node", "some key", "x"] {
+            tbl.insert(key.into(), Attribute::Integer(1));
+        }
+        assert_eq!(table_key("null"), "\"null\"");
+        let mut attrs = tbl.clone();
+        attrs.insert("tbl".into(), Attribute::Table(tbl));
+        let mut buf = Vec::new();
+        write_attrs(&mut buf, &attrs).unwrap();
+        let txt = String::from_utf8(buf).unwrap();
+        let tokens = crate::parser::tokenizer::get_tokens(&txt).unwrap();
+        assert_eq!(crate::parser::attrs::parse(tokens), Ok(attrs));
+    }
+
+    #[rstest]
+    #[case("arr.0", Ok(Some(Attribute::Integer(1))))]
+    #[case("arr.2", Ok(Some(Attribute::Integer(3))))]
+    #[case("arr.-1", Ok(Some(Attribute::Integer(3))))]
+    #[case("arr.-3", Ok(Some(Attribute::Integer(1))))]
+    #[case("arr.-0", Ok(Some(Attribute::Integer(1))))]
+    #[case("tbl.x", Ok(Some(Attribute::Bool(true))))]
+    #[case("tbl.y", Ok(None))]
+    #[case("missing.0", Ok(None))]
+    #[case("arr.3", Err("Index 3 out of bounds for Array of length 3".to_string()))]
+    #[case("arr.-4", Err("Index -4 out of bounds for Array of length 3".to_string()))]
+    #[case("empty.0", Err("Index 0 out of bounds for Array of length 0".to_string()))]
+    #[case("empty.-0", Err("Index 0 out of bounds for Array of length 0".to_string()))]
+    #[case("empty.-1", Err("Index -1 out of bounds for Array of length 0".to_string()))]
+    #[case("arr.x", Err("Invalid index `x` for Array".to_string()))]
+    fn attr_dot_test(#[case] path: &str, #[case] value: Result<Option<Attribute>, String>) {
+        let mut tbl = AttrMap::new();
+        tbl.insert("x".into(), Attribute::Bool(true));
+        let mut attrs = AttrMap::new();
+        attrs.insert(
+            "arr".into(),
+            Attribute::Array(
+                vec![
+                    Attribute::Integer(1),
+                    Attribute::Integer(2),
+                    Attribute::Integer(3),
+                ]
+                .into(),
+            ),
+        );
+        attrs.insert("empty".into(), Attribute::Array(RVec::new()));
+        attrs.insert("tbl".into(), Attribute::Table(tbl));
+        let node = crate::node::NodeInner {
+            attributes: attrs,
+            ..Default::default()
+        };
+        assert_eq!(node.attr_dot(path).map(|a| a.cloned()), value);
+    }
+
+    #[rstest]
+    fn pretty_string_test() {
+        let mut tbl = AttrMap::new();
+        tbl.insert("b".into(), Attribute::Array(RVec::new()));
+        tbl.insert(
+            "a".into(),
+            Attribute::Array(vec![Attribute::Integer(1), Attribute::Integer(2)].into()),
+        );
+        assert_eq!(
+            Attribute::Table(tbl).to_pretty_string(2),
+            "{\n  a=[\n    1,\n    2\n  ],\n  b=[]\n}"
+        );
+        assert_eq!(Attribute::Integer(1).to_pretty_string(2), "1");
+    }
+
+    #[cfg(feature = "serde")]
+    #[rstest]
+    fn attrmap_from_json_test() {
+        let map =
+            attrmap_from_json(r#"{"a": 1, "b": 2.5, "c": {"d": "x"}, "e": [null, true]}"#).unwrap();
+        assert_eq!(map.get("a"), Some(&Attribute::Integer(1)));
+        assert_eq!(map.get("b"), Some(&Attribute::Float(2.5)));
+        assert_eq!(map.get("c").unwrap().to_string(), "{d=\"x\"}");
+        assert_eq!(map.get("e").unwrap().to_string(), "[null, true]");
+        let err = attrmap_from_json("{\n  \"a\": 1,\n  \"b\" 2\n}").unwrap_err();
+        assert!(err.contains("line 3 column 7"), "{err}");
+    }
+
+    #[rstest]
+    #[case(Attribute::Integer(1), "bool", Attribute::Bool(true))]
+    #[case(Attribute::String("false".into()), "bool", Attribute::Bool(false))]
+    #[case(Attribute::Float(-2.7), "int", Attribute::Integer(-2))]
+    #[case(Attribute::Bool(true), "integer", Attribute::Integer(1))]
+    #[case(Attribute::String(" 12 ".into()), "int", Attribute::Integer(12))]
+    #[case(Attribute::Integer(3), "float", Attribute::Float(3.0))]
+    #[case(Attribute::String("1e3".into()), "float", Attribute::Float(1000.0))]
+    #[case(Attribute::Float(1.5), "string", Attribute::String("1.5".into()))]
+    #[case(Attribute::String("a".into()), "str", Attribute::String("a".into()))]
+    #[case(
+        Attribute::DateTime(DateTime::new(Date::new(2020, 1, 2), Time::new(3, 4, 5, 0), None)),
+        "date",
+        Attribute::Date(Date::new(2020, 1, 2))
+    )]
+    #[case(
+        Attribute::DateTime(DateTime::new(Date::new(2020, 1, 2), Time::new(3, 4, 5, 0), None)),
+        "time",
+        Attribute::Time(Time::new(3, 4, 5, 0))
+    )]
+    #[case(
+        Attribute::Date(Date::new(2020, 1, 2)),
+        "datetime",
+        Attribute::DateTime(DateTime::new(Date::new(2020, 1, 2), Time::default(), None))
+    )]
+    fn coerce_test(#[case] value: Attribute, #[case] target: &str, #[case] result: Attribute) {
+        assert_eq!(value.coerce(target), Ok(result));
+    }
+
+    #[cfg(feature = "parser")]
+    #[rstest]
+    fn coerce_parse_test() {
+        assert_eq!(
+            Attribute::String("2020-01-02".into()).coerce("date"),
+            Ok(Attribute::Date(Date::new(2020, 1, 2)))
+        );
+        assert!(Attribute::String("2020-01".into()).coerce("date").is_err());
+    }
+
+    #[rstest]
+    fn coerce_error_test() {
+        assert_eq!(
+            Attribute::String("yes".into()).coerce("bool"),
+            Err("Cannot cast `String` to `bool`".to_string())
+        );
+        assert!(Attribute::String("x".into()).coerce("int").is_err());
+        assert_eq!(
+            Attribute::Date(Date::new(2020, 1, 2)).coerce("time"),
+            Err("Cannot cast `Date` to `time`".to_string())
+        );
+        assert!(Attribute::Float(f64::NAN).coerce("int").is_err());
+        assert!(Attribute::Null
+            .coerce("array")
+            .unwrap_err()
+            .starts_with("Unknown type"));
+    }
+
+    #[rstest]
+    fn type_name_test() {
+        assert_eq!(type_name::<i64>(), "i64");
+        assert_eq!(type_name::<String>(), "String");
+        assert_eq!(type_name::<RString>(), "RString");
+        assert_eq!(type_name::<&String>(), "&String");
+        assert_eq!(type_name::<Option<&str>>(), "Option<&str>");
+        assert_eq!(type_name::<Vec<i64>>(), "Vec<i64>");
+        assert_eq!(type_name::<(i64, bool)>(), "(i64, bool)");
+        assert_eq!(type_name::<Vec<(i64, bool)>>(), "Vec<(i64, bool)>");
+        assert_eq!(
+            type_name::<HashMap<String, Vec<i64>>>(),
+            "HashMap<String, Vec<i64>>"
+        );
+        assert_eq!(type_name::<[String; 3]>(), "[String; 3]");
+        assert_eq!(
+            type_name::<(Date, (Time, PathBuf))>(),
+            "(Date, (Time, PathBuf))"
+        );
+    }
+
+    #[rstest]
+    #[cfg(all(feature = "chrono", feature = "parser"))]
+    fn today_test() {
+        use std::str::FromStr;
+        let today = Date::today(false);
+        assert_eq!(Date::from_str(&today.to_string()).unwrap(), today);
+        assert!(DateTime::now(true).offset.is_some());
+    }
+
+    #[rstest]
+    fn option_from_attr_test() {
+        use crate::functions::FunctionCtx;
+        let ctx = FunctionCtx::from_arg_kwarg(
+            vec![Attribute::Null],
+            [("y".to_string(), Attribute::Float(1.5))]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(ctx.arg_kwarg::<Option<f64>>(0, "x"), Some(Ok(None)));
+        assert_eq!(ctx.arg_kwarg::<Option<f64>>(1, "y"), Some(Ok(Some(1.5))));
+        assert_eq!(ctx.arg_kwarg::<Option<f64>>(1, "z"), None);
+        assert!(ctx.arg_kwarg::<Option<bool>>(1, "y").unwrap().is_err());
+        assert_eq!(Attribute::Null.to_string(), "null");
+    }
+
+    #[rstest]
+    fn attr_diff_test() {
+        fn map(items: Vec<(&str, Attribute)>) -> AttrMap {
+            items.into_iter().map(|(k, v)| (k.into(), v)).collect()
+        }
+        let before = map(vec![
+            ("a", Attribute::Integer(1)),
+            ("b", Attribute::Bool(true)),
+            ("c", Attribute::Integer(2)),
+            (
+                "t",
+                Attribute::Table(map(vec![
+                    ("x", Attribute::Integer(1)),
+                    ("y", Attribute::Integer(2)),
+                ])),
+            ),
+        ]);
+        let after = map(vec![
+            ("a", Attribute::Integer(1)),
+            ("c", Attribute::Float(2.5)),
+            ("d", Attribute::Null),
+            (
+                "t",
+                Attribute::Table(map(vec![
+                    ("x", Attribute::Integer(3)),
+                    ("z", Attribute::Bool(false)),
+                ])),
+            ),
+        ]);
+        let changes = attr_diff(&before, &after);
+        assert_eq!(
+            changes,
+            vec![
+                AttrChange::Removed {
+                    key: "b".into(),
+                    val: Attribute::Bool(true)
+                },
+                AttrChange::Changed {
+                    key: "c".into(),
+                    old: Attribute::Integer(2),
+                    new: Attribute::Float(2.5)
+                },
+                AttrChange::Added {
+                    key: "d".into(),
+                    val: Attribute::Null
+                },
+                AttrChange::Changed {
+                    key: "t.x".into(),
+                    old: Attribute::Integer(1),
+                    new: Attribute::Integer(3)
+                },
+                AttrChange::Removed {
+                    key: "t.y".into(),
+                    val: Attribute::Integer(2)
+                },
+                AttrChange::Added {
+                    key: "t.z".into(),
+                    val: Attribute::Bool(false)
+                },
+            ]
+        );
+        assert_eq!(changes[1].to_string(), "c=2 -> 2.5");
+        assert!(attr_diff(&after, &after).is_empty());
+    }
 }
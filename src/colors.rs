@@ -0,0 +1,72 @@
+//! Colors for the terminal output
+//!
+//! With the `pretty` feature (default) this is the [`colored`] crate's
+//! `Colorize` trait, without it the same methods are provided by a
+//! trait that returns the text unchanged, so the presentation code
+//! (`to_colored_string`, error messages, help, etc.) works in both
+//! cases without ANSI escapes in the plain one.
+
+#[cfg(feature = "pretty")]
+pub use colored::Colorize;
+
+#[cfg(not(feature = "pretty"))]
+pub use plain::Colorize;
+
+#[cfg(not(feature = "pretty"))]
+mod plain {
+    macro_rules! plain_colors {
+        ($($name: ident),*) => {
+            /// Plain fallback for `colored::Colorize`, the methods
+            /// return the text as it is
+            pub trait Colorize {
+                $(fn $name(self) -> String;)*
+                fn truecolor(self, r: u8, g: u8, b: u8) -> String;
+            }
+
+            impl Colorize for &str {
+                $(fn $name(self) -> String {
+                    self.to_string()
+                })*
+                fn truecolor(self, _r: u8, _g: u8, _b: u8) -> String {
+                    self.to_string()
+                }
+            }
+        };
+    }
+
+    plain_colors!(
+        black,
+        red,
+        green,
+        yellow,
+        blue,
+        magenta,
+        purple,
+        cyan,
+        white,
+        bright_black,
+        bright_red,
+        bright_green,
+        bright_yellow,
+        bright_blue,
+        bright_magenta,
+        bright_cyan,
+        bright_white,
+        bold,
+        dimmed,
+        italic,
+        underline
+    );
+}
+
+#[cfg(all(test, not(feature = "pretty")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_colors_test() {
+        assert_eq!("a".red(), "a");
+        assert_eq!(String::from("b").blue().bold(), "b");
+        assert_eq!("c".truecolor(1, 2, 3), "c");
+    }
+}
@@ -1,5 +1,6 @@
 #![allow(clippy::module_inception)]
-use crate::attrs::{AttrMap, AttrSlice};
+use crate::attrs::{table_key, AttrMap, AttrSlice};
+use crate::colors::Colorize;
 use crate::network::StrPath;
 use crate::plugins::{load_library_safe, NadiPlugin};
 use crate::prelude::*;
@@ -15,13 +16,17 @@ use abi_stable::{
     },
     StableAbi,
 };
-use colored::Colorize;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::Path;
 
 /// Return values for Nadi Functions
+///
+/// Functions with names ending in `_attrs` can return an
+/// [`Attribute::Table`] to set multiple attributes at once, each key
+/// is set as a separate attribute when the task has no output
+/// attribute (e.g. `node compute_attrs()`).
 #[repr(C)]
 #[derive(StableAbi, Default)]
 pub enum FunctionRet {
@@ -150,6 +155,59 @@ impl ToString for FuncArg {
     }
 }
 
+impl FuncArg {
+    /// Coerce the value to the declared type of the argument when
+    /// the conversion is lossless: `"true"`/`"false"` to bool,
+    /// numeric strings and whole floats to integer, and numeric
+    /// strings and integers to float. It is an error if the value
+    /// for these types can't be converted without loss, values for
+    /// the other types are returned as they are.
+    pub fn coerce(&self, value: &Attribute) -> Result<Attribute, String> {
+        let ty: String = self.ty.chars().filter(|c| !c.is_whitespace()).collect();
+        let ty = ty.trim_start_matches('&');
+        let ty = ty
+            .strip_prefix("Option<")
+            .and_then(|t| t.strip_suffix('>'))
+            .unwrap_or(ty);
+        let val = match (ty, value) {
+            ("bool" | "Bool", Attribute::Bool(_))
+            | ("i64" | "Integer", Attribute::Integer(_))
+            | ("f64" | "Float", Attribute::Float(_))
+            | (_, Attribute::Null) => Some(value.clone()),
+            ("bool" | "Bool", Attribute::String(s)) => match s.as_str() {
+                "true" => Some(Attribute::Bool(true)),
+                "false" => Some(Attribute::Bool(false)),
+                _ => None,
+            },
+            ("i64" | "Integer", Attribute::String(s)) => {
+                s.trim().parse().ok().map(Attribute::Integer)
+            }
+            // i64::MAX as f64 is 2^63, which is out of range
+            ("i64" | "Integer", Attribute::Float(f))
+                if f.fract() == 0.0 && *f >= i64::MIN as f64 && *f < i64::MAX as f64 =>
+            {
+                Some(Attribute::Integer(*f as i64))
+            }
+            ("f64" | "Float", Attribute::String(s)) => s.trim().parse().ok().map(Attribute::Float),
+            // integers up to 2^53 are exact in f64
+            ("f64" | "Float", Attribute::Integer(i)) if i.unsigned_abs() <= 1 << 53 => {
+                Some(Attribute::Float(*i as f64))
+            }
+            ("bool" | "Bool" | "i64" | "Integer" | "f64" | "Float", _) => None,
+            _ => Some(value.clone()),
+        };
+        val.ok_or_else(|| {
+            format!(
+                "Argument `{}` should be `{}`, got {} `{}`",
+                self.name,
+                self.ty,
+                value.type_name(),
+                value.to_string()
+            )
+        })
+    }
+}
+
 #[repr(C)]
 #[derive(StableAbi)]
 pub enum FuncArgType {
@@ -223,23 +281,27 @@ pub struct PluginFunctions {
     network: RVec<RString>,
 }
 
+/// The function names are kept sorted so the listings and generated
+/// docs have a stable order
 impl PluginFunctions {
     pub fn with_network(mut self, func: RString) -> Self {
-        self.network.push(func);
+        self.push_network(func);
         self
     }
 
     pub fn with_node(mut self, func: RString) -> Self {
-        self.node.push(func);
+        self.push_node(func);
         self
     }
 
     pub fn push_network(&mut self, func: RString) {
-        self.network.push(func);
+        let pos = self.network.binary_search(&func).unwrap_or_else(|p| p);
+        self.network.insert(pos, func);
     }
 
     pub fn push_node(&mut self, func: RString) {
-        self.node.push(func);
+        let pos = self.node.binary_search(&func).unwrap_or_else(|p| p);
+        self.node.insert(pos, func);
     }
 
     pub fn network(&self) -> &RVec<RString> {
@@ -251,6 +313,23 @@ impl PluginFunctions {
     }
 }
 
+/// Kind of the function, as the node and network functions are
+/// registered separately
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FunctionKind {
+    Node,
+    Network,
+}
+
+impl std::fmt::Display for FunctionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Node => write!(f, "Node"),
+            Self::Network => write!(f, "Network"),
+        }
+    }
+}
+
 // TODO: add environmental variables, like verbose, progress, debug,
 // etc. that all functions can read (passed along with args, kwargs to
 // all functions)
@@ -262,6 +341,46 @@ pub struct NadiFunctions {
     network: RHashMap<RString, NetworkFunctionBox>,
     network_alias: RHashMap<RString, RString>,
     plugins: RHashMap<RString, PluginFunctions>,
+    /// Plugins and the full names of the functions removed with
+    /// [`NadiFunctions::disable`]
+    disabled: RVec<RString>,
+}
+
+/// Internal plugins with the functions that run shell commands
+pub const SHELL_PLUGINS: [&str; 1] = ["command"];
+
+/// Options for the functions registered in [`NadiFunctions::new_with`]
+/// or [`TaskContext::new_with`](crate::tasks::TaskContext::new_with)
+///
+/// ```
+/// # use nadi_core::functions::{NadiFunctions, RegistrationOptions};
+/// let opts = RegistrationOptions::default().no_shell().exclude("debug.echo");
+/// let funcs = NadiFunctions::new_with(&opts);
+/// assert!(funcs.network("command.command").is_none());
+/// assert!(!funcs.plugins().contains_key("command"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RegistrationOptions {
+    exclude: Vec<String>,
+}
+
+impl RegistrationOptions {
+    /// Exclude all the functions of a plugin (e.g. `command`), or a
+    /// single function with its full name (e.g. `command.parallel`)
+    pub fn exclude(mut self, name: &str) -> Self {
+        self.exclude.push(name.to_string());
+        self
+    }
+
+    /// Exclude the plugins with the functions that run shell
+    /// commands, see [`SHELL_PLUGINS`]
+    pub fn no_shell(self) -> Self {
+        SHELL_PLUGINS.iter().fold(self, |opts, p| opts.exclude(p))
+    }
+
+    pub fn excluded(&self) -> &[String] {
+        &self.exclude
+    }
 }
 
 impl NadiFunctions {
@@ -275,6 +394,94 @@ impl NadiFunctions {
         funcs
     }
 
+    /// Register the internal and external plugins without the
+    /// functions excluded in the options
+    ///
+    /// The excluded functions are removed after all the plugins are
+    /// loaded, so the external plugins cannot provide them either.
+    pub fn new_with(opts: &RegistrationOptions) -> Self {
+        let mut funcs = Self::new();
+        for name in opts.excluded() {
+            funcs.disable(name);
+        }
+        funcs
+    }
+
+    /// Remove the plugin, or the function with its full name
+    /// (`plugin.function`)
+    ///
+    /// Returns the given name followed by the full names of the
+    /// removed functions of the plugin. They are also kept in the
+    /// functions, so the lookups with [`NadiFunctions::try_node`] and
+    /// [`NadiFunctions::try_network`] can tell the disabled functions
+    /// apart from the missing ones.
+    pub fn disable(&mut self, name: &str) -> Vec<String> {
+        let disabled = self.remove(name);
+        self.disabled
+            .extend(disabled.iter().map(|f| RString::from(f.as_str())));
+        disabled
+    }
+
+    /// Check if the plugin or function (by its full name, or the
+    /// function name) has been disabled
+    pub fn is_disabled(&self, name: &str) -> bool {
+        self.disabled.iter().any(|f| {
+            f == name
+                || f.split_once('.')
+                    .map(|(_, func)| func == name)
+                    .unwrap_or(false)
+        })
+    }
+
+    /// Error message for the function that is not available
+    pub fn unavailable_error(&self, kind: FunctionKind, name: &str) -> String {
+        if self.is_disabled(name) {
+            format!("{kind} Function {name} is disabled")
+        } else {
+            format!("{kind} Function {name} not found")
+        }
+    }
+
+    fn remove(&mut self, name: &str) -> Vec<String> {
+        let fullnames: Vec<RString> = match name.split_once('.') {
+            Some((plugin, func)) => {
+                if let Some(p) = self.plugins.get_mut(plugin) {
+                    p.node.retain(|f| f.as_str() != func);
+                    p.network.retain(|f| f.as_str() != func);
+                }
+                vec![name.into()]
+            }
+            None => match self.plugins.remove(name) {
+                RSome(p) => p
+                    .node
+                    .iter()
+                    .chain(p.network.iter())
+                    .map(|f| RString::from(format!("{name}.{f}")))
+                    .collect(),
+                RNone => vec![],
+            },
+        };
+        let mut disabled = vec![name.to_string()];
+        for fullname in fullnames {
+            self.node.remove(&fullname);
+            self.network.remove(&fullname);
+            for aliases in [&mut self.node_alias, &mut self.network_alias] {
+                let keys: Vec<RString> = aliases
+                    .iter()
+                    .filter(|Tuple2(_, v)| v.as_str() == fullname.as_str())
+                    .map(|Tuple2(k, _)| k.clone())
+                    .collect();
+                for k in keys {
+                    aliases.remove(&k);
+                }
+            }
+            if fullname.as_str() != name {
+                disabled.push(fullname.into());
+            }
+        }
+        disabled
+    }
+
     pub fn register_network_function(&mut self, prefix: &str, func: NetworkFunctionBox) {
         let name = func.name();
         let fullname = RString::from(format!("{}.{}", prefix, name));
@@ -314,13 +521,69 @@ impl NadiFunctions {
         };
     }
 
+    /// Register a custom alias for a function, the `fullname` can be
+    /// the full name of the function (`plugin.function`) or another
+    /// alias
+    ///
+    /// # Errors
+    /// Errors if the alias contains a `.`, as those are resolved as
+    /// full names, or if the target function doesn't exist.
+    pub fn add_alias(
+        &mut self,
+        alias: &str,
+        fullname: &str,
+        kind: FunctionKind,
+    ) -> Result<(), String> {
+        if alias.contains('.') {
+            return Err(format!("Alias {alias} can not contain `.`"));
+        }
+        let aliases = match kind {
+            FunctionKind::Node => &self.node_alias,
+            FunctionKind::Network => &self.network_alias,
+        };
+        let target = if fullname.contains('.') {
+            RString::from(fullname)
+        } else {
+            match aliases.get(fullname) {
+                Some(f) => f.clone(),
+                None => return Err(format!("{kind} function {fullname} not found")),
+            }
+        };
+        let exists = match kind {
+            FunctionKind::Node => self.node.contains_key(&target),
+            FunctionKind::Network => self.network.contains_key(&target),
+        };
+        if !exists {
+            return Err(format!("{kind} function {fullname} not found"));
+        }
+        match kind {
+            FunctionKind::Node => self.node_alias.insert(alias.into(), target),
+            FunctionKind::Network => self.network_alias.insert(alias.into(), target),
+        };
+        Ok(())
+    }
+
+    /// Register the functions of the plugin and then call its
+    /// [`NadiPlugin::on_load`] hook
+    pub fn register_plugin<P: NadiPlugin + ?Sized>(&mut self, plugin: &P) {
+        plugin.register(self);
+        plugin.on_load(self);
+    }
+
+    /// Call the [`NadiPlugin::on_unload`] hook of the plugin and then
+    /// remove all of its functions
+    pub fn unload_plugin<P: NadiPlugin + ?Sized>(&mut self, plugin: &P) {
+        plugin.on_unload();
+        self.remove(&plugin.name());
+    }
+
     pub fn load_plugins(&mut self) -> anyhow::Result<()> {
         if let Ok(plugin_dirs) = std::env::var("NADI_PLUGIN_DIRS") {
             for pdir in plugin_dirs.split(':') {
                 if let Ok(dir) = std::fs::read_dir(pdir) {
                     for path in dir {
                         if let Some(lib) = load_library_safe(&path?.path()) {
-                            lib.register(self);
+                            self.register_plugin(&lib);
                         }
                     }
                 }
@@ -351,6 +614,47 @@ impl NadiFunctions {
         &self.plugins
     }
 
+    /// Plugins sorted by their names, the functions in each plugin
+    /// are also sorted, see [`PluginFunctions`]
+    pub fn plugins_sorted(&self) -> Vec<(&RString, &PluginFunctions)> {
+        let mut plugins: Vec<(&RString, &PluginFunctions)> =
+            self.plugins.iter().map(|Tuple2(k, v)| (k, v)).collect();
+        plugins.sort_by(|a, b| a.0.cmp(b.0));
+        plugins
+    }
+
+    /// List the functions in the plugin with their short help
+    ///
+    /// The plugins only register node and network functions, there
+    /// are no env functions to list; the values set with `env` are
+    /// variables or partial functions in the
+    /// [`TaskContext`](crate::tasks::TaskContext), not in a plugin.
+    pub fn plugin_help(&self, plugin: &str) -> Option<String> {
+        let funcs = self.plugins.get(plugin)?;
+        let mut help = String::new();
+        for func in funcs.node() {
+            if let Some(f) = self.node(&format!("{plugin}.{func}")) {
+                help.push_str(&format!(
+                    "node {}.{}: {}\n",
+                    plugin.red(),
+                    func.as_str().blue(),
+                    f.short_help()
+                ));
+            }
+        }
+        for func in funcs.network() {
+            if let Some(f) = self.network(&format!("{plugin}.{func}")) {
+                help.push_str(&format!(
+                    "network {}.{}: {}\n",
+                    plugin.red(),
+                    func.as_str().blue(),
+                    f.short_help()
+                ));
+            }
+        }
+        Some(help)
+    }
+
     pub fn plugins_doc<P: AsRef<Path>>(&self, outdir: P) -> anyhow::Result<()> {
         let mut doc = BufWriter::new(File::create(outdir.as_ref().join("index.md"))?);
         writeln!(doc, "# All Plugin Functions")?;
@@ -376,7 +680,7 @@ impl NadiFunctions {
             }
         }
 
-        for Tuple2(plug, funcs) in self.plugins() {
+        for (plug, funcs) in self.plugins_sorted() {
             let mut doc = BufWriter::new(File::create(
                 outdir.as_ref().join(plug.as_str()).with_extension("md"),
             )?);
@@ -421,8 +725,20 @@ impl NadiFunctions {
     }
 
     pub fn list_functions(&self) {
-        fn print_func(p: &RString, t: &str, f: &RString, sig: RString) {
-            print!("{} {}.{}", t, p.as_str().red(), f.as_str().blue(),);
+        print!("{}", self.list_functions_filtered(None, None));
+    }
+
+    /// Listing of the functions with their signatures like
+    /// [`NadiFunctions::list_functions`], only including the functions
+    /// of the given `kind` and/or the ones with the `pattern` in their
+    /// full name (`plugin.function`, case-insensitive)
+    pub fn list_functions_filtered(
+        &self,
+        kind: Option<FunctionKind>,
+        pattern: Option<&str>,
+    ) -> String {
+        fn format_func(p: &RString, t: &str, f: &RString, sig: RString) -> String {
+            let name = format!("{} {}.{}", t, p.as_str().red(), f.as_str().blue(),);
             let args: Vec<String> = sig
                 .trim_start_matches('(')
                 .trim_end_matches(')')
@@ -442,28 +758,41 @@ impl NadiFunctions {
                 })
                 .collect();
             if args.len() < 3 {
-                println!("({})", args.join(", "));
+                format!("{name}({})\n", args.join(", "))
             } else {
-                println!("(\n    {}\n)", args.join(",\n    "));
+                format!("{name}(\n    {}\n)\n", args.join(",\n    "))
             }
         }
 
-        for Tuple2(plug, funcs) in self.plugins() {
-            if !funcs.node().is_empty() {
+        let pattern = pattern.map(|p| p.to_lowercase());
+        let matches = |fname: &str| {
+            pattern
+                .as_ref()
+                .map(|p| fname.to_lowercase().contains(p.as_str()))
+                .unwrap_or(true)
+        };
+        let mut list = String::new();
+        for (plug, funcs) in self.plugins_sorted() {
+            if kind != Some(FunctionKind::Network) {
                 for func in funcs.node() {
                     let fname = format!("{plug}.{func}");
-                    let func_obj = self.node(&fname).expect("Func Should Exist");
-                    print_func(plug, "node", func, func_obj.signature());
+                    if matches(&fname) {
+                        let func_obj = self.node(&fname).expect("Func Should Exist");
+                        list.push_str(&format_func(plug, "node", func, func_obj.signature()));
+                    }
                 }
             }
-            if !funcs.network().is_empty() {
+            if kind != Some(FunctionKind::Node) {
                 for func in funcs.network() {
                     let fname = format!("{plug}.{func}");
-                    let func_obj = self.network(&fname).expect("Func Should Exist");
-                    print_func(plug, "network", func, func_obj.signature());
+                    if matches(&fname) {
+                        let func_obj = self.network(&fname).expect("Func Should Exist");
+                        list.push_str(&format_func(plug, "network", func, func_obj.signature()));
+                    }
                 }
             }
         }
+        list
     }
 
     pub fn list_functions_md(&self, link: bool) -> (String, String) {
@@ -480,7 +809,13 @@ impl NadiFunctions {
         } else {
             |p: &str, _t: &str, n: &str, h: &str| vec![p.to_string(), n.to_string(), h.to_string()]
         };
-        for Tuple2(func, fobj) in &self.node {
+        let mut node: Vec<(&RString, &NodeFunctionBox)> =
+            self.node.iter().map(|Tuple2(k, v)| (k, v)).collect();
+        node.sort_by(|a, b| a.0.cmp(b.0));
+        let mut network: Vec<(&RString, &NetworkFunctionBox)> =
+            self.network.iter().map(|Tuple2(k, v)| (k, v)).collect();
+        network.sort_by(|a, b| a.0.cmp(b.0));
+        for (func, fobj) in node {
             let (plug, name) = func.split_once('.').unwrap_or(("null", func.as_str()));
             node_functions.push(fname(
                 plug,
@@ -489,7 +824,7 @@ impl NadiFunctions {
                 fobj.help().lines().next().unwrap_or_default(),
             ));
         }
-        for Tuple2(func, fobj) in &self.network {
+        for (func, fobj) in network {
             let (plug, name) = func.split_once('.').unwrap_or(("null", func.as_str()));
             net_functions.push(fname(
                 plug,
@@ -558,6 +893,20 @@ impl NadiFunctions {
         }
     }
 
+    /// Same as [`NadiFunctions::node`], with the error saying if the
+    /// function is disabled or not found
+    pub fn try_node(&self, func: &str) -> Result<&NodeFunctionBox, String> {
+        self.node(func)
+            .ok_or_else(|| self.unavailable_error(FunctionKind::Node, func))
+    }
+
+    /// Same as [`NadiFunctions::network`], with the error saying if
+    /// the function is disabled or not found
+    pub fn try_network(&self, func: &str) -> Result<&NetworkFunctionBox, String> {
+        self.network(func)
+            .ok_or_else(|| self.unavailable_error(FunctionKind::Network, func))
+    }
+
     pub fn help(&self, func: &str) -> Option<String> {
         // node and network function might have same name
         self.help_network(func).or_else(|| self.help_node(func))
@@ -570,6 +919,22 @@ impl NadiFunctions {
     pub fn help_network(&self, func: &str) -> Option<String> {
         self.network(func).map(|f| f.help().into_string())
     }
+    /// Plain text help for the function, without the colors and
+    /// markdown rendering, if both node and network function exist
+    /// with the name, help for both are included
+    pub fn help_plain(&self, func: &str) -> Option<String> {
+        let node = self
+            .node(func)
+            .map(|f| plain_help("node", func, &f.signature(), &f.args(), &f.help()));
+        let network = self
+            .network(func)
+            .map(|f| plain_help("network", func, &f.signature(), &f.args(), &f.help()));
+        match (node, network) {
+            (Some(a), Some(b)) => Some(format!("{a}\n{b}")),
+            (a, b) => a.or(b),
+        }
+    }
+
     pub fn code(&self, func: &str) -> Option<String> {
         // node and network function might have same name
         self.code_network(func).or_else(|| self.code_node(func))
@@ -584,6 +949,38 @@ impl NadiFunctions {
     }
 }
 
+fn plain_help(prefix: &str, name: &str, signature: &str, args: &[FuncArg], help: &str) -> String {
+    let mut help = help.trim().split('\n');
+    let short_help = help.next().unwrap_or("No Help");
+    let desc = help.collect::<Vec<&str>>().join("\n");
+    let mut txt = format!("{prefix} {name}({signature})\n{short_help}\n");
+    if !args.is_empty() {
+        txt.push_str("\nArguments:\n");
+        for arg in args {
+            let line = match &arg.category {
+                FuncArgType::Arg => format!("- {}: {} {}", arg.name, arg.ty, arg.help),
+                FuncArgType::OptArg => {
+                    format!("- {}: {} [optional] {}", arg.name, arg.ty, arg.help)
+                }
+                FuncArgType::DefArg(v) => {
+                    format!("- {}: {} [def = {}] {}", arg.name, arg.ty, v, arg.help)
+                }
+                FuncArgType::Args => format!("- *{} {}", arg.name, arg.help),
+                FuncArgType::KwArgs => format!("- **{} {}", arg.name, arg.help),
+            };
+            txt.push_str(line.trim_end());
+            txt.push('\n');
+        }
+    }
+    let desc = desc.trim();
+    if !desc.is_empty() {
+        txt.push('\n');
+        txt.push_str(desc);
+        txt.push('\n');
+    }
+    txt
+}
+
 #[repr(C)]
 #[derive(StableAbi, Default, Debug, PartialEq)]
 pub struct FunctionCtx {
@@ -649,6 +1046,18 @@ impl FunctionCtx {
         self.args.get(ind)
     }
 
+    /// All the positional arguments converted to `T`, see
+    /// [`attrs_as`](crate::attrs::attrs_as)
+    pub fn args_as<T: FromAttribute>(&self) -> Result<Vec<T>, String> {
+        crate::attrs::attrs_as(&self.args)
+    }
+
+    /// All the positional arguments converted to `T` with the relaxed
+    /// conversion
+    pub fn args_as_relaxed<T: FromAttributeRelaxed>(&self) -> Result<Vec<T>, String> {
+        crate::attrs::attrs_as_relaxed(&self.args)
+    }
+
     pub fn kwargs(&self) -> &AttrMap {
         &self.kwargs
     }
@@ -657,6 +1066,27 @@ impl FunctionCtx {
         self.kwargs.get(name)
     }
 
+    /// Coerce the supplied args and kwargs to the types declared in
+    /// the function signature with [`FuncArg::coerce`], so the
+    /// functions get consistent values irrespective of how they
+    /// extract them
+    pub fn coerce(&mut self, args: &[FuncArg]) -> Result<(), String> {
+        for (ind, arg) in args.iter().enumerate() {
+            if matches!(arg.category, FuncArgType::Args | FuncArgType::KwArgs) {
+                break;
+            }
+            let value = match self.kwargs.get_mut(arg.name.as_str()) {
+                Some(v) => v,
+                None => match self.args.get_mut(ind) {
+                    Some(v) => v,
+                    None => continue,
+                },
+            };
+            *value = arg.coerce(value)?;
+        }
+        Ok(())
+    }
+
     pub fn arg_kwarg<P: FromAttribute>(&self, ind: usize, name: &str) -> Option<Result<P, String>> {
         self.kwarg(name).or_else(|| self.arg(ind)).map(|arg| {
             match FromAttribute::try_from_attr(arg) {
@@ -671,6 +1101,35 @@ impl FunctionCtx {
         })
     }
 
+    /// Nodes selected by the propagation given as a string in the
+    /// argument at `ind` or keyword argument `name`, all the nodes in
+    /// sequential order are selected when it is not given
+    ///
+    /// The propagation is resolved only once here with
+    /// [`Network::nodes_propagation`], so the network functions can
+    /// iterate over the selected nodes consistently instead of
+    /// resolving the propagation on their own. The returned [`Node`]s
+    /// are shared references to the nodes in the network and not
+    /// copies: changes done through them are reflected in the
+    /// network, and they are not updated if the network is modified
+    /// (e.g. nodes removed) after this call, so resolve them again
+    /// after such changes.
+    #[cfg(feature = "parser")]
+    pub fn propagation_nodes(
+        &self,
+        net: &Network,
+        ind: usize,
+        name: &str,
+    ) -> Result<Vec<Node>, String> {
+        let prop = match self.arg_kwarg::<String>(ind, name) {
+            Some(p) => {
+                <Propagation as std::str::FromStr>::from_str(&p?).map_err(|e| e.to_string())?
+            }
+            None => Propagation::Sequential,
+        };
+        net.nodes_propagation(&prop)
+    }
+
     pub fn arg_kwarg_relaxed<P: FromAttributeRelaxed>(
         &self,
         ind: usize,
@@ -690,31 +1149,148 @@ impl FunctionCtx {
     }
 }
 
-// TODO maybe add attr = "smth"; attr > 1.0 etc as conditions
-// Maybe we can't because attribute name can be string or variable for now
 #[repr(C)]
 #[derive(StableAbi, Debug, Clone, PartialEq)]
 pub enum Condition {
     Single(RString),
+    /// Literal `true` or `false`
+    Literal(bool),
+    /// Attribute compared with a value (`elevation > 100`)
+    Compare(RString, CompareOp, Attribute),
     Not(RBox<Condition>),
     And(RBox<Condition>, RBox<Condition>),
     Or(RBox<Condition>, RBox<Condition>),
 }
 
+#[repr(C)]
+#[derive(StableAbi, Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl CompareOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterEqual => ">=",
+        }
+    }
+
+    /// Compare the values, `None` if they can't be ordered
+    ///
+    /// Integers and floats are compared as numbers, strings, bools,
+    /// dates and times with the same type. Any two values can be
+    /// checked for (in)equality, the values of different types are
+    /// not equal.
+    pub fn compare(&self, a: &Attribute, b: &Attribute) -> Option<bool> {
+        let ord = match (a, b) {
+            (Attribute::Integer(x), Attribute::Integer(y)) => x.partial_cmp(y),
+            (
+                Attribute::Integer(_) | Attribute::Float(_),
+                Attribute::Integer(_) | Attribute::Float(_),
+            ) => f64::from_attr_relaxed(a)?.partial_cmp(&f64::from_attr_relaxed(b)?),
+            (Attribute::String(x), Attribute::String(y)) => x.partial_cmp(y),
+            (Attribute::Bool(x), Attribute::Bool(y)) => x.partial_cmp(y),
+            (Attribute::Date(x), Attribute::Date(y)) => {
+                (x.year, x.month, x.day).partial_cmp(&(y.year, y.month, y.day))
+            }
+            (Attribute::Time(x), Attribute::Time(y)) => (x.hour, x.min, x.sec, x.nanosecond)
+                .partial_cmp(&(y.hour, y.min, y.sec, y.nanosecond)),
+            _ => None,
+        };
+        match self {
+            Self::Equal => Some(ord.map(|o| o.is_eq()).unwrap_or(a == b)),
+            Self::NotEqual => Some(ord.map(|o| o.is_ne()).unwrap_or(a != b)),
+            Self::Less => ord.map(|o| o.is_lt()),
+            Self::LessEqual => ord.map(|o| o.is_le()),
+            Self::Greater => ord.map(|o| o.is_gt()),
+            Self::GreaterEqual => ord.map(|o| o.is_ge()),
+        }
+    }
+}
+
 impl NodeInner {
     /// check if the condition is true
     pub fn check(&self, cond: &Condition) -> bool {
         match cond {
             Condition::Single(v) => self.try_attr_relaxed(v.as_str()).unwrap_or(false),
+            Condition::Literal(b) => *b,
+            Condition::Compare(v, op, val) => self
+                .attr(v.as_str())
+                .and_then(|a| op.compare(a, val))
+                .unwrap_or(false),
             Condition::Not(v) => !self.check(v),
             Condition::And(a, b) => self.check(a) & self.check(b),
             Condition::Or(a, b) => self.check(a) | self.check(b),
         }
     }
+
+    /// Evaluate the condition like [`NodeInner::check`] along with a
+    /// trace of the values of each sub-condition
+    ///
+    /// Attributes show their value in the trace (`x(1) = true`), or
+    /// `missing` if the node doesn't have them; the compound
+    /// conditions are wrapped in parenthesis when nested, for example
+    /// `large(true) = true & (!(dam(true) = true) = false) => false`.
+    pub fn explain(&self, cond: &Condition) -> (bool, String) {
+        let nested = |c: &Condition, (b, t): (bool, String)| match c {
+            Condition::Single(_) | Condition::Literal(_) | Condition::Compare(..) => (b, t),
+            _ => (b, format!("({t})")),
+        };
+        match cond {
+            Condition::Single(v) => match self.attr(v.as_str()) {
+                Some(a) => {
+                    let b = self.try_attr_relaxed(v.as_str()).unwrap_or(false);
+                    (b, format!("{v}({}) = {b}", a.to_string()))
+                }
+                None => (false, format!("{v}(missing) = false")),
+            },
+            Condition::Literal(b) => (*b, b.to_string()),
+            Condition::Compare(v, op, val) => {
+                let val = val.to_string();
+                match self.attr(v.as_str()) {
+                    Some(a) => {
+                        let b = self.check(cond);
+                        (
+                            b,
+                            format!("{v}({}) {} {val} = {b}", a.to_string(), op.symbol()),
+                        )
+                    }
+                    None => (false, format!("{v}(missing) {} {val} = false", op.symbol())),
+                }
+            }
+            Condition::Not(v) => {
+                let (b, t) = self.explain(v);
+                (!b, format!("!({t}) = {}", !b))
+            }
+            Condition::And(a, b) => {
+                let (a, ta) = nested(a, self.explain(a));
+                let (b, tb) = nested(b, self.explain(b));
+                (a & b, format!("{ta} & {tb} => {}", a & b))
+            }
+            Condition::Or(a, b) => {
+                let (a, ta) = nested(a, self.explain(a));
+                let (b, tb) = nested(b, self.explain(b));
+                (a | b, format!("{ta} | {tb} => {}", a | b))
+            }
+        }
+    }
+
     /// check if condition is true only if attributes exist
     pub fn check_strict(&self, cond: &Condition) -> Result<bool, String> {
         match cond {
             Condition::Single(v) => self.try_attr_relaxed(v.as_str()),
+            Condition::Literal(b) => Ok(*b),
+            Condition::Compare(v, op, val) => self.check_compare(v, *op, val, false),
             Condition::Not(v) => self.check_strict(v).map(|b| !b),
             Condition::And(a, b) => {
                 let a = self.check_strict(a)?;
@@ -732,6 +1308,8 @@ impl NodeInner {
     pub fn check_super_strict(&self, cond: &Condition) -> Result<bool, String> {
         match cond {
             Condition::Single(v) => self.try_attr(v.as_str()),
+            Condition::Literal(b) => Ok(*b),
+            Condition::Compare(v, op, val) => self.check_compare(v, *op, val, true),
             Condition::Not(v) => self.check_super_strict(v).map(|b| !b),
             Condition::And(a, b) => {
                 let a = self.check_super_strict(a)?;
@@ -745,19 +1323,82 @@ impl NodeInner {
             }
         }
     }
+
+    /// Comparison for the strict checks, the attribute should be
+    /// present and comparable with the value; with `same_type` the
+    /// integers and floats are not compared with each other
+    fn check_compare(
+        &self,
+        name: &str,
+        op: CompareOp,
+        val: &Attribute,
+        same_type: bool,
+    ) -> Result<bool, String> {
+        let a = self
+            .attr(name)
+            .ok_or_else(|| format!("Attribute Error: Attribute {name} not found in Node"))?;
+        let incomparable = || {
+            format!(
+                "Incorrect Type: `{}` cannot be compared with `{}`",
+                a.type_name(),
+                val.type_name()
+            )
+        };
+        if same_type && std::mem::discriminant(a) != std::mem::discriminant(val) {
+            return Err(incomparable());
+        }
+        op.compare(a, val).ok_or_else(incomparable)
+    }
 }
 
 impl Condition {
+    /// Simplify the condition with basic boolean algebra: double
+    /// negation (`!!x` → `x`), and the literals in `&`/`|` (`x &
+    /// true` → `x`, `x | false` → `x`, `x & false` → `false`, `x |
+    /// true` → `true`, `!true` → `false`)
+    ///
+    /// The sub-conditions removed by the simplification are not
+    /// evaluated anymore, so the strict checks will not error for
+    /// the missing attributes in them.
+    pub fn simplify(&self) -> Condition {
+        match self {
+            Condition::Single(_) | Condition::Literal(_) | Condition::Compare(..) => self.clone(),
+            Condition::Not(v) => match v.simplify() {
+                Condition::Not(v) => RBox::into_inner(v),
+                Condition::Literal(b) => Condition::Literal(!b),
+                v => Condition::Not(RBox::new(v)),
+            },
+            Condition::And(a, b) => match (a.simplify(), b.simplify()) {
+                (Condition::Literal(true), c) | (c, Condition::Literal(true)) => c,
+                (Condition::Literal(false), _) | (_, Condition::Literal(false)) => {
+                    Condition::Literal(false)
+                }
+                (a, b) => Condition::And(RBox::new(a), RBox::new(b)),
+            },
+            Condition::Or(a, b) => match (a.simplify(), b.simplify()) {
+                (Condition::Literal(false), c) | (c, Condition::Literal(false)) => c,
+                (Condition::Literal(true), _) | (_, Condition::Literal(true)) => {
+                    Condition::Literal(true)
+                }
+                (a, b) => Condition::Or(RBox::new(a), RBox::new(b)),
+            },
+        }
+    }
+
     fn maybe_paren(&self) -> String {
         match self {
-            Condition::Single(_) => self.to_string(),
+            Condition::Single(_) | Condition::Literal(_) | Condition::Compare(..) => {
+                self.to_string()
+            }
             _ => format!("({})", self.to_string()),
         }
     }
 
     fn maybe_paren_colored(&self) -> String {
         match self {
-            Condition::Single(_) => self.to_colored_string(),
+            Condition::Single(_) | Condition::Literal(_) | Condition::Compare(..) => {
+                self.to_colored_string()
+            }
             _ => format!("{}{}{}", "(".red(), self.to_colored_string(), ")".red()),
         }
     }
@@ -765,6 +1406,10 @@ impl Condition {
     pub fn to_colored_string(&self) -> String {
         match self {
             Condition::Single(v) => v.to_string(),
+            Condition::Literal(b) => b.to_string().magenta().to_string(),
+            Condition::Compare(v, op, val) => {
+                format!("{v} {} {}", op.symbol().yellow(), val.to_colored_string())
+            }
             Condition::Not(v) => format!("{}{}", "!".yellow(), v.maybe_paren_colored()),
             Condition::And(a, b) => {
                 format!(
@@ -790,6 +1435,8 @@ impl ToString for Condition {
     fn to_string(&self) -> String {
         match self {
             Condition::Single(v) => v.to_string(),
+            Condition::Literal(b) => b.to_string(),
+            Condition::Compare(v, op, val) => format!("{v} {} {}", op.symbol(), val.to_string()),
             Condition::Not(v) => format!("!{}", v.maybe_paren()),
             Condition::And(a, b) => format!("{} & {}", a.maybe_paren(), b.maybe_paren()),
             Condition::Or(a, b) => format!("{} | {}", a.maybe_paren(), b.maybe_paren()),
@@ -810,6 +1457,20 @@ pub enum Propagation {
     ConditionalSuperStrict(Condition),
     List(RVec<RString>),
     Path(StrPath),
+    /// Nodes sorted by the numeric attribute (name, ascending), the
+    /// nodes without the attribute are at the end
+    SortedBy(RString, bool),
+    /// The node and all the nodes upstream of it
+    Upstream(RString),
+    /// The node and all the nodes downstream of it till the outlet
+    Downstream(RString),
+    /// Nodes selected by both, in the order of the first one
+    Intersect(RBox<Propagation>, RBox<Propagation>),
+    /// Nodes selected by either, the ones from the second that are
+    /// not in the first are added at the end
+    Union(RBox<Propagation>, RBox<Propagation>),
+    /// Nodes selected by the first but not by the second
+    Difference(RBox<Propagation>, RBox<Propagation>),
 }
 
 impl ToString for Propagation {
@@ -830,11 +1491,78 @@ impl ToString for Propagation {
                     .join(", ")
             ),
             Self::Path(p) => format!("[{}]", p.to_string()),
+            Self::SortedBy(a, true) => format!("<sorted.{a}>"),
+            Self::SortedBy(a, false) => format!("<sorted_desc.{a}>"),
+            Self::Upstream(n) => format!("<upstream.{}>", table_key(n)),
+            Self::Downstream(n) => format!("<downstream.{}>", table_key(n)),
+            Self::Intersect(a, b) => {
+                format!("{} & {}", a.operand_string(false), b.operand_string(false))
+            }
+            Self::Union(a, b) => {
+                format!("{} | {}", a.operand_string(false), b.operand_string(false))
+            }
+            Self::Difference(a, b) => {
+                format!("{} - {}", a.operand_string(false), b.operand_string(false))
+            }
         }
     }
 }
 
 impl Propagation {
+    /// Propagation through the list of nodes by their names
+    ///
+    /// ```
+    /// # use nadi_core::functions::Propagation;
+    /// let prop = Propagation::list(["a", "b"]);
+    /// assert_eq!(prop.to_string(), "[a, b]");
+    /// ```
+    pub fn list<I: IntoIterator<Item = S>, S: Into<RString>>(names: I) -> Self {
+        Self::List(names.into_iter().map(Into::into).collect())
+    }
+
+    /// Propagation through the list of nodes from a slice of names
+    ///
+    /// ```
+    /// # use nadi_core::functions::Propagation;
+    /// let prop = Propagation::from_node_names(&["a", "b"]);
+    /// assert_eq!(prop, Propagation::list(["a", "b"]));
+    /// ```
+    pub fn from_node_names(names: &[&str]) -> Self {
+        Self::list(names.iter().copied())
+    }
+
+    /// Propagation through the path between the start and end node
+    ///
+    /// ```
+    /// # use nadi_core::functions::Propagation;
+    /// let prop = Propagation::path("a", "d");
+    /// assert_eq!(prop.to_string(), "[a -> d]");
+    /// ```
+    pub fn path<S: Into<RString>>(start: S, end: S) -> Self {
+        Self::Path(StrPath::new(start.into(), end.into()))
+    }
+
+    /// Nodes selected by both propagations
+    ///
+    /// ```
+    /// # use nadi_core::functions::Propagation;
+    /// let prop = Propagation::list(["a", "b"]).intersect(Propagation::Upstream("c".into()));
+    /// assert_eq!(prop.to_string(), "[a, b] & <upstream.c>");
+    /// ```
+    pub fn intersect(self, other: Self) -> Self {
+        Self::Intersect(RBox::new(self), RBox::new(other))
+    }
+
+    /// Nodes selected by either of the propagations
+    pub fn union(self, other: Self) -> Self {
+        Self::Union(RBox::new(self), RBox::new(other))
+    }
+
+    /// Nodes selected by this propagation but not the `other`
+    pub fn difference(self, other: Self) -> Self {
+        Self::Difference(RBox::new(self), RBox::new(other))
+    }
+
     pub fn to_colored_string(&self) -> String {
         match self {
             Self::Sequential => format!("<{}>", "sequential".red()),
@@ -852,6 +1580,34 @@ impl Propagation {
                     .join(", ")
             ),
             Self::Path(p) => format!("[{}]", p.to_colored_string()),
+            Self::SortedBy(a, true) => format!("<{}.{a}>", "sorted".red()),
+            Self::SortedBy(a, false) => format!("<{}.{a}>", "sorted_desc".red()),
+            Self::Upstream(n) => format!("<{}.{}>", "upstream".red(), table_key(n).green()),
+            Self::Downstream(n) => format!("<{}.{}>", "downstream".red(), table_key(n).green()),
+            Self::Intersect(a, b) => {
+                format!("{} & {}", a.operand_string(true), b.operand_string(true))
+            }
+            Self::Union(a, b) => {
+                format!("{} | {}", a.operand_string(true), b.operand_string(true))
+            }
+            Self::Difference(a, b) => {
+                format!("{} - {}", a.operand_string(true), b.operand_string(true))
+            }
+        }
+    }
+
+    /// String of the propagation as an operand of the combinators,
+    /// the combined ones are inside `()` so they are parsed back in
+    /// the same order
+    fn operand_string(&self, colored: bool) -> String {
+        let s = if colored {
+            self.to_colored_string()
+        } else {
+            self.to_string()
+        };
+        match self {
+            Self::Intersect(..) | Self::Union(..) | Self::Difference(..) => format!("({s})"),
+            _ => s,
         }
     }
 }
@@ -869,3 +1625,481 @@ pub struct KeyVal {
     pub key: RString,
     pub val: Attribute,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn func_arg(name: &str, ty: &str) -> FuncArg {
+        FuncArg {
+            name: name.into(),
+            ty: ty.into(),
+            help: "".into(),
+            category: FuncArgType::Arg,
+        }
+    }
+
+    #[rstest]
+    #[case("bool", Attribute::String("false".into()), Attribute::Bool(false))]
+    #[case("bool", Attribute::String("true".into()), Attribute::Bool(true))]
+    #[case("bool", Attribute::Bool(true), Attribute::Bool(true))]
+    #[case("Option<bool>", Attribute::String("false".into()), Attribute::Bool(false))]
+    #[case("Option<bool>", Attribute::Null, Attribute::Null)]
+    #[case("i64", Attribute::String(" 12".into()), Attribute::Integer(12))]
+    #[case("i64", Attribute::Float(3.0), Attribute::Integer(3))]
+    #[case("f64", Attribute::Integer(3), Attribute::Float(3.0))]
+    #[case("f64", Attribute::String("2.5".into()), Attribute::Float(2.5))]
+    #[case("&str", Attribute::Integer(1), Attribute::Integer(1))]
+    #[case("String", Attribute::String("false".into()), Attribute::String("false".into()))]
+    fn func_arg_coerce_test(#[case] ty: &str, #[case] value: Attribute, #[case] result: Attribute) {
+        assert_eq!(func_arg("x", ty).coerce(&value), Ok(result));
+    }
+
+    #[rstest]
+    #[case("bool", Attribute::String("yes".into()), "String `\"yes\"`")]
+    #[case("bool", Attribute::Integer(1), "Integer `1`")]
+    #[case("i64", Attribute::Float(3.5), "Float `3.5`")]
+    #[case("i64", Attribute::Float(1e20), "Float `1e20`")]
+    #[case("f64", Attribute::Integer(i64::MAX), "Integer `9223372036854775807`")]
+    #[case("Option<f64>", Attribute::Bool(true), "Bool `true`")]
+    fn func_arg_coerce_error_test(#[case] ty: &str, #[case] value: Attribute, #[case] got: &str) {
+        assert_eq!(
+            func_arg("x", ty).coerce(&value),
+            Err(format!("Argument `x` should be `{ty}`, got {got}"))
+        );
+    }
+
+    #[rstest]
+    fn function_ctx_coerce_test() {
+        let args = [func_arg("flag", "bool"), func_arg("name", "&str")];
+        let mut kwargs = std::collections::HashMap::new();
+        kwargs.insert("name".to_string(), Attribute::String("false".into()));
+        let mut ctx = FunctionCtx::from_arg_kwarg(vec![Attribute::String("false".into())], kwargs);
+        ctx.coerce(&args).unwrap();
+        assert_eq!(ctx.arg(0), Some(&Attribute::Bool(false)));
+        assert_eq!(ctx.kwarg("name"), Some(&Attribute::String("false".into())));
+        let mut ctx = FunctionCtx::from_arg_kwarg(
+            vec![Attribute::Integer(0)],
+            std::collections::HashMap::new(),
+        );
+        assert_eq!(
+            ctx.coerce(&args),
+            Err("Argument `flag` should be `bool`, got Integer `0`".to_string())
+        );
+    }
+
+    fn single(v: &str) -> Condition {
+        Condition::Single(v.into())
+    }
+
+    fn not(c: Condition) -> Condition {
+        Condition::Not(RBox::new(c))
+    }
+
+    fn and(a: Condition, b: Condition) -> Condition {
+        Condition::And(RBox::new(a), RBox::new(b))
+    }
+
+    fn or(a: Condition, b: Condition) -> Condition {
+        Condition::Or(RBox::new(a), RBox::new(b))
+    }
+
+    #[derive(Default)]
+    struct HookPlugin {
+        loaded: std::cell::Cell<bool>,
+        unloaded: std::cell::Cell<bool>,
+    }
+
+    impl NadiPlugin for HookPlugin {
+        fn register(&self, _func: &mut NadiFunctions) {
+            assert!(!self.loaded.get(), "on_load called before register");
+        }
+        fn name(&self) -> RString {
+            "hook".into()
+        }
+        fn on_load(&self, _funcs: &mut NadiFunctions) {
+            self.loaded.set(true);
+        }
+        fn on_unload(&self) {
+            assert!(self.loaded.get(), "on_unload called before on_load");
+            self.unloaded.set(true);
+        }
+    }
+
+    /// Plugin without functions, to refer to the registered ones by name
+    struct NamedPlugin(&'static str);
+
+    impl NadiPlugin for NamedPlugin {
+        fn register(&self, _func: &mut NadiFunctions) {}
+        fn name(&self) -> RString {
+            self.0.into()
+        }
+    }
+
+    #[rstest]
+    fn args_as_test() {
+        let ctx = FunctionCtx::from_arg_kwarg(
+            vec![
+                Attribute::Integer(1),
+                Attribute::Float(2.5),
+                Attribute::String("x".into()),
+            ],
+            HashMap::new(),
+        );
+        assert_eq!(
+            ctx.args_as_relaxed::<f64>().unwrap_err().split(':').next(),
+            Some("Argument 3 [f64]")
+        );
+        assert!(ctx
+            .args_as::<i64>()
+            .unwrap_err()
+            .starts_with("Argument 2 [i64]"));
+        let ctx = FunctionCtx::from_arg_kwarg(
+            vec![Attribute::Integer(1), Attribute::Float(2.5)],
+            HashMap::new(),
+        );
+        assert_eq!(ctx.args_as_relaxed::<f64>(), Ok(vec![1.0, 2.5]));
+        assert_eq!(ctx.args_as::<Attribute>().unwrap().len(), 2);
+    }
+
+    #[rstest]
+    fn plugin_hooks_test() {
+        let plugin = HookPlugin::default();
+        let mut funcs = NadiFunctions::default();
+        plugin.register(&mut funcs);
+        assert!(!plugin.loaded.get());
+        funcs.register_plugin(&plugin);
+        assert!(plugin.loaded.get());
+        assert!(!plugin.unloaded.get());
+        funcs.unload_plugin(&plugin);
+        assert!(plugin.unloaded.get());
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn unload_plugin_test() {
+        let mut funcs = NadiFunctions::default();
+        funcs.register_plugin(&AliasPlugin);
+        assert!(funcs.network("command.command").is_some());
+        funcs.unload_plugin(&NamedPlugin("command"));
+        assert!(funcs.network("command.command").is_none());
+        assert!(!funcs.plugins().contains_key("command"));
+    }
+
+    /// Plugin with the internal functions, and aliases for them
+    /// added in the hook
+    #[cfg(feature = "functions")]
+    struct AliasPlugin;
+
+    #[cfg(feature = "functions")]
+    impl NadiPlugin for AliasPlugin {
+        fn register(&self, funcs: &mut NadiFunctions) {
+            crate::internal::register_internal(funcs);
+        }
+        fn name(&self) -> RString {
+            "alias".into()
+        }
+        fn on_load(&self, funcs: &mut NadiFunctions) {
+            funcs
+                .add_alias("sa", "attrs.set_attrs", FunctionKind::Node)
+                .unwrap();
+        }
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn plugin_on_load_test() {
+        let mut funcs = NadiFunctions::default();
+        funcs.register_plugin(&AliasPlugin);
+        assert_eq!(funcs.node("sa").unwrap().name().as_str(), "set_attrs");
+        assert!(funcs.node("attrs.set_attrs").is_some());
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn plugin_help_test() {
+        let mut funcs = NadiFunctions::default();
+        crate::internal::register_internal(&mut funcs);
+        let help = funcs.plugin_help("command").unwrap();
+        let lines: Vec<&str> = help.lines().collect();
+        assert!(lines
+            .iter()
+            .any(|l| l.starts_with("node ") && l.contains("run")));
+        assert!(lines
+            .iter()
+            .any(|l| l.starts_with("network ") && l.contains("parallel")));
+        assert!(funcs.plugin_help("nonexistent").is_none());
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn add_alias_test() {
+        let mut funcs = NadiFunctions::default();
+        crate::internal::register_internal(&mut funcs);
+        funcs
+            .add_alias("sa", "attrs.set_attrs", FunctionKind::Node)
+            .unwrap();
+        assert_eq!(funcs.node("sa").unwrap().name().as_str(), "set_attrs");
+        assert!(funcs.network("sa").is_none());
+        funcs.add_alias("sa2", "sa", FunctionKind::Node).unwrap();
+        assert_eq!(funcs.node("sa2").unwrap().name().as_str(), "set_attrs");
+        assert_eq!(
+            funcs.add_alias("x", "attrs.nonexistent", FunctionKind::Network),
+            Err("Network function attrs.nonexistent not found".to_string())
+        );
+        assert!(funcs
+            .add_alias("a.b", "attrs.set_attrs", FunctionKind::Node)
+            .is_err());
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn disabled_functions_test() {
+        let mut funcs = NadiFunctions::new();
+        let disabled = funcs.disable("command");
+        assert_eq!(disabled[0], "command");
+        for name in ["command.command", "command.run", "command.parallel"] {
+            assert!(disabled.iter().any(|f| f == name), "{name}");
+        }
+        assert_eq!(funcs.disable("debug.echo"), vec!["debug.echo".to_string()]);
+        assert!(!funcs.plugins().contains_key("command"));
+        for name in ["command", "run", "command.command", "command.run"] {
+            assert!(funcs.node(name).is_none(), "{name}");
+        }
+        for name in [
+            "command",
+            "parallel",
+            "command.parallel",
+            "echo",
+            "debug.echo",
+        ] {
+            assert!(funcs.network(name).is_none(), "{name}");
+        }
+        assert!(funcs.is_disabled("run"));
+        assert!(!funcs.is_disabled("clip"));
+        assert_eq!(
+            funcs.try_network("parallel").err(),
+            Some("Network Function parallel is disabled".to_string())
+        );
+        assert_eq!(
+            funcs.try_node("command.run").err(),
+            Some("Node Function command.run is disabled".to_string())
+        );
+        assert_eq!(
+            funcs.try_network("nonexistent").err(),
+            Some("Network Function nonexistent not found".to_string())
+        );
+        assert!(funcs.try_network("clip").is_ok());
+        // the rest of the plugin is still available
+        assert!(funcs.network("debug.clip").is_some());
+        let debug = funcs.plugins().get("debug").unwrap();
+        assert!(!debug.network().contains(&RString::from("echo")));
+
+        let opts = RegistrationOptions::default().no_shell();
+        assert!(NadiFunctions::new_with(&opts)
+            .network("command.parallel")
+            .is_none());
+        assert!(NadiFunctions::new().network("command.parallel").is_some());
+    }
+
+    // run with and without the `chrono` feature to check both
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn chrono_functions_test() {
+        let mut funcs = NadiFunctions::default();
+        crate::internal::register_internal(&mut funcs);
+        let chrono = cfg!(feature = "chrono");
+        assert_eq!(funcs.plugins().contains_key("datetime"), chrono);
+        for name in ["now", "today", "datetime.now", "datetime.today"] {
+            assert_eq!(funcs.node(name).is_some(), chrono, "{name}");
+            assert_eq!(funcs.network(name).is_some(), chrono, "{name}");
+        }
+        // date/time parsing doesn't need chrono
+        assert!(funcs.node("set_attrs").is_some());
+        let dt: crate::attrs::DateTime = "2020-01-02 03:04:05".parse().unwrap();
+        assert_eq!(dt.to_string(), "2020-01-02 03:04:05");
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn list_functions_filtered_test() {
+        let mut funcs = NadiFunctions::default();
+        crate::internal::register_internal(&mut funcs);
+        let kinds = |list: &str| -> Vec<String> {
+            list.lines()
+                .filter_map(|l| l.split_once(' '))
+                .filter(|(k, _)| *k == "node" || *k == "network")
+                .map(|(k, _)| k.to_string())
+                .collect()
+        };
+        let all = funcs.list_functions_filtered(None, None);
+        let node = funcs.list_functions_filtered(Some(FunctionKind::Node), None);
+        let network = funcs.list_functions_filtered(Some(FunctionKind::Network), None);
+        assert!(kinds(&node).iter().all(|k| k == "node"));
+        assert!(kinds(&network).iter().all(|k| k == "network"));
+        assert_eq!(
+            kinds(&all).len(),
+            kinds(&node).len() + kinds(&network).len()
+        );
+
+        let ts = funcs.list_functions_filtered(Some(FunctionKind::Node), Some("TS_STAT"));
+        assert_eq!(kinds(&ts), vec!["node"]);
+        assert!(ts.contains("ts_stats"));
+        assert!(funcs
+            .list_functions_filtered(None, Some("no_such_function"))
+            .is_empty());
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn sorted_listing_test() {
+        let mut funcs = NadiFunctions::default();
+        crate::internal::register_internal(&mut funcs);
+        let plugins: Vec<&str> = funcs
+            .plugins_sorted()
+            .iter()
+            .map(|(p, _)| p.as_str())
+            .collect();
+        assert!(plugins.windows(2).all(|w| w[0] < w[1]));
+        for (_, f) in funcs.plugins_sorted() {
+            assert!(f.node().windows(2).all(|w| w[0] <= w[1]));
+            assert!(f.network().windows(2).all(|w| w[0] <= w[1]));
+        }
+        let (node_md, net_md) = funcs.list_functions_md(false);
+        for md in [node_md, net_md] {
+            // skip the header and alignment rows
+            let rows: Vec<(&str, &str)> = md
+                .lines()
+                .skip(2)
+                .map(|l| {
+                    let mut cols = l.split('|').map(str::trim).filter(|c| !c.is_empty());
+                    (cols.next().unwrap(), cols.next().unwrap())
+                })
+                .collect();
+            assert!(!rows.is_empty());
+            assert!(rows.windows(2).all(|w| w[0] <= w[1]));
+        }
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn help_plain_test() {
+        let mut funcs = NadiFunctions::default();
+        crate::internal::register_internal(&mut funcs);
+        let help = funcs.help_plain("echo").unwrap();
+        assert!(help.starts_with("network echo("));
+        assert!(help.contains("\nArguments:\n"));
+        assert!(help.contains("- error: bool [def = "));
+        assert!(help.contains("print to stderr instead of stdout\n"));
+        assert!(!help.contains('\x1b'));
+        assert!(funcs.help_plain("nonexistent").is_none());
+    }
+
+    #[cfg(feature = "parser")]
+    #[rstest]
+    #[case(None, vec!["d", "c", "b", "a"])]
+    #[case(Some("[b, a]"), vec!["b", "a"])]
+    #[case(Some("inverse"), vec!["a", "b", "c", "d"])]
+    fn propagation_nodes_test(#[case] prop: Option<&str>, #[case] names: Vec<&str>) {
+        let net = Network::from_edges(&[("a", "b"), ("b", "c"), ("c", "d")]);
+        let mut kwargs = HashMap::new();
+        if let Some(p) = prop {
+            kwargs.insert("propagation".to_string(), Attribute::String(p.into()));
+        }
+        let ctx = FunctionCtx::from_arg_kwarg(vec![], kwargs);
+        let nodes = ctx.propagation_nodes(&net, 0, "propagation").unwrap();
+        let nodes: Vec<String> = nodes.iter().map(|n| n.lock().name().to_string()).collect();
+        assert_eq!(nodes, names);
+    }
+
+    #[rstest]
+    #[case(single("x"), "x")]
+    #[case(not(not(single("x"))), "x")]
+    #[case(not(not(not(single("x")))), "!x")]
+    #[case(not(Condition::Literal(true)), "false")]
+    #[case(and(single("x"), Condition::Literal(true)), "x")]
+    #[case(and(Condition::Literal(true), single("x")), "x")]
+    #[case(and(single("x"), Condition::Literal(false)), "false")]
+    #[case(or(single("x"), Condition::Literal(false)), "x")]
+    #[case(or(Condition::Literal(false), single("x")), "x")]
+    #[case(or(single("x"), Condition::Literal(true)), "true")]
+    #[case(and(Condition::Literal(true), Condition::Literal(false)), "false")]
+    #[case(
+        or(not(not(single("x"))), and(single("y"), Condition::Literal(true))),
+        "x | y"
+    )]
+    #[case(
+        and(single("x"), not(or(single("y"), Condition::Literal(true)))),
+        "false"
+    )]
+    fn simplify_test(#[case] cond: Condition, #[case] simple: &str) {
+        assert_eq!(cond.simplify().to_string(), simple);
+    }
+
+    #[rstest]
+    #[case(single("large"), "large(true) = true")]
+    #[case(single("gauge"), "gauge(missing) = false")]
+    #[case(not(single("dam")), "!(dam(false) = false) = true")]
+    #[case(
+        and(single("large"), single("dam")),
+        "large(true) = true & dam(false) = false => false"
+    )]
+    #[case(
+        or(single("gauge"), not(single("dam"))),
+        "gauge(missing) = false | (!(dam(false) = false) = true) => true"
+    )]
+    #[case(
+        and(or(single("dam"), Condition::Literal(true)), not(and(single("large"), single("gauge")))),
+        "(dam(false) = false | true => true) & (!(large(true) = true & gauge(missing) = false => false) = true) => true"
+    )]
+    fn explain_test(#[case] cond: Condition, #[case] trace: &str) {
+        let mut node = NodeInner::new(0, "a");
+        node.set_attr("large", Attribute::Bool(true));
+        node.set_attr("dam", Attribute::Bool(false));
+        let (res, tr) = node.explain(&cond);
+        assert_eq!(res, node.check(&cond));
+        assert_eq!(tr, trace);
+    }
+
+    fn compare(v: &str, op: CompareOp, val: Attribute) -> Condition {
+        Condition::Compare(v.into(), op, val)
+    }
+
+    #[rstest]
+    #[case(
+        compare("elev", CompareOp::Greater, Attribute::Integer(100)),
+        Ok(true),
+        Ok(true)
+    )]
+    #[case(compare("elev", CompareOp::LessEqual, Attribute::Float(150.5)), Ok(true), Err(()))]
+    #[case(compare("elev", CompareOp::Equal, Attribute::Float(150.0)), Ok(true), Err(()))]
+    #[case(
+        compare("elev", CompareOp::NotEqual, Attribute::Integer(150)),
+        Ok(false),
+        Ok(false)
+    )]
+    #[case(compare("basin", CompareOp::Equal, Attribute::String("ohio".into())), Ok(true), Ok(true))]
+    #[case(compare("basin", CompareOp::Less, Attribute::String("a".into())), Ok(false), Ok(false))]
+    #[case(compare("basin", CompareOp::Less, Attribute::Integer(1)), Err(()), Err(()))]
+    #[case(compare("basin", CompareOp::Equal, Attribute::Integer(1)), Ok(false), Err(()))]
+    #[case(compare("gauge", CompareOp::Greater, Attribute::Integer(1)), Err(()), Err(()))]
+    #[case(
+        not(compare("elev", CompareOp::Less, Attribute::Integer(100))),
+        Ok(true),
+        Ok(true)
+    )]
+    fn compare_test(
+        #[case] cond: Condition,
+        #[case] strict: Result<bool, ()>,
+        #[case] super_strict: Result<bool, ()>,
+    ) {
+        let mut node = NodeInner::new(0, "a");
+        node.set_attr("elev", Attribute::Integer(150));
+        node.set_attr("basin", Attribute::String("ohio".into()));
+        assert_eq!(node.check(&cond), strict == Ok(true));
+        assert_eq!(node.check_strict(&cond).map_err(|_| ()), strict);
+        assert_eq!(node.check_super_strict(&cond).map_err(|_| ()), super_strict);
+        assert_eq!(node.explain(&cond).0, node.check(&cond));
+    }
+}
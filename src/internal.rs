@@ -4,7 +4,13 @@ mod attrs;
 mod attrs2;
 mod command;
 mod connections;
+// functions that need the current date/time (`now`, `today`) are
+// only available with `chrono`, parsing and formatting of the
+// date/time values work without it
+#[cfg(feature = "chrono")]
+mod datetime;
 mod debug;
+mod numbers;
 mod regex;
 mod render;
 mod table;
@@ -12,20 +18,22 @@ mod timeseries;
 mod visuals;
 
 use crate::functions::NadiFunctions;
-use crate::plugins::NadiPlugin;
 
 pub(crate) fn register_internal(funcs: &mut NadiFunctions) {
     // These things need to be automated if possible, but I don't
     // think that is possible: search all types that implement
     // NadiPlugin trait within functions
-    attrs::AttrsMod {}.register(funcs);
-    attrs2::AttrsMod {}.register(funcs);
-    command::CommandMod {}.register(funcs);
-    connections::ConnectionsMod {}.register(funcs);
-    debug::DebugMod {}.register(funcs);
-    regex::RegexMod {}.register(funcs);
-    render::RenderMod {}.register(funcs);
-    table::TableMod {}.register(funcs);
-    timeseries::TimeseriesMod {}.register(funcs);
-    visuals::VisualsMod {}.register(funcs);
+    funcs.register_plugin(&attrs::AttrsMod {});
+    funcs.register_plugin(&attrs2::AttrsMod {});
+    funcs.register_plugin(&command::CommandMod {});
+    funcs.register_plugin(&connections::ConnectionsMod {});
+    #[cfg(feature = "chrono")]
+    funcs.register_plugin(&datetime::DatetimeMod {});
+    funcs.register_plugin(&debug::DebugMod {});
+    funcs.register_plugin(&numbers::NumbersMod {});
+    funcs.register_plugin(&regex::RegexMod {});
+    funcs.register_plugin(&render::RenderMod {});
+    funcs.register_plugin(&table::TableMod {});
+    funcs.register_plugin(&timeseries::TimeseriesMod {});
+    funcs.register_plugin(&visuals::VisualsMod {});
 }
@@ -1,6 +1,7 @@
 // This mod is kept as an example to how the functions are written
 // without the macros. Any additional functions are recommended to be
 // written using the macros provided by nadi_plugin crate
+use crate::attrs::sorted_attrs;
 use crate::functions::{
     FuncArg, FuncArgType, FunctionCtx, FunctionRet, NadiFunctions, NodeFunction, NodeFunction_TO,
 };
@@ -9,7 +10,7 @@ use crate::prelude::*;
 use crate::return_on_err;
 use abi_stable::sabi_trait::TD_CanDowncast;
 
-use abi_stable::std_types::{RString, RVec, Tuple2};
+use abi_stable::std_types::{RString, RVec};
 use nadi_plugin::node_func;
 use string_template_plus::Template;
 
@@ -51,9 +52,13 @@ impl NodeFunction for LoadAttrs {
 # Arguments
 - `filename`: Template for the filename to load node attributes from
 - `verbose`: print verbose message
+- `comments`: keep the comments preceding the keys in `_comments`
 
 The template will be rendered for each node, and that filename from the
-rendered template will be used to load the attributes.
+rendered template will be used to load the attributes. If the
+rendered filename is `-` the attributes are read from stdin until
+EOF, since the stdin is consumed by the first node, rest of the nodes
+will not get any attributes from it.
 
 # Errors
 The function will error out in following conditions:
@@ -65,12 +70,26 @@ The function will error out in following conditions:
     }
 
     fn args(&self) -> RVec<FuncArg> {
-        vec![FuncArg {
-            name: "filename".into(),
-            ty: "PathBuf".into(),
-            help: "Template for the filename to load node attributes from".into(),
-            category: FuncArgType::Arg,
-        }]
+        vec![
+            FuncArg {
+                name: "filename".into(),
+                ty: "PathBuf".into(),
+                help: "Template for the filename to load node attributes from".into(),
+                category: FuncArgType::Arg,
+            },
+            FuncArg {
+                name: "verbose".into(),
+                ty: "bool".into(),
+                help: "print verbose message".into(),
+                category: FuncArgType::DefArg("false".into()),
+            },
+            FuncArg {
+                name: "comments".into(),
+                ty: "bool".into(),
+                help: "keep the comments preceding the keys in `_comments`".into(),
+                category: FuncArgType::DefArg("false".into()),
+            },
+        ]
         .into()
     }
 
@@ -85,6 +104,11 @@ The function will error out in following conditions:
             Some(Err(e)) => return FunctionRet::Error(e.into()),
             None => false,
         };
+        let comments: bool = match ctx.arg_kwarg(2, "comments") {
+            Some(Ok(a)) => a,
+            Some(Err(e)) => return FunctionRet::Error(e.into()),
+            None => false,
+        };
         let filepath = match node.render(&templ) {
             Ok(f) => f,
             Err(e) => return FunctionRet::Error(e.to_string().into()),
@@ -92,7 +116,7 @@ The function will error out in following conditions:
         if verbose {
             eprintln!("Loadin Attributes from: {filepath}");
         }
-        if let Err(e) = node.load_attr(&filepath) {
+        if let Err(e) = node.load_attr_with(&filepath, comments) {
             FunctionRet::Error(RString::from(e.to_string()))
         } else {
             FunctionRet::None
@@ -129,7 +153,7 @@ No arguments and no errors, it'll just print all the attributes in a node with
     }
 
     fn call(&self, node: &mut NodeInner, _ctx: &FunctionCtx) -> FunctionRet {
-        for Tuple2(k, v) in node.attr_map() {
+        for (k, v) in sorted_attrs(node.attr_map()) {
             println!("{}::{k} = {}", node.name(), v.to_string());
         }
         FunctionRet::None
@@ -157,10 +181,7 @@ The attributes will be printed in `key=val` format.
 */
 #[node_func]
 fn print_attrs(node: &mut NodeInner, #[args] attrs: AttrSlice, name: bool) -> FunctionRet {
-    let attrs = return_on_err!(attrs
-        .iter()
-        .map(String::try_from_attr)
-        .collect::<Result<Vec<String>, String>>());
+    let attrs: Vec<String> = return_on_err!(crate::attrs::attrs_as(attrs.as_slice()));
 
     for a in attrs {
         if let Some(v) = node.attr(&a) {
@@ -2,9 +2,11 @@ use nadi_plugin::nadi_internal_plugin;
 
 #[nadi_internal_plugin]
 mod attrs {
+    use crate::attrs::attrs_as_relaxed;
     use crate::prelude::*;
     use abi_stable::std_types::Tuple2;
-    use nadi_plugin::{network_func, node_func};
+    use nadi_plugin::{nadi_func, network_func, node_func};
+    use std::path::PathBuf;
 
     use string_template_plus::Template;
 
@@ -31,6 +33,9 @@ mod attrs {
         #[kwargs]
         attrs: &AttrMap,
     ) -> Result<(), String> {
+        for Tuple2(k, _) in attrs {
+            node.check_writable(k)?;
+        }
         for Tuple2(k, v) in attrs {
             node.set_attr(k.as_str(), v.clone());
         }
@@ -104,12 +109,8 @@ mod attrs {
         /// List of attributes that can be cast to bool
         #[args]
         conds: &[Attribute],
-    ) -> bool {
-        let mut ans = true;
-        for c in conds {
-            ans = ans && bool::from_attr_relaxed(c).unwrap();
-        }
-        ans
+    ) -> Result<bool, String> {
+        Ok(attrs_as_relaxed::<bool>(conds)?.into_iter().all(|c| c))
     }
 
     /// boolean or
@@ -119,12 +120,8 @@ mod attrs {
         /// List of attributes that can be cast to bool
         #[args]
         conds: &[Attribute],
-    ) -> bool {
-        let mut ans = false;
-        for c in conds {
-            ans = ans || bool::from_attr_relaxed(c).unwrap();
-        }
-        ans
+    ) -> Result<bool, String> {
+        Ok(attrs_as_relaxed::<bool>(conds)?.into_iter().any(|c| c))
     }
 
     /// map values from the attribute based on the given table
@@ -177,6 +174,30 @@ mod attrs {
         Ok(())
     }
 
+    /// Set the node attribute to the rendered template
+    ///
+    /// The template is rendered with the node attributes at the time
+    /// of the call and stored as a string, later changes to those
+    /// attributes do not update it. Use the `_` prefix to get the
+    /// string attributes without quotes.
+    ///
+    /// # Example
+    /// ```task
+    /// node set_attr_template("label", "{_NAME} ({INDEX})")
+    /// ```
+    #[node_func]
+    fn set_attr_template(
+        node: &mut NodeInner,
+        /// Name of the attribute to set
+        name: &str,
+        /// String template to render
+        template: &Template,
+    ) -> anyhow::Result<()> {
+        let text = node.render(template)?;
+        node.set_attr(name, text.into());
+        Ok(())
+    }
+
     /// Set node attributes based on string templates
     #[node_func(echo = false)]
     fn load_toml_render(
@@ -192,7 +213,49 @@ mod attrs {
         }
         let tokens = crate::parser::tokenizer::get_tokens(&toml)?;
         let attrs = crate::parser::attrs::parse(tokens)?;
-        node.attr_map_mut().extend(attrs);
+        node.extend_attrs(attrs);
+        Ok(())
+    }
+
+    /// Set node attributes from a TOML or JSON string
+    ///
+    /// The string is parsed into attributes and merged into the
+    /// node, replacing the existing values of the same keys. JSON
+    /// input should be an object, and needs the `serde` feature.
+    ///
+    /// # Error
+    /// The function errors with the line and column of the invalid
+    /// part of the string if it can't be parsed.
+    ///
+    /// # Example
+    /// ```task
+    /// node set_attrs_from_str("x = 1\ny = \"a\"")
+    /// node set_attrs_from_str("{\"x\": 1}", format = "json")
+    /// ```
+    #[node_func(format = "toml")]
+    fn set_attrs_from_str(
+        node: &mut NodeInner,
+        /// String with the attributes
+        s: &str,
+        /// Format of the string, one of toml/json
+        format: &str,
+    ) -> anyhow::Result<()> {
+        let attrs = match format {
+            "toml" => {
+                let toml = format!("{s}\n");
+                let tokens = crate::parser::tokenizer::get_tokens(&toml)?;
+                crate::parser::attrs::parse(tokens)?
+            }
+            #[cfg(feature = "serde")]
+            "json" => crate::attrs::attrmap_from_json(s).map_err(anyhow::Error::msg)?,
+            #[cfg(not(feature = "serde"))]
+            "json" => anyhow::bail!("JSON attributes need the `serde` feature"),
+            f => anyhow::bail!("Unknown format {f:?}, should be one of toml/json"),
+        };
+        for Tuple2(k, _) in &attrs {
+            node.check_writable(k).map_err(anyhow::Error::msg)?;
+        }
+        node.extend_attrs(attrs);
         Ok(())
     }
 
@@ -216,6 +279,75 @@ mod attrs {
         }))
     }
 
+    /// Cast the value to the given type
+    ///
+    /// The type can be one of bool, int, float, string, date, time or
+    /// datetime. Strings are parsed for the numbers and dates, and
+    /// floats are truncated to integers.
+    ///
+    /// # Example
+    /// ```task
+    /// node.area_int = cast(area, "int")
+    /// ```
+    #[nadi_func]
+    fn cast(
+        /// Value to cast
+        value: Attribute,
+        /// Name of the type to cast to
+        ty: &str,
+    ) -> Result<Attribute, String> {
+        value.coerce(ty)
+    }
+
+    /// Value of the first case that is equal to the given value
+    ///
+    /// The arguments after the value are the alternating cases and
+    /// results, and the last one is the default value returned when
+    /// none of the cases match. The cases and results can also be
+    /// given as two arrays of the same length followed by the
+    /// default.
+    ///
+    /// # Error
+    /// Errors if the default value is missing, or the two arrays
+    /// have different lengths.
+    ///
+    /// # Example
+    /// ```task
+    /// node.cost = switch(landuse, "urban", 3, "forest", 1, 2)
+    /// node.cost = switch(landuse, ["urban", "forest"], [3, 1], 2)
+    /// ```
+    #[nadi_func]
+    fn switch(
+        /// Value to match, the cases and results, and the default value
+        #[args]
+        args: &[Attribute],
+    ) -> Result<Attribute, String> {
+        let (value, rest) = args.split_first().ok_or("No value to match")?;
+        let (default, rest) = match rest.split_last() {
+            Some((d, r)) if r.len() % 2 == 0 => (d, r),
+            _ => return Err("The default value should be the last argument".to_string()),
+        };
+        let pairs: Vec<(&Attribute, &Attribute)> = match rest {
+            [Attribute::Array(cases), Attribute::Array(results)] => {
+                if cases.len() != results.len() {
+                    return Err(format!(
+                        "Number of cases ({}) and results ({}) are different",
+                        cases.len(),
+                        results.len()
+                    ));
+                }
+                cases.iter().zip(results.iter()).collect()
+            }
+            _ => rest.chunks(2).map(|c| (&c[0], &c[1])).collect(),
+        };
+        Ok(pairs
+            .into_iter()
+            .find(|(c, _)| *c == value)
+            .map(|(_, r)| r)
+            .unwrap_or(default)
+            .clone())
+    }
+
     /// Set network attributes
     ///
     /// # Arguments
@@ -233,6 +365,41 @@ mod attrs {
         Ok(())
     }
 
+    /// Load network attributes from the file
+    ///
+    /// The file is in the same format as the node attribute files,
+    /// use `-` as the filename to read the attributes from stdin
+    /// until EOF. This can be used to pipe the attributes generated
+    /// by other programs into nadi.
+    ///
+    /// # Example
+    /// ```task
+    /// network load_attrs("-")
+    /// ```
+    #[network_func]
+    fn load_attrs(
+        network: &mut Network,
+        /// File to load the attributes from, `-` for stdin
+        filename: PathBuf,
+    ) -> anyhow::Result<()> {
+        network.load_attr(filename)
+    }
+
+    /// Set the attribute on all nodes
+    ///
+    /// Same as `node.name = value` without any node selection, but
+    /// done in a single pass over the nodes.
+    #[network_func]
+    fn set_all(
+        network: &mut Network,
+        /// Name of the attribute
+        name: &str,
+        /// Value of the attribute
+        value: Attribute,
+    ) {
+        network.set_attr_all(name, value);
+    }
+
     /// Set network attributes based on string templates
     #[network_func]
     fn set_attrs_render(
@@ -249,3 +416,83 @@ mod attrs {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::functions::{FunctionCtx, NadiFunctions};
+    use crate::prelude::*;
+    use rstest::rstest;
+    use std::collections::HashMap;
+
+    #[rstest]
+    fn set_attr_template_test() {
+        let mut funcs = NadiFunctions::default();
+        crate::internal::register_internal(&mut funcs);
+        let func = funcs.node("attrs.set_attr_template").unwrap();
+        let mut node = NodeInner::new(3, "a");
+        let ctx = FunctionCtx::from_arg_kwarg(
+            vec![
+                Attribute::String("label".into()),
+                Attribute::String("{_NAME} ({INDEX})".into()),
+            ],
+            HashMap::new(),
+        );
+        func.call(&mut node, &ctx).res().unwrap();
+        assert_eq!(node.attr("label"), Some(&Attribute::String("a (3)".into())));
+        // the attribute is not updated on later changes
+        node.set_index(4);
+        assert_eq!(node.attr("label"), Some(&Attribute::String("a (3)".into())));
+    }
+
+    #[rstest]
+    #[case("x = 1\ny = \"b\"", Ok(()))]
+    #[case("x = 1\nINDEX = 5", Err("Attribute INDEX of node a is read-only"))]
+    #[case("NAME = \"b\"", Err("Attribute NAME of node a is read-only"))]
+    fn set_attrs_from_str_test(#[case] s: &str, #[case] result: Result<(), &str>) {
+        let mut funcs = NadiFunctions::default();
+        crate::internal::register_internal(&mut funcs);
+        let func = funcs.node("attrs.set_attrs_from_str").unwrap();
+        let mut node = NodeInner::new(3, "a");
+        let ctx = FunctionCtx::from_arg_kwarg(vec![Attribute::String(s.into())], HashMap::new());
+        assert_eq!(
+            func.call(&mut node, &ctx).res(),
+            result.map(|_| None).map_err(String::from)
+        );
+        // nothing is set when one of the keys is reserved
+        let x = result.ok().map(|_| Attribute::Integer(1));
+        assert_eq!(node.attr("x"), x.as_ref());
+        assert_eq!(node.name(), "a");
+        assert_eq!(node.index(), 3);
+    }
+
+    fn ints(v: &[i64]) -> Vec<Attribute> {
+        v.iter().map(|i| Attribute::Integer(*i)).collect()
+    }
+
+    #[rstest]
+    #[case(ints(&[2, 1, 10, 2, 20, 0]), Ok(20))]
+    #[case(ints(&[5, 1, 10, 0]), Ok(0))]
+    #[case(
+        vec![
+            Attribute::Integer(2),
+            Attribute::from(vec![1i64, 2]),
+            Attribute::from(vec![10i64, 20]),
+            Attribute::Integer(0),
+        ],
+        Ok(20)
+    )]
+    #[case(ints(&[1, 1, 10]), Err("The default value should be the last argument"))]
+    fn switch_test(#[case] args: Vec<Attribute>, #[case] result: Result<i64, &str>) {
+        let mut funcs = NadiFunctions::default();
+        crate::internal::register_internal(&mut funcs);
+        let func = funcs.node("attrs.switch").unwrap();
+        let mut node = NodeInner::new(0, "a");
+        let ctx = FunctionCtx::from_arg_kwarg(args, HashMap::new());
+        assert_eq!(
+            func.call(&mut node, &ctx).res(),
+            result
+                .map(|r| Some(Attribute::Integer(r)))
+                .map_err(String::from)
+        );
+    }
+}
@@ -2,6 +2,7 @@ use nadi_plugin::nadi_internal_plugin;
 
 #[nadi_internal_plugin]
 mod connections {
+    use crate::network::ReorderMode;
     use crate::parser::tokenizer::valid_variable_name;
     use crate::prelude::*;
     use nadi_plugin::network_func;
@@ -13,23 +14,81 @@ mod connections {
     /// Load the given file into the network
     ///
     /// This replaces the current network with the one loaded from the
-    /// file.
-    #[network_func(append = false)]
+    /// file. The nodes are ordered from the outlet to the inputs
+    /// unless `preserve_order` is set, in which case they are in the
+    /// order they appear in the file, but the input dependent
+    /// propagations will not be in correct order.
+    #[network_func(append = false, preserve_order = false)]
     fn load_file(
         net: &mut Network,
         /// File to load the network connections from
         file: PathBuf,
         /// Append the connections in the current network
         append: bool,
+        /// Keep the order of the nodes as in the file
+        preserve_order: bool,
     ) -> anyhow::Result<()> {
+        let mode = if preserve_order {
+            ReorderMode::Preserve
+        } else {
+            ReorderMode::Topological
+        };
         if append {
             todo!()
         } else {
-            *net = Network::from_file(file)?;
+            *net = Network::from_file_opts(file, mode)?;
         }
         Ok(())
     }
 
+    /// Cumulative distance to the outlet for each node
+    ///
+    /// The `length` attribute of a node is the length from that node
+    /// to its output, those are added along the path to the outlet.
+    #[network_func(missing_zero = false)]
+    fn downstream_distance(
+        net: &mut Network,
+        /// Attribute with the length to the output node
+        length: &str,
+        /// Attribute to save the distance to the outlet in
+        outattr: &str,
+        /// Treat missing length as zero instead of error
+        missing_zero: bool,
+    ) -> Result<(), String> {
+        net.downstream_distance(length, outattr, missing_zero)
+    }
+
+    /// Remove the leaf nodes whose attribute fails the threshold
+    ///
+    /// The leaf nodes (nodes without inputs) are removed repeatedly
+    /// until none of them satisfy `attr <op> threshold`. Only the
+    /// leaves are removed so the network stays connected.
+    #[network_func(op = "<")]
+    fn prune_leaves(
+        net: &mut Network,
+        /// Attribute to compare
+        attr: &str,
+        /// Threshold value for the attribute
+        threshold: f64,
+        /// Comparison operator (<, <=, >, >=, ==, !=)
+        op: &str,
+    ) -> Result<usize, String> {
+        net.prune_leaves(attr, threshold, op)
+    }
+
+    /// Print the network statistics
+    ///
+    /// Prints the number of nodes and edges, the depth (longest path
+    /// from the outlet to a leaf) and the width (most nodes in a
+    /// level) of the network.
+    #[network_func]
+    fn stats(net: &mut Network) {
+        println!("Nodes: {}", net.nodes_count());
+        println!("Edges: {}", net.edges_ind().count());
+        println!("Depth: {}", net.depth());
+        println!("Width: {}", net.width());
+    }
+
     /// Save the network into the given file
     ///
     /// For more control on graphviz file writing use
@@ -0,0 +1,30 @@
+use nadi_plugin::nadi_internal_plugin;
+
+#[nadi_internal_plugin]
+mod datetime {
+    use crate::attrs::{Date, DateTime};
+    use nadi_plugin::nadi_func;
+
+    /// Current date and time
+    ///
+    /// The time is in the local timezone unless `utc` is set. Useful
+    /// to stamp the outputs, or in the templates for filenames.
+    #[nadi_func(utc = false)]
+    fn now(
+        /// Use UTC instead of the local timezone
+        utc: bool,
+    ) -> DateTime {
+        DateTime::now(utc)
+    }
+
+    /// Current date
+    ///
+    /// The date is in the local timezone unless `utc` is set.
+    #[nadi_func(utc = false)]
+    fn today(
+        /// Use UTC instead of the local timezone
+        utc: bool,
+    ) -> Date {
+        Date::today(utc)
+    }
+}
@@ -2,10 +2,10 @@ use nadi_plugin::nadi_internal_plugin;
 
 #[nadi_internal_plugin]
 mod debug {
+    use crate::attrs::sorted_attrs;
+    use crate::colors::Colorize;
     use crate::prelude::*;
-    use abi_stable::std_types::Tuple2;
-    use colored::Colorize;
-    use nadi_plugin::network_func;
+    use nadi_plugin::{nadi_func, network_func};
 
     /// Print the args and kwargs on this function
     ///
@@ -28,9 +28,9 @@ mod debug {
             .iter()
             .map(|a| Attribute::to_colored_string(a).to_string())
             .collect();
-        let kwargs_str: Vec<String> = kwargs
-            .iter()
-            .map(|Tuple2(k, v)| format!("{}={}", k.to_string().blue(), v.to_colored_string()))
+        let kwargs_str: Vec<String> = sorted_attrs(kwargs)
+            .into_iter()
+            .map(|(k, v)| format!("{}={}", k.to_string().blue(), v.to_colored_string()))
             .collect();
         args_str.extend(kwargs_str);
         println!("Function Call: debug({})", args_str.join(", "));
@@ -38,6 +38,24 @@ mod debug {
         println!("KwArgs: {kwargs:?}");
     }
 
+    /// Print the attribute value
+    ///
+    /// With `pretty` the nested arrays and tables are printed in
+    /// multiple lines with indentation.
+    #[nadi_func(pretty = false)]
+    fn print(
+        /// Value to print
+        attr: Attribute,
+        /// Print nested values in multiple lines
+        pretty: bool,
+    ) {
+        if pretty {
+            println!("{}", attr.to_pretty_colored_string(2));
+        } else {
+            println!("{}", attr.to_colored_string());
+        }
+    }
+
     /// Echo the string to stdout or stderr
     ///
     /// This simply echoes anything given to it. This can be used in
@@ -0,0 +1,137 @@
+use nadi_plugin::nadi_internal_plugin;
+
+#[nadi_internal_plugin]
+mod numbers {
+    use nadi_plugin::nadi_func;
+
+    /// Round the number to the given decimal digits
+    ///
+    /// The ties are rounded to the even digit (`round(2.5, 0)` is
+    /// `2.0`), negative `digits` round to the tens, hundreds, etc.
+    #[nadi_func]
+    fn round(
+        /// Number to round
+        #[relaxed]
+        x: f64,
+        /// Number of digits after the decimal point
+        digits: i64,
+    ) -> f64 {
+        super::number_utils::round(x, digits)
+    }
+
+    /// Format the number with fixed decimal digits
+    ///
+    /// Same as `round` but returns a string with exactly `digits`
+    /// digits after the decimal point (`to_fixed(1.5, 2)` is
+    /// `"1.50"`).
+    #[nadi_func]
+    fn to_fixed(
+        /// Number to format
+        #[relaxed]
+        x: f64,
+        /// Number of digits after the decimal point
+        digits: i64,
+    ) -> String {
+        super::number_utils::format_number(x, digits, false)
+    }
+
+    /// Format the number for the reports
+    ///
+    /// The number is rounded to the `digits` after decimal point, and
+    /// with `thousands` the integer part is separated by commas
+    /// (`1234.5678` to `"1,234.57"`).
+    ///
+    /// # Example
+    /// ```task
+    /// node.area_str = format_number(area, 2, thousands = true)
+    /// ```
+    #[nadi_func(thousands = false)]
+    fn format_number(
+        /// Number to format
+        #[relaxed]
+        x: f64,
+        /// Number of digits after the decimal point
+        digits: i64,
+        /// Separate the thousands with commas
+        thousands: bool,
+    ) -> String {
+        super::number_utils::format_number(x, digits, thousands)
+    }
+}
+
+mod number_utils {
+    /// Round half to even at the given decimal digits
+    pub fn round(x: f64, digits: i64) -> f64 {
+        let digits = digits.clamp(-308, 308) as i32;
+        if digits >= 0 {
+            let scale = 10f64.powi(digits);
+            (x * scale).round_ties_even() / scale
+        } else {
+            let scale = 10f64.powi(-digits);
+            (x / scale).round_ties_even() * scale
+        }
+    }
+
+    pub fn format_number(x: f64, digits: i64, thousands: bool) -> String {
+        let prec = digits.max(0) as usize;
+        let txt = format!("{:.*}", prec, round(x, digits));
+        if !thousands || !x.is_finite() {
+            return txt;
+        }
+        let (sign, txt) = match txt.strip_prefix('-') {
+            Some(t) => ("-", t),
+            None => ("", txt.as_str()),
+        };
+        let (int, frac) = match txt.split_once('.') {
+            Some((i, f)) => (i, Some(f)),
+            None => (txt, None),
+        };
+        let mut grouped = String::with_capacity(int.len() + int.len() / 3);
+        for (i, c) in int.chars().enumerate() {
+            if i > 0 && (int.len() - i) % 3 == 0 {
+                grouped.push(',');
+            }
+            grouped.push(c);
+        }
+        match frac {
+            Some(f) => format!("{sign}{grouped}.{f}"),
+            None => format!("{sign}{grouped}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::number_utils::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(2.5, 0, 2.0)]
+    #[case(3.5, 0, 4.0)]
+    #[case(-2.5, 0, -2.0)]
+    #[case(0.125, 2, 0.12)]
+    #[case(0.375, 2, 0.38)]
+    #[case(1234.5678, 2, 1234.57)]
+    #[case(1250.0, -2, 1200.0)]
+    #[case(1350.0, -2, 1400.0)]
+    fn round_test(#[case] x: f64, #[case] digits: i64, #[case] value: f64) {
+        assert_eq!(round(x, digits), value);
+    }
+
+    #[rstest]
+    #[case(1234.5678, 2, true, "1,234.57")]
+    #[case(1234.5678, 2, false, "1234.57")]
+    #[case(-1234567.0, 0, true, "-1,234,567")]
+    #[case(123.0, 1, true, "123.0")]
+    #[case(999999.995, 2, true, "1,000,000.00")]
+    #[case(0.5, 0, true, "0")]
+    #[case(1.5, 3, false, "1.500")]
+    fn format_number_test(
+        #[case] x: f64,
+        #[case] digits: i64,
+        #[case] thousands: bool,
+        #[case] value: &str,
+    ) {
+        assert_eq!(format_number(x, digits, thousands), value);
+    }
+}
@@ -2,9 +2,13 @@ use nadi_plugin::nadi_internal_plugin;
 
 #[nadi_internal_plugin]
 mod render {
+    use crate::functions::Propagation;
     use crate::prelude::*;
+    use abi_stable::std_types::Tuple2;
     use nadi_plugin::{network_func, node_func};
+    use std::collections::HashMap;
     use std::path::PathBuf;
+    use std::str::FromStr;
     use string_template_plus::Template;
 
     /// Render the template based on the node attributes
@@ -18,12 +22,26 @@ mod render {
         template: &Template,
         /// if render fails keep it as it is instead of exiting
         safe: bool,
+        /// extra variables for the template, overriding the attributes
+        vars: Option<AttrMap>,
     ) -> Result<String, String> {
+        let extra: HashMap<String, String> = vars
+            .unwrap_or_default()
+            .into_iter()
+            .map(|Tuple2(k, v)| {
+                let v = match v {
+                    Attribute::String(s) => s.into(),
+                    v => v.to_string(),
+                };
+                (k.into(), v)
+            })
+            .collect();
         let text = if safe {
-            node.render(template)
+            node.render_with(template, &extra)
                 .unwrap_or_else(|_| template.original().to_string())
         } else {
-            node.render(template).map_err(|e| e.to_string())?
+            node.render_with(template, &extra)
+                .map_err(|e| e.to_string())?
         };
         Ok(text)
     }
@@ -41,7 +59,10 @@ mod render {
     /// If you want only a portion of the file repeated for nodes
     /// inclose them with lines with `---8<---` on both start and the
     /// end. The lines containing the clip syntax will be ignored,
-    /// ideally you can put them in comments.
+    /// ideally you can put them in comments. The starting line can
+    /// have a propagation after a colon (e.g. `---8<---:(large & !dam)`,
+    /// `---8<---:(elevation > 100)` or `---8<---:<inverse>`) to select
+    /// the nodes for the snippet.
     ///
     /// You can also use `---include:<filename>[::line_range]` syntax to
     /// include a file, the line_range syntax, if present, should be
@@ -63,6 +84,30 @@ mod render {
         let template = super::render_utils::RenderFileContents::read_file(&template)?;
         template.print_render(network, outfile)
     }
+
+    /// Render a File template for each node into separate files
+    ///
+    /// The template file is the same as in the `render` network
+    /// function, but the snippets are rendered only for the current
+    /// node. The output directory is created if it doesn't exist, and
+    /// the file name for each node is rendered from `name_template`.
+    /// Returns the number of files written.
+    #[network_func(propagation = "sequential")]
+    fn render_nodes(
+        network: &mut Network,
+        /// Path to the template file
+        template: PathBuf,
+        /// Directory to save the rendered files
+        outdir: PathBuf,
+        /// Template for the file name of each node
+        name_template: &Template,
+        /// Propagation for the nodes to render
+        propagation: &str,
+    ) -> anyhow::Result<usize> {
+        let template = super::render_utils::RenderFileContents::read_file(&template)?;
+        let prop = Propagation::from_str(propagation)?;
+        template.write_nodes(network, &prop, &outdir, name_template)
+    }
 }
 
 mod render_utils {
@@ -189,18 +234,7 @@ mod render_utils {
             for part in &self.contents {
                 match part {
                     RenderFileContentsType::Include(filename, lines) => {
-                        let file = File::open(filename)
-                            .with_context(|| format!("File {filename:?} not found"))?;
-                        let reader_lines: Vec<String> = BufReader::new(file)
-                            .lines()
-                            .collect::<Result<Vec<String>, std::io::Error>>()?;
-                        let lines = NumberRangeOptions::default()
-                            .with_default_start(1)
-                            .with_default_end(reader_lines.len())
-                            .parse(lines)?;
-                        for l in lines {
-                            writeln!(writer, "{}", reader_lines[l - 1])?;
-                        }
+                        write_include(filename, lines, &mut writer)?
                     }
                     RenderFileContentsType::Literal(s) => write!(writer, "{}", s)?,
                     RenderFileContentsType::Snippet(templ, prop) => {
@@ -215,5 +249,124 @@ mod render_utils {
             }
             Ok(())
         }
+
+        /// Render the contents for a single node, the snippets are
+        /// rendered with the node irrespective of their propagation
+        pub fn render_node<W: Write>(
+            &self,
+            node: &NodeInner,
+            writer: &mut W,
+        ) -> anyhow::Result<()> {
+            for part in &self.contents {
+                match part {
+                    RenderFileContentsType::Include(filename, lines) => {
+                        write_include(filename, lines, writer)?
+                    }
+                    RenderFileContentsType::Literal(s) => write!(writer, "{}", s)?,
+                    RenderFileContentsType::Snippet(templ, _) => {
+                        write!(writer, "{}", node.render(templ)?)?
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        /// Render the contents for each node in the propagation into
+        /// the files in `outdir` named by rendering `name`
+        pub fn write_nodes(
+            &self,
+            net: &Network,
+            prop: &Propagation,
+            outdir: &Path,
+            name: &Template,
+        ) -> anyhow::Result<usize> {
+            std::fs::create_dir_all(outdir)
+                .with_context(|| format!("Couldn't create directory {outdir:?}"))?;
+            let nodes = net.nodes_propagation(prop).map_err(Error::msg)?;
+            for node in &nodes {
+                let node = node.lock();
+                let path = outdir.join(node.render(name)?);
+                let file = File::create(&path)
+                    .with_context(|| format!("Couldn't create file {path:?}"))?;
+                let mut writer = BufWriter::new(file);
+                self.render_node(&node, &mut writer)?;
+                writer.flush()?;
+            }
+            Ok(nodes.len())
+        }
+    }
+
+    fn write_include<W: Write + ?Sized>(
+        filename: &Path,
+        lines: &str,
+        writer: &mut W,
+    ) -> anyhow::Result<()> {
+        let file = File::open(filename).with_context(|| format!("File {filename:?} not found"))?;
+        let reader_lines: Vec<String> = BufReader::new(file)
+            .lines()
+            .collect::<Result<Vec<String>, std::io::Error>>()?;
+        let lines = NumberRangeOptions::default()
+            .with_default_start(1)
+            .with_default_end(reader_lines.len())
+            .parse(lines)?;
+        for l in lines {
+            writeln!(writer, "{}", reader_lines[l - 1])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_utils::RenderFileContents;
+    use crate::functions::Propagation;
+    use crate::functions::{FunctionCtx, NadiFunctions};
+    use crate::prelude::*;
+    use rstest::rstest;
+    use std::collections::HashMap;
+    use string_template_plus::Template;
+
+    #[rstest]
+    #[case(vec![("y", Attribute::Integer(2))], "1 Ohio 2")]
+    #[case(vec![("y", Attribute::String("two".into()))], "1 Ohio two")]
+    #[case(vec![("y", Attribute::Integer(2)), ("x", Attribute::Integer(5))], "5 Ohio 2")]
+    fn render_vars_test(#[case] vars: Vec<(&str, Attribute)>, #[case] text: &str) {
+        let mut funcs = NadiFunctions::default();
+        crate::internal::register_internal(&mut funcs);
+        let func = funcs.node("render.render").unwrap();
+        let mut node = NodeInner::new(0, "a");
+        node.set_attr("x", Attribute::Integer(1));
+        node.set_attr("basin", Attribute::String("Ohio".into()));
+        let vars: AttrMap = vars.into_iter().map(|(k, v)| (k.into(), v)).collect();
+        let ctx = FunctionCtx::from_arg_kwarg(
+            vec![Attribute::String("{x} {_basin} {y}".into())],
+            HashMap::from([("vars".to_string(), Attribute::Table(vars))]),
+        );
+        assert_eq!(
+            func.call(&mut node, &ctx).res(),
+            Ok(Some(Attribute::String(text.into())))
+        );
+    }
+
+    #[rstest]
+    fn write_nodes_test() {
+        let dir = std::env::temp_dir().join("nadi-render-nodes-test");
+        let templ = dir.join("template.txt");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&templ, "Node {_NAME}: {x}\n").unwrap();
+        let net = Network::from_edges(&[("a", "b")]);
+        net.set_attr_all("x", Attribute::Integer(1));
+        let outdir = dir.join("out");
+        let name = Template::parse_template("{_NAME}.txt").unwrap();
+        let count = RenderFileContents::read_file(&templ)
+            .unwrap()
+            .write_nodes(&net, &Propagation::Sequential, &outdir, &name)
+            .unwrap();
+        assert_eq!(count, 2);
+        for n in ["a", "b"] {
+            let contents = std::fs::read_to_string(outdir.join(format!("{n}.txt"))).unwrap();
+            assert_eq!(contents, format!("Node {n}: 1\n"));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
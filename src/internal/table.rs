@@ -48,4 +48,285 @@ mod table {
         }
         Ok(())
     }
+
+    /** Join the columns of a CSV file to the nodes as attributes
+
+    The rows are matched to the nodes by the value of the `key_col`
+    column and the node name. The values of the `attrs` columns (all
+    except the key by default) are set as node attributes, with the
+    type inferred from the value (bool, integer, float, date/time, or
+    else string). Empty cells are skipped.
+
+    For the data with comma as the decimal separator, use
+    `comma_decimal = true` with a different `delimiter` (e.g. `";"`),
+    or quote the values. The cells with the `missing` value (like
+    `-9999`) are set as `null`.
+
+    Returns a summary with the number of nodes joined, and the
+    unmatched rows and nodes.
+
+    # Error
+    The function will error out if,
+    - error reading the csv file,
+    - the `key_col` or any of the `attrs` columns is not in the header
+    - the `delimiter` is not a single character
+    */
+    #[network_func(delimiter = ",", comma_decimal = false)]
+    fn join_table(
+        net: &mut Network,
+        /// Path to the csv file
+        path: PathBuf,
+        /// Column with the node names
+        key_col: String,
+        /// Columns to set as attributes
+        attrs: Option<Vec<String>>,
+        /// Value used for missing data in the file
+        missing: Option<String>,
+        /// Character separating the fields
+        delimiter: String,
+        /// Use comma as the decimal separator in numbers
+        comma_decimal: bool,
+    ) -> anyhow::Result<String> {
+        let mut chars = delimiter.chars();
+        let delimiter = match (chars.next(), chars.next()) {
+            (Some(c), None) => c,
+            _ => {
+                return Err(anyhow::Error::msg(format!(
+                    "Delimiter should be a single character, got {delimiter:?}"
+                )))
+            }
+        };
+        let opts = super::table_utils::CsvOptions {
+            delimiter,
+            missing,
+            comma_decimal,
+        };
+        let contents = std::fs::read_to_string(path)?;
+        super::table_utils::join_csv(net, &contents, &key_col, attrs.as_deref(), &opts)
+            .map_err(anyhow::Error::msg)
+    }
+}
+
+mod table_utils {
+    use crate::prelude::*;
+    use std::collections::HashSet;
+    use std::str::FromStr;
+
+    /// Options to read the values from the csv file
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct CsvOptions {
+        /// Character separating the fields
+        pub delimiter: char,
+        /// Value used for missing data, read as `null`
+        pub missing: Option<String>,
+        /// Numbers use comma as the decimal separator
+        pub comma_decimal: bool,
+    }
+
+    impl Default for CsvOptions {
+        fn default() -> Self {
+            Self {
+                delimiter: ',',
+                missing: None,
+                comma_decimal: false,
+            }
+        }
+    }
+
+    /// Split a csv line into the fields, the fields can be quoted
+    /// with `"` to include commas, and `""` inside the quotes is a
+    /// literal `"`
+    pub fn csv_row(line: &str) -> Vec<String> {
+        csv_row_delim(line, ',')
+    }
+
+    /// Same as [`csv_row`] with a different delimiter
+    pub fn csv_row_delim(line: &str, delimiter: char) -> Vec<String> {
+        let mut fields = vec![];
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = !quoted,
+                c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+                c => field.push(c),
+            }
+        }
+        fields.push(field);
+        fields
+    }
+
+    /// Attribute from the csv value with the type inferred like in
+    /// the `FromStr` for [`Attribute`]
+    pub fn infer_attr(val: &str) -> Attribute {
+        infer_attr_opts(val, &CsvOptions::default())
+    }
+
+    /// Same as [`infer_attr`], the values equal to the missing value
+    /// (as text, or as number) are `null`
+    pub fn infer_attr_opts(val: &str, opts: &CsvOptions) -> Attribute {
+        let num = if opts.comma_decimal && val.matches(',').count() == 1 && !val.contains('.') {
+            val.replace(',', ".")
+        } else {
+            val.to_string()
+        };
+        if let Some(m) = &opts.missing {
+            let m = m.trim();
+            let same_num = match (num.parse::<f64>(), m.parse::<f64>()) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => false,
+            };
+            if val == m || same_num {
+                return Attribute::Null;
+            }
+        }
+        // the value with the decimal comma replaced is only used if
+        // it is a number
+        if num != val {
+            if let Ok(a @ (Attribute::Integer(_) | Attribute::Float(_))) = Attribute::from_str(&num)
+            {
+                return a;
+            }
+        }
+        Attribute::from_str(val).unwrap_or_else(|e| match e {})
+    }
+
+    pub fn join_csv(
+        net: &Network,
+        contents: &str,
+        key_col: &str,
+        attrs: Option<&[String]>,
+        opts: &CsvOptions,
+    ) -> Result<String, String> {
+        let mut lines = contents.lines().filter(|l| !l.trim().is_empty());
+        let header = csv_row_delim(lines.next().ok_or("Empty csv file")?, opts.delimiter);
+        let col_index = |name: &str| {
+            header
+                .iter()
+                .position(|h| h.trim() == name)
+                .ok_or(format!("Column {name} not found"))
+        };
+        let key = col_index(key_col)?;
+        let cols: Vec<(usize, &str)> = match attrs {
+            Some(a) => a
+                .iter()
+                .map(|c| Ok((col_index(c)?, c.as_str())))
+                .collect::<Result<_, String>>()?,
+            None => header
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != key)
+                .map(|(i, h)| (i, h.trim()))
+                .collect(),
+        };
+        let mut matched = HashSet::new();
+        let mut unmatched_rows = vec![];
+        for line in lines {
+            let row = csv_row_delim(line, opts.delimiter);
+            let name = row.get(key).map(|k| k.trim()).unwrap_or_default();
+            match net.node_by_name(name) {
+                Some(node) => {
+                    let mut node = node.lock();
+                    for (i, col) in &cols {
+                        match row.get(*i).map(|v| v.trim()) {
+                            Some(v) if !v.is_empty() => {
+                                node.set_attr(col, infer_attr_opts(v, opts));
+                            }
+                            _ => (),
+                        }
+                    }
+                    matched.insert(name.to_string());
+                }
+                None => unmatched_rows.push(name.to_string()),
+            }
+        }
+        let unmatched_nodes: Vec<&str> =
+            net.node_names().filter(|n| !matched.contains(*n)).collect();
+        Ok(format!(
+            "Joined {} nodes; unmatched rows: [{}]; unmatched nodes: [{}]",
+            matched.len(),
+            unmatched_rows.join(", "),
+            unmatched_nodes.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::table_utils::*;
+    use crate::prelude::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("a,b,c", vec!["a", "b", "c"])]
+    #[case("a,\"b,c\",d", vec!["a", "b,c", "d"])]
+    #[case("\"say \"\"hi\"\"\",", vec!["say \"hi\"", ""])]
+    fn csv_row_test(#[case] line: &str, #[case] fields: Vec<&str>) {
+        assert_eq!(csv_row(line), fields);
+    }
+
+    #[rstest]
+    fn join_csv_test() {
+        let net = Network::from_edges(&[("a", "b"), ("c", "b")]);
+        let csv = "name,area,gauged,note\na,12.5,true,x\nb,3,false,\nz,1,true,y\n";
+        let msg = join_csv(&net, csv, "name", None, &CsvOptions::default()).unwrap();
+        assert_eq!(
+            msg,
+            "Joined 2 nodes; unmatched rows: [z]; unmatched nodes: [c]"
+        );
+        let a = net.try_node_by_name("a").unwrap().lock();
+        assert_eq!(a.attr("area"), Some(&Attribute::Float(12.5)));
+        assert_eq!(a.attr("gauged"), Some(&Attribute::Bool(true)));
+        assert_eq!(a.attr("note"), Some(&Attribute::String("x".into())));
+        let b = net.try_node_by_name("b").unwrap().lock();
+        assert_eq!(b.attr("area"), Some(&Attribute::Integer(3)));
+        assert_eq!(b.attr("note"), None);
+        assert!(join_csv(&net, csv, "id", None, &CsvOptions::default()).is_err());
+    }
+
+    #[rstest]
+    #[case("-9999", Attribute::Null)]
+    #[case("-9999.0", Attribute::Null)]
+    #[case("-999", Attribute::Integer(-999))]
+    #[case("1,5", Attribute::Float(1.5))]
+    #[case("1.5", Attribute::Float(1.5))]
+    #[case("1,5,2", Attribute::String("1,5,2".into()))]
+    #[case("x", Attribute::String("x".into()))]
+    #[case("a,b", Attribute::String("a,b".into()))]
+    #[case("2020-01-02", Attribute::Date(crate::attrs::Date::new(2020, 1, 2)))]
+    #[case("99999999999999999999", Attribute::String("99999999999999999999".into()))]
+    fn infer_attr_opts_test(#[case] val: &str, #[case] attr: Attribute) {
+        let opts = CsvOptions {
+            delimiter: ';',
+            missing: Some("-9999".into()),
+            comma_decimal: true,
+        };
+        assert_eq!(infer_attr_opts(val, &opts), attr);
+        // defaults are unchanged
+        assert_eq!(infer_attr("-9999"), Attribute::Integer(-9999));
+        assert_eq!(infer_attr("1,5"), Attribute::String("1,5".into()));
+    }
+
+    #[rstest]
+    fn join_csv_options_test() {
+        let net = Network::from_edges(&[("a", "b")]);
+        let csv = "name;area;flow\na;12,5;-9999\nb;3;0,25\n";
+        let opts = CsvOptions {
+            delimiter: ';',
+            missing: Some("-9999".into()),
+            comma_decimal: true,
+        };
+        join_csv(&net, csv, "name", None, &opts).unwrap();
+        let a = net.try_node_by_name("a").unwrap().lock();
+        assert_eq!(a.attr("area"), Some(&Attribute::Float(12.5)));
+        assert_eq!(a.attr("flow"), Some(&Attribute::Null));
+        let b = net.try_node_by_name("b").unwrap().lock();
+        assert_eq!(b.attr("area"), Some(&Attribute::Integer(3)));
+        assert_eq!(b.attr("flow"), Some(&Attribute::Float(0.25)));
+    }
 }
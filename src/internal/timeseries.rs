@@ -4,6 +4,8 @@ use nadi_plugin::nadi_internal_plugin;
 mod timeseries {
 
     use crate::prelude::*;
+    use crate::table::{contents_2_md, ColumnAlign};
+    use crate::timeseries::{Aggregation, TimeSeries};
     use abi_stable::std_types::{ROption, RString};
     use nadi_plugin::{network_func, node_func};
     use std::collections::HashSet;
@@ -24,6 +26,160 @@ mod timeseries {
         println!();
     }
 
+    /// Print the table of timeseries in all nodes with their type and length
+    #[network_func]
+    fn list_timeseries(net: &mut Network) {
+        let contents: Vec<Vec<String>> = net
+            .nodes()
+            .flat_map(|n| {
+                let n = n.lock();
+                n.timeseries_info()
+                    .into_iter()
+                    .map(|(name, ty, len)| {
+                        vec![n.name().to_string(), name, ty.to_string(), len.to_string()]
+                    })
+                    .collect::<Vec<Vec<String>>>()
+            })
+            .collect();
+        println!(
+            "{}",
+            contents_2_md(
+                &["Node", "Timeseries", "Type", "Length"],
+                &[
+                    &ColumnAlign::Left,
+                    &ColumnAlign::Left,
+                    &ColumnAlign::Left,
+                    &ColumnAlign::Right
+                ],
+                contents,
+            )
+        );
+    }
+
+    /// Resample the timeseries by aggregating every `step` values
+    ///
+    /// The result is a timeseries of floats saved as `out_name`, the
+    /// timeseries should be of floats or integers.
+    ///
+    /// # Example
+    /// ```task
+    /// node ts_resample("flow", 7, "mean", "flow_weekly")
+    /// ```
+    #[node_func(agg = "mean")]
+    fn ts_resample(
+        node: &mut NodeInner,
+        /// name of the timeseries
+        name: &str,
+        /// number of values to aggregate into one
+        step: usize,
+        /// aggregation (mean, sum, min, max, first or last)
+        agg: &str,
+        /// name of the resampled timeseries
+        out_name: &str,
+    ) -> Result<(), String> {
+        let agg: Aggregation = agg.parse()?;
+        let ts = node.try_ts(name)?.resample(step, agg)?;
+        node.set_ts(out_name, ts);
+        Ok(())
+    }
+
+    /// Save the values from index `start` up to (excluding) `end` as
+    /// a new timeseries `out_name`
+    #[node_func]
+    fn ts_slice(
+        node: &mut NodeInner,
+        /// name of the timeseries
+        name: &str,
+        /// start index
+        start: usize,
+        /// end index (excluded)
+        end: usize,
+        /// name of the sliced timeseries
+        out_name: &str,
+    ) -> Result<(), String> {
+        let ts = node.try_ts(name)?.slice(start, end)?;
+        node.set_ts(out_name, ts);
+        Ok(())
+    }
+
+    /// Summary statistics (count, mean, std, min, max) of the timeseries
+    ///
+    /// `NaN` values are skipped, the result is a table that can be
+    /// saved as an attribute.
+    ///
+    /// # Example
+    /// ```task
+    /// node flow_stats = ts_stats("flow")
+    /// ```
+    #[node_func]
+    fn ts_stats(
+        node: &mut NodeInner,
+        /// name of the timeseries
+        name: &str,
+    ) -> Result<Attribute, String> {
+        let stats = node.try_ts(name)?.stats()?;
+        let mut tbl = AttrMap::new();
+        tbl.insert("count".into(), stats.count.into());
+        tbl.insert("mean".into(), Attribute::Float(stats.mean));
+        tbl.insert("std".into(), Attribute::Float(stats.std));
+        tbl.insert("min".into(), Attribute::Float(stats.min));
+        tbl.insert("max".into(), Attribute::Float(stats.max));
+        Ok(Attribute::Table(tbl))
+    }
+
+    /// Save the quantile `q` (0 to 1) of the timeseries values as the
+    /// attribute `out_attr`
+    ///
+    /// `NaN` values are skipped, and the value is linearly
+    /// interpolated between the nearest values.
+    ///
+    /// # Example
+    /// ```task
+    /// node ts_quantile("flow", 0.5, "median_flow")
+    /// ```
+    #[node_func]
+    fn ts_quantile(
+        node: &mut NodeInner,
+        /// name of the timeseries
+        name: &str,
+        /// quantile to calculate
+        q: f64,
+        /// attribute to save the value in
+        out_attr: &str,
+    ) -> Result<(), String> {
+        let val = node.try_ts(name)?.quantile(q)?;
+        node.set_attr(out_attr, Attribute::Float(val));
+        Ok(())
+    }
+
+    /// Save a boolean timeseries `out_name` marking the values of the
+    /// timeseries that are more than `n_sigma` standard deviations away
+    /// from the mean
+    ///
+    /// `NaN` values are skipped for the mean and standard deviation,
+    /// and are not marked as outliers.
+    ///
+    /// # Example
+    /// ```task
+    /// node flag_outliers("flow", 3.0, "flow_outliers")
+    /// ```
+    #[node_func]
+    fn flag_outliers(
+        node: &mut NodeInner,
+        /// name of the timeseries
+        name: &str,
+        /// number of standard deviations
+        n_sigma: f64,
+        /// name of the boolean timeseries
+        out_name: &str,
+    ) -> Result<(), String> {
+        let ts = node.try_ts(name)?;
+        let mask = ts.outlier_mask(n_sigma)?;
+        let flags = TimeSeries::new(ts.timeline().clone(), mask.into());
+        node.set_ts(out_name, flags);
+        Ok(())
+    }
+
     /** Print the given timeseries values in csv format
     # TODO
     - save to file instead of showing with `outfile: Option<PathBuf>`
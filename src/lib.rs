@@ -1,4 +1,5 @@
 pub mod attrs;
+pub mod colors;
 pub mod functions;
 pub mod graphics;
 pub mod network;
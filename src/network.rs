@@ -1,17 +1,24 @@
-use abi_stable::std_types::{RDuration, Tuple2};
-use colored::Colorize;
-use std::collections::HashMap;
+use crate::colors::Colorize;
+use abi_stable::pointer_trait::AsPtr;
+use abi_stable::std_types::RDuration;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 
-use crate::attrs::{AttrMap, HasAttributes};
+use crate::attrs::{
+    attrs_heap_size, merge_attrs, sorted_attrs, AttrMap, Attribute, FromAttributeRelaxed,
+    HasAttributes, MergePolicy,
+};
 use crate::functions::Propagation;
-use crate::node::{new_node, Node, NodeInner};
-use crate::timeseries::{HasTimeSeries, TsMap};
+use crate::node::{new_node, without_properties, Node, NodeInner};
+use crate::timeseries::{Aggregation, HasTimeSeries, TimeLine, TsMap};
 use abi_stable::{
+    sabi_trait,
+    sabi_trait::TD_Opaque,
     std_types::{
-        RHashMap,
+        RBox, RHashMap,
         ROption::{self, RNone, RSome},
-        RString, RVec,
+        RString, RVec, Tuple2,
     },
     StableAbi,
 };
@@ -54,7 +61,7 @@ use abi_stable::{
 /// connection information, the functions that are independent to each
 /// other can be run in sequential order.
 #[repr(C)]
-#[derive(StableAbi, Default, Clone)]
+#[derive(StableAbi, Default)]
 pub struct Network {
     /// List of [`Node`]s
     pub(crate) nodes: RVec<RString>,
@@ -68,6 +75,67 @@ pub struct Network {
     pub(crate) outlet: ROption<Node>,
     /// network is ordered based on input topology
     pub(crate) ordered: bool,
+    /// Observer notified of the changes, see [`Network::set_observer`]
+    pub(crate) observer: ROption<NetworkObserverBox>,
+}
+
+/// The observer is not cloned, the clone is a separate network
+impl Clone for Network {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self.nodes.clone(),
+            nodes_map: self.nodes_map.clone(),
+            attributes: self.attributes.clone(),
+            timeseries: self.timeseries.clone(),
+            outlet: self.outlet.clone(),
+            ordered: self.ordered,
+            observer: RNone,
+        }
+    }
+}
+
+/// Changes in the [`Network`] sent to its observer, the nodes are
+/// given by their names
+#[repr(C)]
+#[derive(StableAbi, Clone, Debug, PartialEq)]
+pub enum NetworkEvent {
+    NodeAdded(RString),
+    NodeRemoved(RString),
+    /// Edge from the input to the output node
+    EdgeAdded(RString, RString),
+    EdgeRemoved(RString, RString),
+    /// Attribute of the node (or the network if `RNone`) that was
+    /// set or deleted
+    AttrChanged(ROption<RString>, RString),
+}
+
+#[sabi_trait]
+pub trait NetworkObserver: Send + Sync {
+    fn notify(&self, event: &NetworkEvent);
+}
+
+pub type NetworkObserverBox = NetworkObserver_TO<'static, RBox<()>>;
+
+struct FnObserver<F>(F);
+
+impl<F: Fn(&NetworkEvent) + Send + Sync> NetworkObserver for FnObserver<F> {
+    fn notify(&self, event: &NetworkEvent) {
+        (self.0)(event)
+    }
+}
+
+/// Order of the nodes in the network when it is loaded
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum ReorderMode {
+    /// Outlet first, and then the inputs recursively, the functions
+    /// that depend on the inputs being processed before the outputs
+    /// (e.g. `<inputsfirst>` propagation) need this order
+    #[default]
+    Topological,
+    /// Keep the order the nodes appear in the file, the connections
+    /// and levels are still set, but the input dependent
+    /// propagations will not be in the correct order
+    Preserve,
 }
 
 impl std::fmt::Debug for Network {
@@ -89,9 +157,25 @@ impl HasAttributes for Network {
         &self.attributes
     }
 
+    /// Direct access to the attributes, the changes through this are
+    /// not sent to the observer
     fn attr_map_mut(&mut self) -> &mut AttrMap {
         &mut self.attributes
     }
+
+    fn set_attr(&mut self, name: &str, val: Attribute) -> Option<Attribute> {
+        let old = self.attributes.insert(name.into(), val).into();
+        self.notify(|| NetworkEvent::AttrChanged(RNone, name.into()));
+        old
+    }
+
+    fn del_attr(&mut self, name: &str) -> Option<Attribute> {
+        let old: Option<Attribute> = self.attributes.remove(name).into();
+        if old.is_some() {
+            self.notify(|| NetworkEvent::AttrChanged(RNone, name.into()));
+        }
+        old
+    }
 }
 
 impl HasTimeSeries for Network {
@@ -123,6 +207,13 @@ impl Network {
             .map(|(s, e)| (self.nodes[s].as_str(), self.nodes[e].as_str()))
     }
 
+    /// Edges with their attributes, the attributes for the edges are
+    /// not supported in the network file yet so they are always
+    /// `None` for now
+    pub fn edges_with_attrs(&self) -> impl Iterator<Item = (&str, &str, Option<&AttrMap>)> + '_ {
+        self.edges_str().map(|(s, e)| (s, e, None))
+    }
+
     pub fn edges_ind(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
         self.nodes().filter_map(|n| {
             let n = n.lock();
@@ -141,6 +232,38 @@ impl Network {
         self.nodes.iter().rev().map(|n| &self.nodes_map[n])
     }
 
+    /// Call the function on each node in order, the node is only
+    /// locked during the call so no lock outlives the iteration
+    pub fn each_node(&self, mut f: impl FnMut(&NodeInner)) {
+        for node in self.nodes() {
+            f(&node.lock());
+        }
+    }
+
+    /// Call the function on each node in order with mutable access,
+    /// the node is only locked during the call
+    ///
+    /// The function must not change the connections (inputs and
+    /// output) of the node, the network is not updated for those
+    /// changes and will be left in an invalid state. This is checked
+    /// in debug builds.
+    pub fn each_node_mut(&self, mut f: impl FnMut(&mut NodeInner)) {
+        for node in self.nodes() {
+            let mut n = node.lock();
+            if cfg!(debug_assertions) {
+                let links = node_links(&n);
+                f(&mut n);
+                assert!(
+                    links == node_links(&n),
+                    "Connections of node {} changed inside each_node_mut",
+                    n.name()
+                );
+            } else {
+                f(&mut n);
+            }
+        }
+    }
+
     pub fn nodes_count(&self) -> usize {
         self.nodes.len()
     }
@@ -149,6 +272,89 @@ impl Network {
         let node = new_node(self.nodes_count(), name);
         self.nodes_map.insert(name.into(), node);
         self.nodes.push(name.into());
+        self.notify(|| NetworkEvent::NodeAdded(name.into()));
+    }
+
+    /// Set the function to call on the changes to the network (see
+    /// [`NetworkEvent`]), replacing the previous one
+    ///
+    /// The events are sent for the nodes added and removed by the
+    /// network methods, the edges changed along with them, and the
+    /// attributes set through [`Network::set_attr_all`] or the
+    /// [`HasAttributes`] methods of the network. The task system also
+    /// sends the events for the node attributes it assigns. The
+    /// changes made directly on the nodes (e.g.
+    /// `node.lock().set_attr(..)`) are not observed, use
+    /// [`Network::notify`] for those. Nothing is done for the events
+    /// when there is no observer.
+    ///
+    /// ```
+    /// # use nadi_core::network::{Network, NetworkEvent};
+    /// use std::sync::{Arc, Mutex};
+    /// let events = Arc::new(Mutex::new(vec![]));
+    /// let ev = events.clone();
+    /// let mut net = Network::from_edges(&[("a", "b")]);
+    /// net.set_observer(move |e| ev.lock().unwrap().push(e.clone()));
+    /// net.insert_node_by_name("c");
+    /// assert_eq!(
+    ///     events.lock().unwrap()[0],
+    ///     NetworkEvent::NodeAdded("c".into())
+    /// );
+    /// ```
+    pub fn set_observer<F>(&mut self, f: F)
+    where
+        F: Fn(&NetworkEvent) + Send + Sync + 'static,
+    {
+        self.observer = RSome(NetworkObserver_TO::from_value(FnObserver(f), TD_Opaque));
+    }
+
+    /// Remove the observer set with [`Network::set_observer`]
+    pub fn remove_observer(&mut self) {
+        self.observer = RNone;
+    }
+
+    /// Send the event to the observer, the event is only made if
+    /// there is one
+    pub fn notify(&self, event: impl FnOnce() -> NetworkEvent) {
+        if let RSome(obs) = &self.observer {
+            obs.notify(&event());
+        }
+    }
+
+    /// Create a network from the edges (input, output) given by the
+    /// node names, nodes are created as they appear in the edges
+    pub fn from_edges<S: AsRef<str>>(edges: &[(S, S)]) -> Self {
+        Self::from_edges_opts(edges, ReorderMode::Topological)
+    }
+
+    /// Create a network from the edges with the given [`ReorderMode`]
+    pub fn from_edges_opts<S: AsRef<str>>(edges: &[(S, S)], mode: ReorderMode) -> Self {
+        let mut network = Self::default();
+        for (start, end) in edges {
+            let (start, end) = (start.as_ref(), end.as_ref());
+            if !network.nodes_map.contains_key(start) {
+                network.insert_node_by_name(start);
+            }
+            if !network.nodes_map.contains_key(end) {
+                network.insert_node_by_name(end);
+            }
+            let inp = &network.nodes_map[start];
+            let out = &network.nodes_map[end];
+            {
+                inp.lock().set_output(out.clone());
+                out.lock().add_input(inp.clone());
+            }
+        }
+        match mode {
+            ReorderMode::Topological => network.reorder(),
+            ReorderMode::Preserve => {
+                network.calc_order();
+                network.set_outlet();
+                network.ordered = false;
+            }
+        }
+        network.set_levels();
+        network
     }
 
     pub fn node(&self, ind: usize) -> Option<&Node> {
@@ -165,6 +371,51 @@ impl Network {
             .ok_or_else(|| format!("Node {name} not found"))
     }
 
+    /// Set the attribute on all the nodes, returns the number of
+    /// nodes updated
+    pub fn set_attr_all(&self, name: &str, val: Attribute) -> usize {
+        for Tuple2(k, n) in &self.nodes_map {
+            n.lock().set_attr(name, val.clone());
+            self.notify(|| NetworkEvent::AttrChanged(RSome(k.clone()), name.into()));
+        }
+        self.nodes_map.len()
+    }
+
+    /// Nodes whose attribute (dotted path, see
+    /// [`HasAttributes::attr_dot`]) is equal to the given value
+    pub fn find_nodes_by_attr(&self, attr: &str, value: &Attribute) -> Vec<Node> {
+        self.nodes_by_attr(attr, value).cloned().collect()
+    }
+
+    /// Single node whose attribute is equal to the given value, it is
+    /// an error if there are no nodes or multiple nodes matching it
+    pub fn find_one_by_attr(&self, attr: &str, value: &Attribute) -> Result<&Node, String> {
+        let mut nodes = self.nodes_by_attr(attr, value);
+        match (nodes.next(), nodes.next()) {
+            (Some(n), None) => Ok(n),
+            (None, _) => Err(format!(
+                "No node with attribute {attr}={}",
+                value.to_string()
+            )),
+            (Some(n1), Some(n2)) => Err(format!(
+                "Multiple nodes with attribute {attr}={}: {}, {}{}",
+                value.to_string(),
+                n1.lock().name(),
+                n2.lock().name(),
+                if nodes.next().is_some() { ", ..." } else { "" }
+            )),
+        }
+    }
+
+    fn nodes_by_attr<'a>(
+        &'a self,
+        attr: &'a str,
+        value: &'a Attribute,
+    ) -> impl Iterator<Item = &'a Node> + 'a {
+        self.nodes()
+            .filter(move |n| matches!(n.lock().attr_dot(attr), Ok(Some(a)) if a == value))
+    }
+
     pub fn nodes_propagation(&self, prop: &Propagation) -> Result<Vec<Node>, String> {
         match prop {
             Propagation::Sequential | Propagation::OutputFirst => {
@@ -214,22 +465,118 @@ impl Network {
                 .filter(|(c, _)| *c)
                 .map(|(_, n)| n.clone())
                 .collect()),
-            Propagation::List(n) => n
-                .iter()
-                .map(|n| {
-                    self.nodes_map
-                        .get(n)
-                        .cloned()
-                        .ok_or_else(|| format!("Node {n} not found"))
-                })
-                .collect(),
+            Propagation::List(n) => {
+                let (found, missing): (Vec<_>, Vec<_>) =
+                    n.iter().partition(|n| self.nodes_map.contains_key(*n));
+                if !missing.is_empty() {
+                    return Err(missing_nodes_error(&missing));
+                }
+                Ok(found
+                    .into_iter()
+                    .map(|n| self.nodes_map[n].clone())
+                    .collect())
+            }
             Propagation::Path(p) => self.nodes_path(p),
+            Propagation::SortedBy(attr, asc) => {
+                let mut nodes: Vec<(Option<f64>, Node)> = self
+                    .nodes()
+                    .map(|n| (n.lock().try_attr_relaxed::<f64>(attr).ok(), n.clone()))
+                    .collect();
+                nodes.sort_by(|(a, _), (b, _)| match (a, b) {
+                    (Some(a), Some(b)) if *asc => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+                    (Some(a), Some(b)) => b.partial_cmp(a).unwrap_or(Ordering::Equal),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                });
+                Ok(nodes.into_iter().map(|(_, n)| n).collect())
+            }
+            Propagation::Upstream(name) => {
+                fn insert_node(n: &Node, names: &mut HashSet<String>) {
+                    let n = n.lock();
+                    names.insert(n.name().to_string());
+                    for i in n.inputs() {
+                        insert_node(i, names);
+                    }
+                }
+                let mut names = HashSet::new();
+                insert_node(self.try_node_by_name(name)?, &mut names);
+                Ok(self
+                    .nodes()
+                    .filter(|n| names.contains(n.lock().name()))
+                    .cloned()
+                    .collect())
+            }
+            Propagation::Downstream(name) => {
+                let mut names = HashSet::new();
+                let mut curr = self.try_node_by_name(name)?.clone();
+                loop {
+                    names.insert(curr.lock().name().to_string());
+                    let next = curr.lock().output().cloned();
+                    match next {
+                        RSome(o) => curr = o,
+                        RNone => break,
+                    }
+                }
+                Ok(self
+                    .nodes()
+                    .filter(|n| names.contains(n.lock().name()))
+                    .cloned()
+                    .collect())
+            }
+            Propagation::Intersect(a, b) => {
+                let names = self.propagation_names(b)?;
+                let mut nodes = self.nodes_propagation(a)?;
+                nodes.retain(|n| names.contains(n.lock().name()));
+                Ok(nodes)
+            }
+            Propagation::Difference(a, b) => {
+                let names = self.propagation_names(b)?;
+                let mut nodes = self.nodes_propagation(a)?;
+                nodes.retain(|n| !names.contains(n.lock().name()));
+                Ok(nodes)
+            }
+            Propagation::Union(a, b) => {
+                let mut nodes = self.nodes_propagation(a)?;
+                let mut names: HashSet<String> =
+                    nodes.iter().map(|n| n.lock().name().to_string()).collect();
+                for n in self.nodes_propagation(b)? {
+                    let name = n.lock().name().to_string();
+                    if names.insert(name) {
+                        nodes.push(n);
+                    }
+                }
+                Ok(nodes)
+            }
         }
     }
 
+    /// Names of the nodes selected by the propagation
+    fn propagation_names(&self, prop: &Propagation) -> Result<HashSet<String>, String> {
+        Ok(self
+            .nodes_propagation(prop)?
+            .iter()
+            .map(|n| n.lock().name().to_string())
+            .collect())
+    }
+
     pub fn nodes_path(&self, path: &StrPath) -> Result<Vec<Node>, String> {
-        let start = self.try_node_by_name(path.start.as_str())?;
-        let end = self.try_node_by_name(path.end.as_str())?;
+        let (start, end) = match (
+            self.node_by_name(path.start.as_str()),
+            self.node_by_name(path.end.as_str()),
+        ) {
+            (Some(s), Some(e)) => (s, e),
+            (s, e) => {
+                let mut missing = vec![];
+                if s.is_none() {
+                    missing.push(&path.start);
+                }
+                if e.is_none() {
+                    missing.push(&path.end);
+                }
+                return Err(missing_nodes_error(&missing));
+            }
+        };
         // we'll assume the network is indexed based on order, small
         // indices are closer to outlet; and resuffle the nodes
         // let (start, end) = if start.lock().index() > end.lock().index() {
@@ -291,8 +638,8 @@ impl Network {
         }
     }
 
-    pub fn reorder(&mut self) {
-        self.calc_order();
+    /// Set the outlet by following the outputs from the first node
+    fn set_outlet(&mut self) {
         self.outlet = self
             .node(0)
             .cloned()
@@ -307,6 +654,109 @@ impl Network {
                 }
             })
             .into();
+    }
+
+    /// Number of nodes without an output, a valid network has a
+    /// single outlet
+    pub fn outlet_count(&self) -> usize {
+        self.nodes().filter(|n| n.lock().output().is_none()).count()
+    }
+
+    /// JSON document with the nodes, edges and the network
+    /// attributes, the timeseries are only summarized (name, type and
+    /// length), see [`Network::to_json_opts`] to include the values
+    ///
+    /// The document is of the form `{"nodes": [{"name", "index",
+    /// "level", "attributes", "timeseries"}, ..], "edges": [[input,
+    /// output], ..], "attributes": {..}, "timeseries": [..]}`
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, String> {
+        self.to_json_opts(false)
+    }
+
+    /// Same as [`Network::to_json`], and includes the values of the
+    /// timeseries if `ts_values` is true
+    #[cfg(feature = "serde")]
+    pub fn to_json_opts(&self, ts_values: bool) -> Result<String, String> {
+        use serde_json::{json, Value};
+
+        fn attrs_json(map: &AttrMap) -> Value {
+            Value::from(&Attribute::Table(map.clone()))
+        }
+        fn ts_json(map: &TsMap, values: bool) -> Value {
+            let mut names: Vec<&RString> = map.keys().collect();
+            names.sort();
+            names
+                .into_iter()
+                .map(|name| {
+                    let ts = &map[name];
+                    let mut obj = json!({
+                        "name": name.as_str(),
+                        "type": ts.values_type(),
+                        "length": ts.len(),
+                    });
+                    if values {
+                        obj["values"] = ts.values_as_attributes().iter().map(Value::from).collect();
+                    }
+                    obj
+                })
+                .collect()
+        }
+        let nodes: Vec<Value> = self
+            .nodes()
+            .map(|n| {
+                let n = n.lock();
+                json!({
+                    "name": n.name(),
+                    "index": n.index(),
+                    "level": n.level(),
+                    "attributes": attrs_json(n.attr_map()),
+                    "timeseries": ts_json(n.ts_map(), ts_values),
+                })
+            })
+            .collect();
+        let edges: Vec<[&str; 2]> = self.edges_str().map(|(a, b)| [a, b]).collect();
+        let doc = json!({
+            "nodes": nodes,
+            "edges": edges,
+            "attributes": attrs_json(&self.attributes),
+            "timeseries": ts_json(&self.timeseries, ts_values),
+        });
+        serde_json::to_string_pretty(&doc).map_err(|e| e.to_string())
+    }
+
+    /// Approximate heap bytes used by the attributes of the network
+    /// and all of its nodes, see [`crate::attrs::attrs_heap_size`]
+    pub fn attrs_heap_size(&self) -> usize {
+        let mut size = attrs_heap_size(&self.attributes);
+        self.each_node(|n| size += n.attrs_heap_size());
+        size
+    }
+
+    /// Summary of the network structure, see [`NetworkReport`]
+    pub fn report(&self) -> NetworkReport {
+        let mut report = NetworkReport {
+            node_count: self.nodes_count(),
+            edge_count: self.edges_ind().count(),
+            ordered: self.ordered,
+            ..Default::default()
+        };
+        for node in self.nodes() {
+            let node = node.lock();
+            if node.output().is_none() {
+                report.outlets.push(node.name().to_string());
+            }
+            if node.inputs().is_empty() {
+                report.leaf_count += 1;
+            }
+            report.max_level = report.max_level.max(node.level());
+        }
+        report.outlet_count = report.outlets.len();
+        report
+    }
+
+    /// Nodes upstream of the outlet (including it) in the topological order
+    fn outlet_component(&self) -> Vec<Node> {
         let mut new_nodes: Vec<Node> = Vec::with_capacity(self.nodes.len());
         fn insert_node(nv: &mut Vec<Node>, n: Node) {
             nv.push(n.clone());
@@ -319,14 +769,70 @@ impl Network {
         if let RSome(out) = &self.outlet {
             insert_node(&mut new_nodes, out.clone());
         }
+        new_nodes
+    }
+
+    /// Reorder the network starting from the given outlet instead of
+    /// the outlet of the first node like [`Network::reorder`]
+    ///
+    /// If the network is disconnected, the nodes of the outlet's
+    /// component are placed first in topological order, followed by
+    /// the rest in their current order; the network is not marked as
+    /// ordered in that case.
+    pub fn reorder_from(&mut self, outlet_name: &str) -> Result<(), String> {
+        let outlet = self
+            .node_by_name(outlet_name)
+            .ok_or_else(|| format!("Node {outlet_name} not found"))?
+            .clone();
+        if outlet.lock().output().is_some() {
+            return Err(format!("Node {outlet_name} is not an outlet"));
+        }
+        self.calc_order();
+        self.outlet = RSome(outlet);
+        let mut new_nodes: Vec<RString> = self
+            .outlet_component()
+            .iter()
+            .map(|n| n.lock().name().into())
+            .collect();
+        self.ordered = new_nodes.len() == self.nodes.len();
+        if !self.ordered {
+            let included: HashSet<RString> = new_nodes.iter().cloned().collect();
+            new_nodes.extend(
+                self.nodes
+                    .iter()
+                    .filter(|n| !included.contains(*n))
+                    .cloned(),
+            );
+        }
+        self.nodes = new_nodes.into();
+        self.reindex();
+        Ok(())
+    }
+
+    pub fn reorder(&mut self) {
+        self.calc_order();
+        self.set_outlet();
+        let new_nodes = self.outlet_component();
         if new_nodes.len() < self.nodes.len() {
             // todo, make the nodes into different groups?
-            eprintln!(
-                "Reorder not done, the nodes are not connected: {} connected out of {}",
-                new_nodes.len(),
-                self.nodes.len()
-            );
+            let outlets = self.outlet_count();
+            if outlets > 1 {
+                eprintln!(
+                    "Reorder not done, the network has {outlets} outlets: {} connected out of {}",
+                    new_nodes.len(),
+                    self.nodes.len()
+                );
+            } else {
+                eprintln!(
+                    "Reorder not done, the nodes are not connected: {} connected out of {}",
+                    new_nodes.len(),
+                    self.nodes.len()
+                );
+            }
             self.ordered = false;
+            // keep the current order, but make sure the indices
+            // match it so the nodes can still be looked up by index
+            self.reindex();
             return;
         }
         self.nodes = new_nodes
@@ -364,6 +870,31 @@ impl Network {
         }
     }
 
+    /// Length of the longest path (number of edges) from the outlet
+    /// to any of the leaf nodes
+    pub fn depth(&self) -> u64 {
+        let mut stack: Vec<(Node, u64)> = match &self.outlet {
+            RSome(output) => vec![(output.clone(), 0)],
+            RNone => return 0,
+        };
+        let mut depth = 0;
+        while let Some((node, d)) = stack.pop() {
+            depth = depth.max(d);
+            stack.extend(node.lock().inputs().iter().map(|i| (i.clone(), d + 1)));
+        }
+        depth
+    }
+
+    /// Maximum number of nodes in any of the levels (see
+    /// [`Network::set_levels`])
+    pub fn width(&self) -> usize {
+        let mut counts: HashMap<u64, usize> = HashMap::new();
+        for node in self.nodes() {
+            *counts.entry(node.lock().level()).or_default() += 1;
+        }
+        counts.into_values().max().unwrap_or(0)
+    }
+
     fn remove_node_single(&mut self, node: &Node) {
         let n = node.lock();
         let ind = n.index();
@@ -375,30 +906,445 @@ impl Network {
                 .lock()
                 .inputs()
                 .iter()
-                .position(|i| i.lock().index() == ind)
+                // the node itself is locked, so compare the pointers
+                .position(|i| AsPtr::as_ptr(i) == AsPtr::as_ptr(node))
                 .expect("Node should be in input list of output");
             out.lock().inputs_mut().remove(pos);
+            let out_name = || RString::from(out.lock().name());
+            self.notify(|| NetworkEvent::EdgeRemoved(n.name().into(), out_name()));
             for inp in n.inputs() {
                 inp.lock().set_output(out.clone());
                 out.lock().add_input(inp.clone());
+                let inp_name = || RString::from(inp.lock().name());
+                self.notify(|| NetworkEvent::EdgeRemoved(inp_name(), n.name().into()));
+                self.notify(|| NetworkEvent::EdgeAdded(inp_name(), out_name()));
             }
         } else {
             for inp in n.inputs() {
                 inp.lock().unset_output();
+                self.notify(|| {
+                    NetworkEvent::EdgeRemoved(inp.lock().name().into(), n.name().into())
+                });
             }
             if n.inputs().len() > 1 {
                 eprintln!("WARN: Node with multiple inputs and no output Removed");
             }
         }
+        self.notify(|| NetworkEvent::NodeRemoved(n.name().into()));
         self.reindex();
     }
 
+    /// New network with the direction of all the edges reversed, the
+    /// nodes are cloned along with their attributes and timeseries.
+    ///
+    /// The network can only have a single outlet and each node can
+    /// only have one output, so only the networks without branches
+    /// (each node has at most one input) can be reversed. The source
+    /// of the original network becomes the outlet of the reversed
+    /// one, and the order and levels are recalculated from it.
+    pub fn reverse(&self) -> Result<Network, String> {
+        if let Some(n) = self.nodes().find(|n| n.lock().inputs().len() > 1) {
+            return Err(format!(
+                "Node {} has multiple inputs, branched network cannot be reversed",
+                n.lock().name()
+            ));
+        }
+        let mut network = Network {
+            attributes: self.attributes.clone(),
+            timeseries: self.timeseries.clone(),
+            ..Default::default()
+        };
+        for node in self.nodes() {
+            let node = node.lock();
+            network.insert_node_by_name(node.name());
+            let mut new = network.nodes_map[node.name()].lock();
+            new.attributes = node.attributes.clone();
+            new.timeseries = node.timeseries.clone();
+        }
+        for (start, end) in self.edges_str() {
+            let inp = &network.nodes_map[end];
+            let out = &network.nodes_map[start];
+            inp.lock().set_output(out.clone());
+            out.lock().add_input(inp.clone());
+        }
+        network.reorder();
+        network.set_levels();
+        Ok(network)
+    }
+
+    /// Compact representation of the network as the node names and
+    /// the index of the output (parent) of each node, `-1` for the
+    /// outlet
+    pub fn to_parent_array(&self) -> (Vec<String>, Vec<i64>) {
+        let names: Vec<String> = self.node_names().map(String::from).collect();
+        let index: HashMap<&str, i64> = self
+            .node_names()
+            .enumerate()
+            .map(|(i, n)| (n, i as i64))
+            .collect();
+        let parents = self
+            .nodes()
+            .map(|n| match n.lock().output() {
+                RSome(o) => index[o.lock().name()],
+                RNone => -1,
+            })
+            .collect();
+        (names, parents)
+    }
+
+    /// Network from the node names and their parent indices, see
+    /// [`Network::to_parent_array`]
+    ///
+    /// # Errors
+    /// Errors if the lengths don't match, names are repeated, an
+    /// index is out of range, the edges make a cycle, or there isn't
+    /// exactly one outlet (`-1`).
+    pub fn from_parent_array<S: AsRef<str>>(names: &[S], parents: &[i64]) -> Result<Self, String> {
+        if names.len() != parents.len() {
+            return Err(format!(
+                "Number of names ({}) and parents ({}) are different",
+                names.len(),
+                parents.len()
+            ));
+        }
+        let mut unique = HashSet::new();
+        if let Some(n) = names.iter().find(|n| !unique.insert(n.as_ref())) {
+            return Err(format!("Node {} is repeated", n.as_ref()));
+        }
+        let outlets = parents.iter().filter(|p| **p == -1).count();
+        if outlets != 1 {
+            return Err(format!(
+                "Parent array should have exactly one outlet (-1), found {outlets}"
+            ));
+        }
+        let mut builder = NetworkBuilder::new();
+        for (name, parent) in names.iter().zip(parents) {
+            if *parent == -1 {
+                continue;
+            }
+            let out = usize::try_from(*parent)
+                .ok()
+                .and_then(|p| names.get(p))
+                .ok_or_else(|| format!("Invalid parent {parent} for node {}", name.as_ref()))?;
+            builder.add_edge(name.as_ref(), out.as_ref())?;
+        }
+        for name in names {
+            if !builder.network.nodes_map.contains_key(name.as_ref()) {
+                builder.network.insert_node_by_name(name.as_ref());
+            }
+        }
+        Ok(builder.finish())
+    }
+
+    /// Check if both networks have the same set of nodes and edges
+    /// based on the node names, irrespective of the node order
+    pub fn same_topology(&self, other: &Network) -> bool {
+        self.nodes_count() == other.nodes_count()
+            && self.node_names().collect::<HashSet<_>>()
+                == other.node_names().collect::<HashSet<_>>()
+            && self.edges_str().collect::<HashSet<_>>() == other.edges_str().collect::<HashSet<_>>()
+    }
+
+    /// Check if both networks have the same topology (see
+    /// [`Network::same_topology`]), with the same network attributes
+    /// and same attributes on the nodes of the same name
+    ///
+    /// Timeseries and the [`crate::node::NODE_PROPERTIES`] of the
+    /// nodes are not compared.
+    pub fn deep_eq(&self, other: &Network) -> bool {
+        if !self.same_topology(other) || self.attributes != other.attributes {
+            return false;
+        }
+        self.nodes_map.iter().all(|Tuple2(name, node)| {
+            let onode = &other.nodes_map[name];
+            // same node can't be locked twice
+            node.as_ptr() == onode.as_ptr()
+                || without_properties(node.lock().attr_map())
+                    == without_properties(onode.lock().attr_map())
+        })
+    }
+
+    /// Remove the leaf nodes (nodes without inputs) whose attribute
+    /// satisfies the comparison (`<`, `<=`, `>`, `>=`, `==`, `!=`)
+    /// with the threshold, repeatedly until no more leaves qualify.
+    ///
+    /// Only the leaves are removed so that the network stays
+    /// connected, nodes without the attribute and the outlet are
+    /// never removed. Returns the number of nodes removed.
+    pub fn prune_leaves(&mut self, attr: &str, threshold: f64, op: &str) -> Result<usize, String> {
+        let cmp: fn(f64, f64) -> bool = match op {
+            "<" => |a, b| a < b,
+            "<=" => |a, b| a <= b,
+            ">" => |a, b| a > b,
+            ">=" => |a, b| a >= b,
+            "==" => |a, b| a == b,
+            "!=" => |a, b| a != b,
+            _ => return Err(format!("Invalid comparison operator {op}")),
+        };
+        let mut removed = 0;
+        loop {
+            let leaves = self
+                .nodes()
+                .filter(|n| {
+                    let n = n.lock();
+                    n.inputs().is_empty()
+                        && n.output().is_some()
+                        && n.attr(attr)
+                            .and_then(f64::from_attr_relaxed)
+                            .map(|v| cmp(v, threshold))
+                            .unwrap_or(false)
+                })
+                .cloned()
+                .collect::<Vec<Node>>();
+            if leaves.is_empty() {
+                break;
+            }
+            removed += leaves.len();
+            for leaf in &leaves {
+                self.remove_node_single(leaf);
+            }
+        }
+        if removed > 0 {
+            self.reorder();
+            self.set_levels();
+        }
+        Ok(removed)
+    }
+
+    /// Calculate the cumulative distance to the outlet for each node
+    /// by adding the `length_attr` of the nodes along the path, and
+    /// set it as `out_attr`. The `length_attr` of a node is the
+    /// length from that node to its output, missing lengths are an
+    /// error unless `missing_zero` is true.
+    pub fn downstream_distance(
+        &self,
+        length_attr: &str,
+        out_attr: &str,
+        missing_zero: bool,
+    ) -> Result<(), String> {
+        let mut dists = HashMap::<String, f64>::with_capacity(self.nodes.len());
+        for node in self.nodes() {
+            // walk downstream until a node with known distance (or
+            // the outlet), keeping the lengths along the way
+            let mut path: Vec<(String, f64)> = vec![];
+            let mut current = node.clone();
+            let mut dist = loop {
+                let next = {
+                    let n = current.lock();
+                    if let Some(d) = dists.get(n.name()) {
+                        break *d;
+                    }
+                    let out = match n.output() {
+                        RSome(out) => out.clone(),
+                        RNone => {
+                            dists.insert(n.name().to_string(), 0.0);
+                            break 0.0;
+                        }
+                    };
+                    let len = match n.attr(length_attr) {
+                        Some(a) => f64::try_from_attr_relaxed(a)
+                            .map_err(|e| format!("Node {}: {e}", n.name()))?,
+                        None if missing_zero => 0.0,
+                        None => {
+                            return Err(format!(
+                                "Node {}: Attribute {length_attr} not found",
+                                n.name()
+                            ))
+                        }
+                    };
+                    path.push((n.name().to_string(), len));
+                    out
+                };
+                current = next;
+            };
+            while let Some((name, len)) = path.pop() {
+                dist += len;
+                dists.insert(name, dist);
+            }
+            node.lock().set_attr(out_attr, Attribute::Float(dist));
+        }
+        Ok(())
+    }
+
     pub fn remove_node(&mut self, node: &Node) {
         self.remove_node_single(node);
         self.reorder();
         self.set_levels();
     }
 
+    /// Remove the node and merge its attributes into its output node
+    /// based on the [`MergePolicy`], the inputs of the node are
+    /// connected to the output. The outlet cannot be contracted as
+    /// it doesn't have an output. The [`crate::node::NODE_PROPERTIES`]
+    /// of the node are not merged.
+    pub fn contract_node(&mut self, name: &str, merge: MergePolicy) -> Result<(), String> {
+        let node = self.try_node_by_name(name)?.clone();
+        let (output, attrs) = {
+            let n = node.lock();
+            match n.output() {
+                RSome(o) => (o.clone(), without_properties(n.attr_map())),
+                RNone => return Err(format!("Node {name} has no output, cannot be contracted")),
+            }
+        };
+        merge_attrs(output.lock().attr_map_mut(), &attrs, merge)?;
+        self.remove_node(&node);
+        Ok(())
+    }
+
+    /// Merge the nodes and edges of the other network into this one
+    ///
+    /// Nodes are matched by name and their attributes (along with the
+    /// network attributes) are merged based on the [`MergePolicy`],
+    /// except the [`crate::node::NODE_PROPERTIES`] that are set from
+    /// the merged network; the timeseries not present in this network
+    /// are added. The
+    /// edges of both networks are combined and checked like in
+    /// [`NetworkBuilder`], so a node with different outputs in the
+    /// two networks, or an edge that makes a cycle is an error. The
+    /// network is not modified on error. The nodes and edges that were
+    /// not in this network are sent to the observer as added.
+    pub fn merge(&mut self, other: Network, policy: MergePolicy) -> Result<(), String> {
+        let mut builder = NetworkBuilder::new();
+        let mut edges = HashSet::new();
+        for (start, end) in self.edges_str().chain(other.edges_str()) {
+            if edges.insert((start, end)) {
+                builder.add_edge(start, end)?;
+            }
+        }
+        let mut network = builder.network;
+        for net in [&*self, &other] {
+            for node in net.nodes() {
+                let node = node.lock();
+                if !network.nodes_map.contains_key(node.name()) {
+                    network.insert_node_by_name(node.name());
+                }
+                let mut new = network.nodes_map[node.name()].lock();
+                merge_attrs(
+                    &mut new.attributes,
+                    &without_properties(&node.attributes),
+                    policy,
+                )
+                .map_err(|e| format!("Node {}: {e}", node.name()))?;
+                for Tuple2(k, v) in &node.timeseries {
+                    if !new.timeseries.contains_key(k) {
+                        new.timeseries.insert(k.clone(), v.clone());
+                    }
+                }
+            }
+            merge_attrs(&mut network.attributes, &net.attributes, policy)
+                .map_err(|e| format!("Network: {e}"))?;
+            for Tuple2(k, v) in &net.timeseries {
+                if !network.timeseries.contains_key(k) {
+                    network.timeseries.insert(k.clone(), v.clone());
+                }
+            }
+        }
+        network.reorder();
+        network.set_levels();
+        if self.observer.is_some() {
+            for name in network.node_names() {
+                if self.node_by_name(name).is_none() {
+                    self.notify(|| NetworkEvent::NodeAdded(name.into()));
+                }
+            }
+            let old_edges: HashSet<(&str, &str)> = self.edges_str().collect();
+            for (start, end) in network.edges_str() {
+                if !old_edges.contains(&(start, end)) {
+                    self.notify(|| NetworkEvent::EdgeAdded(start.into(), end.into()));
+                }
+            }
+        }
+        network.observer = std::mem::replace(&mut self.observer, RNone);
+        *self = network;
+        Ok(())
+    }
+
+    /// Align the timeseries `name` of all the nodes to a common
+    /// timeline and return it
+    ///
+    /// The common timeline covers the period where all the series
+    /// overlap, and its step is the largest step among them. Each
+    /// series is sliced to that period and resampled (with
+    /// [`Aggregation::Mean`]) if its step is smaller, then they all
+    /// share the same timeline.
+    ///
+    /// # Errors
+    /// Errors if any of the nodes doesn't have the timeseries, the
+    /// timeseries are not regular, the steps are not multiples of
+    /// each other, the time points can't be aligned, or there is no
+    /// common period. The timeseries are not modified on error.
+    pub fn align_timeseries(&mut self, name: &str) -> Result<TimeLine, String> {
+        let missing: Vec<String> = self
+            .nodes()
+            .map(|n| n.lock())
+            .filter(|n| n.ts(name).is_none())
+            .map(|n| n.name().to_string())
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "Timeseries `{name}` not found in nodes: {}",
+                missing.join(", ")
+            ));
+        }
+        // (start, end, step) of each node's timeseries
+        let mut periods = Vec::with_capacity(self.nodes_count());
+        for node in self.nodes() {
+            let node = node.lock();
+            let tl = node.try_ts(name)?.timeline().lock();
+            if !tl.regular() {
+                return Err(format!(
+                    "Timeseries `{name}` of node {} is not regular",
+                    node.name()
+                ));
+            }
+            periods.push((tl.start(), tl.end(), tl.step()));
+        }
+        let start = periods
+            .iter()
+            .map(|p| p.0)
+            .max()
+            .ok_or("No nodes in the network")?;
+        let step = periods.iter().map(|p| p.2).max().unwrap_or(1);
+        // number of full steps that fit in the common period
+        let mut count = i64::MAX;
+        for (node, (s, e, st)) in self.nodes().zip(&periods) {
+            if step % st != 0 || (start - s) % st != 0 {
+                return Err(format!(
+                    "Timeseries `{name}` of node {} can't be aligned to start {start} with step {step}",
+                    node.lock().name()
+                ));
+            }
+            count = count.min((e - start + st).div_euclid(step));
+        }
+        if count <= 0 {
+            return Err(format!("Timeseries `{name}` has no common period"));
+        }
+        let mut aligned = Vec::with_capacity(self.nodes_count());
+        for (node, (s, _, st)) in self.nodes().zip(&periods) {
+            let node = node.lock();
+            let n = (step / st) as usize;
+            let first = ((start - s) / st) as usize;
+            let ts = node
+                .try_ts(name)?
+                .slice(first, first + count as usize * n)?;
+            aligned.push(if n > 1 {
+                ts.resample(n, Aggregation::Mean)?
+            } else {
+                ts
+            });
+        }
+        // all the series are checked before any of them is written
+        let timeline = aligned[0].timeline().clone();
+        for (node, ts) in self.nodes().zip(aligned.iter_mut()) {
+            ts.share_timeline(&timeline)
+                .map_err(|e| format!("Timeseries `{name}` of node {}: {e}", node.lock().name()))?;
+        }
+        for (node, ts) in self.nodes().zip(aligned) {
+            node.lock().set_ts(name, ts);
+        }
+        Ok(timeline)
+    }
+
     pub fn connections_utf8(&self) -> Vec<String> {
         self.nodes()
             .map(|node| {
@@ -471,8 +1417,9 @@ impl ToString for StrPath {
                 "{} -> {} [{}]",
                 self.start,
                 self.end,
-                a.iter()
-                    .map(|Tuple2(k, v)| format!("{}={}", k, v.to_string()))
+                sorted_attrs(a)
+                    .into_iter()
+                    .map(|(k, v)| format!("{}={}", k, v.to_string()))
                     .collect::<Vec<String>>()
                     .join(", ")
             )
@@ -500,8 +1447,38 @@ impl StrPath {
     }
 }
 
+fn missing_nodes_error(names: &[&RString]) -> String {
+    match names {
+        [n] => format!("Node {n} not found"),
+        _ => format!(
+            "Nodes not found: {}",
+            names
+                .iter()
+                .map(|n| n.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Pointers to the inputs (sorted) and the output of the node, used
+/// to check if the connections have changed
+fn node_links(node: &NodeInner) -> (Vec<*const ()>, Option<*const ()>) {
+    let mut inputs: Vec<*const ()> = node.inputs().iter().map(|i| i.as_ptr().cast()).collect();
+    inputs.sort();
+    (inputs, node.output().map(|o| o.as_ptr().cast()).into())
+}
+
+/// Compare nodes by their order, ties are broken by the node name so
+/// the sorting is deterministic
 fn compare_node_order(n1: &Node, n2: &Node) -> std::cmp::Ordering {
-    n1.lock().order().partial_cmp(&n2.lock().order()).unwrap()
+    let (o1, name1) = {
+        let n = n1.lock();
+        (n.order(), n.name().to_string())
+    };
+    let n2 = n2.lock();
+    o1.cmp(&n2.order())
+        .then_with(|| name1.as_str().cmp(n2.name()))
 }
 
 /// Take any [`Node`] and create [`Network`] with it as the outlet.
@@ -524,6 +1501,13 @@ impl From<Node> for Network {
             }
         }
         insert_node(&node, &mut nodes);
+        // nodes are in the order they were visited (inputs first) so
+        // it's well defined even if the reorder fails
+        net.nodes = nodes
+            .iter()
+            .map(|n| RString::from(n.lock().name()))
+            .collect::<Vec<_>>()
+            .into();
         net.nodes_map = nodes
             .into_iter()
             .map(|n| {
@@ -532,15 +1516,830 @@ impl From<Node> for Network {
             })
             .collect::<HashMap<RString, Node>>()
             .into();
-        net.nodes = net
-            .nodes_map
-            .keys()
-            .map(|s| s.clone())
-            .collect::<Vec<_>>()
-            .into();
+        net.reindex();
         net.outlet = RSome(node);
         net.reorder();
         net.set_levels();
         net
     }
 }
+
+/// Summary of the [`Network`] structure, from [`Network::report`]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NetworkReport {
+    pub node_count: usize,
+    pub edge_count: usize,
+    /// Number of nodes without an output, should be 1 for a valid
+    /// network
+    pub outlet_count: usize,
+    /// Number of nodes without any inputs
+    pub leaf_count: usize,
+    /// Highest level of the nodes (see [`Network::set_levels`])
+    pub max_level: u64,
+    /// Network is ordered based on the input topology
+    pub ordered: bool,
+    /// Names of the nodes without an output
+    pub outlets: Vec<String>,
+}
+
+impl NetworkReport {
+    pub fn to_colored_string(&self) -> String {
+        let rows = [
+            ("Nodes", self.node_count.to_string().blue()),
+            ("Edges", self.edge_count.to_string().blue()),
+            ("Outlets", self.outlet_count.to_string().blue()),
+            ("Leaves", self.leaf_count.to_string().blue()),
+            ("Max Level", self.max_level.to_string().blue()),
+            ("Ordered", self.ordered.to_string().magenta()),
+        ];
+        let mut text: Vec<String> = rows
+            .iter()
+            .map(|(k, v)| format!("{}: {v}", k.green()))
+            .collect();
+        text.push(format!(
+            "{}: {}",
+            "Outlet Nodes".green(),
+            self.outlets
+                .iter()
+                .map(|o| o.red().to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        ));
+        text.join("\n")
+    }
+}
+
+/// Build the [`Network`] one edge at a time, each edge is checked as
+/// it's added so the edge that makes the network invalid is rejected
+/// right away instead of causing problems in [`Network::reorder`]
+#[derive(Default)]
+pub struct NetworkBuilder {
+    network: Network,
+}
+
+impl NetworkBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the edge from `start` to `end`, the nodes are created if
+    /// they don't exist yet. The edge is rejected if `start` already
+    /// has an output, or if it makes a cycle
+    pub fn add_edge(&mut self, start: &str, end: &str) -> Result<(), String> {
+        if start == end {
+            return Err(format!("Edge {start} -> {end} connects node to itself"));
+        }
+        if let Some(node) = self.network.nodes_map.get(start) {
+            if let RSome(out) = node.lock().output() {
+                return Err(format!(
+                    "Edge {start} -> {end} rejected, node {start} already has output {}",
+                    out.lock().name()
+                ));
+            }
+        }
+        // the edge makes a cycle if `start` is downstream of `end`
+        if let Some(node) = self.network.nodes_map.get(end) {
+            let mut curr = node.clone();
+            loop {
+                let next = curr.lock().output().cloned();
+                match next {
+                    RSome(o) => {
+                        if o.lock().name() == start {
+                            return Err(format!(
+                                "Edge {start} -> {end} rejected, it makes a cycle"
+                            ));
+                        }
+                        curr = o;
+                    }
+                    RNone => break,
+                }
+            }
+        }
+        for name in [start, end] {
+            if !self.network.nodes_map.contains_key(name) {
+                self.network.insert_node_by_name(name);
+            }
+        }
+        let inp = &self.network.nodes_map[start];
+        let out = &self.network.nodes_map[end];
+        inp.lock().set_output(out.clone());
+        out.lock().add_input(inp.clone());
+        Ok(())
+    }
+
+    /// Number of nodes added so far
+    pub fn nodes_count(&self) -> usize {
+        self.network.nodes_count()
+    }
+
+    /// Reorder the nodes and set the levels to get the [`Network`]
+    pub fn finish(self) -> Network {
+        let mut network = self.network;
+        network.reorder();
+        network.set_levels();
+        network
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn observer_test() {
+        use std::sync::{Arc, Mutex};
+        let events = Arc::new(Mutex::new(vec![]));
+        let ev = events.clone();
+        let mut net = Network::from_edges(&[("a", "b"), ("c", "b"), ("b", "d")]);
+        net.set_observer(move |e| ev.lock().unwrap().push(e.clone()));
+        net.insert_node_by_name("e");
+        net.set_attr("x", Attribute::Integer(1));
+        net.del_attr("x");
+        net.del_attr("y");
+        assert_eq!(net.set_attr_all("y", Attribute::Bool(true)), 5);
+        let b = net.try_node_by_name("b").unwrap().clone();
+        net.remove_node(&b);
+
+        let ev = std::mem::take(&mut *events.lock().unwrap());
+        let s = |n: &str| RString::from(n);
+        assert_eq!(ev.len(), 14);
+        assert_eq!(ev[0], NetworkEvent::NodeAdded(s("e")));
+        assert_eq!(ev[1], NetworkEvent::AttrChanged(RNone, s("x")));
+        assert_eq!(ev[2], NetworkEvent::AttrChanged(RNone, s("x")));
+        let attr_nodes: HashSet<String> = ev[3..8]
+            .iter()
+            .map(|e| match e {
+                NetworkEvent::AttrChanged(RSome(n), k) if k.as_str() == "y" => n.to_string(),
+                e => panic!("Unexpected event {e:?}"),
+            })
+            .collect();
+        assert_eq!(attr_nodes.len(), 5);
+        for e in [
+            NetworkEvent::EdgeRemoved(s("b"), s("d")),
+            NetworkEvent::EdgeRemoved(s("a"), s("b")),
+            NetworkEvent::EdgeRemoved(s("c"), s("b")),
+            NetworkEvent::EdgeAdded(s("a"), s("d")),
+            NetworkEvent::EdgeAdded(s("c"), s("d")),
+        ] {
+            assert!(ev[8..13].contains(&e), "{e:?}");
+        }
+        assert_eq!(ev[13], NetworkEvent::NodeRemoved(s("b")));
+
+        // a -> d, c -> d; contracting a connects nothing new
+        net.contract_node("a", MergePolicy::Overwrite).unwrap();
+        let ev = std::mem::take(&mut *events.lock().unwrap());
+        assert_eq!(
+            ev,
+            vec![
+                NetworkEvent::EdgeRemoved(s("a"), s("d")),
+                NetworkEvent::NodeRemoved(s("a")),
+            ]
+        );
+
+        let other = Network::from_edges(&[("f", "c"), ("e", "d")]);
+        net.merge(other, MergePolicy::Overwrite).unwrap();
+        let ev = std::mem::take(&mut *events.lock().unwrap());
+        assert_eq!(ev.len(), 3);
+        for e in [
+            NetworkEvent::NodeAdded(s("f")),
+            NetworkEvent::EdgeAdded(s("f"), s("c")),
+            NetworkEvent::EdgeAdded(s("e"), s("d")),
+        ] {
+            assert!(ev.contains(&e), "{e:?}");
+        }
+
+        // the clone doesn't have the observer
+        let mut other = net.clone();
+        other.insert_node_by_name("g");
+        net.remove_observer();
+        net.insert_node_by_name("h");
+        net.set_attr("z", Attribute::Integer(1));
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[rstest]
+    fn find_by_attr_test() {
+        let net = Network::from_edges(&[("a", "b"), ("b", "c"), ("d", "c")]);
+        for (name, basin) in [("a", "x"), ("b", "y"), ("c", "y")] {
+            net.try_node_by_name(name)
+                .unwrap()
+                .lock()
+                .set_attr("basin", Attribute::String(basin.into()));
+        }
+        let x = Attribute::String("x".into());
+        let y = Attribute::String("y".into());
+        let names = |nodes: Vec<Node>| {
+            let mut names: Vec<String> =
+                nodes.iter().map(|n| n.lock().name().to_string()).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(net.find_nodes_by_attr("basin", &y)), vec!["b", "c"]);
+        assert!(net
+            .find_nodes_by_attr("basin", &Attribute::Integer(1))
+            .is_empty());
+        assert_eq!(
+            net.find_one_by_attr("basin", &x).unwrap().lock().name(),
+            "a"
+        );
+        assert_eq!(
+            net.find_one_by_attr("basin", &Attribute::String("z".into()))
+                .unwrap_err(),
+            "No node with attribute basin=\"z\""
+        );
+        assert!(net
+            .find_one_by_attr("basin", &y)
+            .unwrap_err()
+            .starts_with("Multiple nodes with attribute basin=\"y\": "));
+    }
+
+    #[rstest]
+    #[case(Propagation::list(["a", "x"]), "Node x not found")]
+    #[case(Propagation::list(["x", "b", "y"]), "Nodes not found: x, y")]
+    #[case(Propagation::Path(StrPath::new("a".into(), "z".into())), "Node z not found")]
+    #[case(Propagation::Path(StrPath::new("x".into(), "z".into())), "Nodes not found: x, z")]
+    fn missing_nodes_test(#[case] prop: Propagation, #[case] err: &str) {
+        let net = Network::from_edges(&[("a", "b"), ("b", "c")]);
+        assert_eq!(net.nodes_propagation(&prop).unwrap_err(), err);
+    }
+
+    #[rstest]
+    fn network_builder_test() {
+        let mut builder = NetworkBuilder::new();
+        builder.add_edge("a", "b").unwrap();
+        builder.add_edge("b", "c").unwrap();
+        builder.add_edge("d", "c").unwrap();
+        assert_eq!(
+            builder.add_edge("c", "a"),
+            Err("Edge c -> a rejected, it makes a cycle".to_string())
+        );
+        assert!(builder.add_edge("a", "d").is_err());
+        assert!(builder.add_edge("e", "e").is_err());
+        // rejected edges don't add the nodes
+        assert_eq!(builder.nodes_count(), 4);
+        let net = builder.finish();
+        assert!(net.ordered);
+        assert!(net.same_topology(&Network::from_edges(&[("a", "b"), ("b", "c"), ("d", "c")])));
+    }
+
+    #[rstest]
+    fn depth_width_test() {
+        let net =
+            Network::from_edges(&[("a", "b"), ("b", "c"), ("c", "d"), ("e", "c"), ("f", "e")]);
+        assert_eq!(net.depth(), 3);
+        // levels: d, c, b, a in 0 and e, f in 1
+        assert_eq!(net.width(), 4);
+        assert_eq!(Network::default().depth(), 0);
+        assert_eq!(Network::default().width(), 0);
+    }
+
+    #[rstest]
+    fn same_topology_test() {
+        let net1 = Network::from_edges(&[("a", "b"), ("b", "c"), ("e", "c")]);
+        let net2 = Network::from_edges(&[("e", "c"), ("a", "b"), ("b", "c")]);
+        assert!(net1.same_topology(&net2));
+        assert!(net1.deep_eq(&net2));
+        assert!(net1.deep_eq(&net1));
+        net1.try_node_by_name("a")
+            .unwrap()
+            .lock()
+            .set_attr("x", Attribute::Integer(1));
+        assert!(net1.same_topology(&net2));
+        assert!(!net1.deep_eq(&net2));
+        let net3 = Network::from_edges(&[("a", "c"), ("b", "c"), ("e", "c")]);
+        assert!(!net1.same_topology(&net3));
+        // the node properties are not compared
+        net2.try_node_by_name("a")
+            .unwrap()
+            .lock()
+            .set_attr("x", Attribute::Integer(1));
+        assert!(net1.deep_eq(&net2));
+        net2.try_node_by_name("a").unwrap().lock().set_index(10);
+        net2.try_node_by_name("a").unwrap().lock().set_level(5);
+        assert!(net1.deep_eq(&net2));
+    }
+
+    #[rstest]
+    fn downstream_distance_test() {
+        let net = Network::from_edges(&[("a", "b"), ("b", "c"), ("c", "d")]);
+        for (name, len) in [("a", 1.0), ("b", 2.0), ("c", 3.0)] {
+            net.try_node_by_name(name)
+                .unwrap()
+                .lock()
+                .set_attr("length", Attribute::Float(len));
+        }
+        net.downstream_distance("length", "dist", false).unwrap();
+        let dists: Vec<f64> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|n| {
+                net.try_node_by_name(n)
+                    .unwrap()
+                    .lock()
+                    .try_attr("dist")
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(dists, vec![6.0, 5.0, 3.0, 0.0]);
+
+        net.try_node_by_name("b").unwrap().lock().del_attr("length");
+        assert_eq!(
+            net.downstream_distance("length", "dist", false),
+            Err("Node b: Attribute length not found".to_string())
+        );
+        net.downstream_distance("length", "dist", true).unwrap();
+        let dist: f64 = net
+            .try_node_by_name("a")
+            .unwrap()
+            .lock()
+            .try_attr("dist")
+            .unwrap();
+        assert_eq!(dist, 4.0);
+    }
+
+    #[rstest]
+    #[case(10.0, vec![("c", "d"), ("b", "c")])]
+    #[case(25.0, vec![])]
+    fn prune_leaves_test(#[case] threshold: f64, #[case] edges: Vec<(&str, &str)>) {
+        let mut net = Network::from_edges(&[("a", "c"), ("b", "c"), ("c", "d"), ("e", "d")]);
+        for (name, area) in [
+            ("a", 5.0),
+            ("b", 20.0),
+            ("c", 15.0),
+            ("d", 100.0),
+            ("e", 1.0),
+        ] {
+            net.try_node_by_name(name)
+                .unwrap()
+                .lock()
+                .set_attr("area", Attribute::Float(area));
+        }
+        net.prune_leaves("area", threshold, "<").unwrap();
+        assert_eq!(net.edges_str().collect::<Vec<(&str, &str)>>(), edges);
+        assert!(net.prune_leaves("area", threshold, "~").is_err());
+    }
+
+    #[rstest]
+    fn reverse_test() {
+        let net = Network::from_edges(&[("a", "b"), ("b", "c")]);
+        net.try_node_by_name("a")
+            .unwrap()
+            .lock()
+            .set_attr("x", Attribute::Integer(1));
+        let rev = net.reverse().unwrap();
+        assert_eq!(
+            rev.edges_str().collect::<Vec<(&str, &str)>>(),
+            vec![("b", "a"), ("c", "b")]
+        );
+        assert_eq!(
+            rev.node(0).map(|n| n.lock().name().to_string()),
+            Some("a".to_string())
+        );
+        let x: i64 = rev
+            .try_node_by_name("a")
+            .unwrap()
+            .lock()
+            .try_attr("x")
+            .unwrap();
+        assert_eq!(x, 1);
+        let back = rev.reverse().unwrap();
+        assert_eq!(
+            back.edges_str().collect::<Vec<(&str, &str)>>(),
+            net.edges_str().collect::<Vec<(&str, &str)>>()
+        );
+        let branched = Network::from_edges(&[("a", "c"), ("b", "c")]);
+        assert!(branched.reverse().is_err());
+    }
+
+    #[rstest]
+    fn outlet_count_test() {
+        let mut net = Network::from_edges(&[("a", "b"), ("c", "b"), ("d", "e")]);
+        assert_eq!(net.outlet_count(), 2);
+        net.reorder();
+        assert!(!net.ordered);
+        let net = Network::from_edges(&[("a", "b"), ("c", "b"), ("b", "e")]);
+        assert_eq!(net.outlet_count(), 1);
+    }
+
+    #[rstest]
+    #[case(MergePolicy::Keep, 2.0)]
+    #[case(MergePolicy::Overwrite, 1.0)]
+    #[case(MergePolicy::Sum, 3.0)]
+    fn contract_node_test(#[case] merge: MergePolicy, #[case] length: f64) {
+        let mut net = Network::from_edges(&[("a", "b"), ("b", "c"), ("d", "b")]);
+        for (name, len) in [("b", 1.0), ("c", 2.0)] {
+            net.try_node_by_name(name)
+                .unwrap()
+                .lock()
+                .set_attr("length", Attribute::Float(len));
+        }
+        net.try_node_by_name("b")
+            .unwrap()
+            .lock()
+            .set_attr("id", Attribute::Integer(10));
+        net.contract_node("b", merge).unwrap();
+        let mut edges: Vec<(&str, &str)> = net.edges_str().collect();
+        edges.sort();
+        assert_eq!(edges, vec![("a", "c"), ("d", "c")]);
+        assert!(net.node_by_name("b").is_none());
+        let c = net.try_node_by_name("c").unwrap().lock();
+        assert_eq!(c.try_attr::<f64>("length").unwrap(), length);
+        assert_eq!(c.try_attr::<i64>("id").unwrap(), 10);
+        assert_eq!(c.attr("NAME"), Some(&Attribute::String("c".into())));
+        assert_eq!(c.attr("INDEX"), Some(&Attribute::Integer(c.index() as i64)));
+        assert_eq!(c.attr("LEVEL"), Some(&Attribute::Integer(c.level() as i64)));
+        drop(c);
+        assert!(net.contract_node("c", merge).is_err());
+    }
+
+    #[rstest]
+    fn merge_test() {
+        let mut net = Network::from_edges(&[("a", "b"), ("b", "c")]);
+        let other = Network::from_edges(&[("b", "c"), ("d", "c")]);
+        for (n, name, area) in [(&net, "b", 1.0), (&other, "b", 2.0), (&other, "d", 3.0)] {
+            n.try_node_by_name(name)
+                .unwrap()
+                .lock()
+                .set_attr("area", Attribute::Float(area));
+        }
+        assert_eq!(
+            net.clone().merge(other.clone(), MergePolicy::Error),
+            Err("Node b: Conflicting values for attribute area: 1.0 and 2.0".to_string())
+        );
+        // unchanged on error
+        assert!(net.same_topology(&Network::from_edges(&[("a", "b"), ("b", "c")])));
+        net.merge(other, MergePolicy::Sum).unwrap();
+        assert!(net.ordered);
+        assert!(net.same_topology(&Network::from_edges(&[("a", "b"), ("b", "c"), ("d", "c")])));
+        for (name, area) in [("b", 3.0), ("d", 3.0)] {
+            let node = net.try_node_by_name(name).unwrap().lock();
+            assert_eq!(node.try_attr::<f64>("area").unwrap(), area);
+        }
+        let conflict = Network::from_edges(&[("b", "d")]);
+        assert_eq!(
+            net.merge(conflict, MergePolicy::Keep),
+            Err("Edge b -> d rejected, node b already has output c".to_string())
+        );
+    }
+
+    #[rstest]
+    fn merge_topology_test() {
+        // same node names, but their index, level and order differ
+        let mut net = Network::from_edges(&[("a", "b"), ("b", "c")]);
+        let other = Network::from_edges(&[("d", "b"), ("e", "d"), ("f", "d"), ("a", "b")]);
+        net.merge(other, MergePolicy::Error).unwrap();
+        assert!(net.same_topology(&Network::from_edges(&[
+            ("a", "b"),
+            ("b", "c"),
+            ("d", "b"),
+            ("e", "d"),
+            ("f", "d")
+        ])));
+        for node in net.nodes() {
+            let n = node.lock();
+            assert_eq!(n.attr("NAME"), Some(&Attribute::String(n.name().into())));
+            assert_eq!(n.attr("INDEX"), Some(&Attribute::Integer(n.index() as i64)));
+            assert_eq!(n.attr("LEVEL"), Some(&Attribute::Integer(n.level() as i64)));
+            assert_eq!(n.attr("ORDER"), Some(&Attribute::Integer(n.order() as i64)));
+        }
+    }
+
+    #[rstest]
+    #[case(Propagation::Upstream("c".into()), vec!["c", "b", "a"])]
+    #[case(Propagation::Upstream("e".into()), vec!["e", "f", "d", "c", "b", "a"])]
+    #[case(Propagation::Upstream("f".into()), vec!["f"])]
+    #[case(Propagation::Downstream("a".into()), vec!["e", "d", "c", "b", "a"])]
+    #[case(Propagation::Downstream("f".into()), vec!["e", "f"])]
+    #[case(Propagation::Downstream("e".into()), vec!["e"])]
+    fn upstream_downstream_test(#[case] prop: Propagation, #[case] order: Vec<&str>) {
+        let net =
+            Network::from_edges(&[("a", "b"), ("b", "c"), ("c", "d"), ("d", "e"), ("f", "e")]);
+        let nodes: Vec<String> = net
+            .nodes_propagation(&prop)
+            .unwrap()
+            .iter()
+            .map(|n| n.lock().name().to_string())
+            .collect();
+        assert_eq!(nodes, order);
+        assert_eq!(
+            net.nodes_propagation(&Propagation::Upstream("x".into())),
+            Err("Node x not found".to_string())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[rstest]
+    fn to_json_test() {
+        use crate::attrs::attrmap_from_json;
+        use crate::timeseries::{TimeLineInner, TimeSeries};
+        use abi_stable::{external_types::RMutex, std_types::RArc};
+
+        let mut net = Network::from_edges(&[("a", "b"), ("c", "b")]);
+        net.set_attr("title", Attribute::String("test".into()));
+        {
+            let mut a = net.try_node_by_name("a").unwrap().lock();
+            a.set_attr("area", Attribute::Float(1.5));
+            a.set_attr("gauged", Attribute::Bool(true));
+            let tl = RArc::new(RMutex::new(TimeLineInner::new(0, 2, 1, true, vec![], "")));
+            a.set_ts("q", TimeSeries::new(tl, vec![1i64, 2, 3].into()));
+        }
+        let doc: serde_json::Value = serde_json::from_str(&net.to_json().unwrap()).unwrap();
+        let names: Vec<&str> = doc["nodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["b", "a", "c"]);
+        assert_eq!(doc["edges"], serde_json::json!([["a", "b"], ["c", "b"]]));
+        assert_eq!(doc["attributes"]["title"], "test");
+        let a = &doc["nodes"][1];
+        assert_eq!(a["index"], 1);
+        assert_eq!(a["level"], 0);
+        assert_eq!(
+            a["timeseries"],
+            serde_json::json!([{"name": "q", "type": "Integers", "length": 3}])
+        );
+        let attrs = attrmap_from_json(&a["attributes"].to_string()).unwrap();
+        let node = net.try_node_by_name("a").unwrap().lock();
+        assert_eq!(&attrs, node.attr_map());
+        drop(node);
+
+        let doc: serde_json::Value =
+            serde_json::from_str(&net.to_json_opts(true).unwrap()).unwrap();
+        assert_eq!(
+            doc["nodes"][1]["timeseries"][0]["values"],
+            serde_json::json!([1, 2, 3])
+        );
+    }
+
+    #[rstest]
+    fn combined_propagation_test() {
+        use crate::functions::Condition;
+
+        let net =
+            Network::from_edges(&[("a", "b"), ("b", "c"), ("c", "d"), ("d", "e"), ("f", "e")]);
+        for n in ["a", "c", "e", "f"] {
+            net.try_node_by_name(n)
+                .unwrap()
+                .lock()
+                .set_attr("flag", Attribute::Bool(true));
+        }
+        let names = |prop: Propagation| -> Vec<String> {
+            net.nodes_propagation(&prop)
+                .unwrap()
+                .iter()
+                .map(|n| n.lock().name().to_string())
+                .collect()
+        };
+        let cond = || Propagation::Conditional(Condition::Single("flag".into()));
+        let list = || Propagation::list(["b", "c", "a"]);
+        assert_eq!(names(list().intersect(cond())), vec!["c", "a"]);
+        assert_eq!(names(cond().intersect(list())), vec!["c", "a"]);
+        assert_eq!(names(list().difference(cond())), vec!["b"]);
+        assert_eq!(names(list().union(cond())), vec!["b", "c", "a", "e", "f"]);
+        assert_eq!(
+            names(Propagation::Upstream("c".into()).intersect(cond())),
+            vec!["c", "a"]
+        );
+        assert_eq!(
+            net.nodes_propagation(&list().intersect(Propagation::list(["x"]))),
+            Err("Node x not found".to_string())
+        );
+    }
+
+    #[rstest]
+    fn align_timeseries_test() {
+        use crate::timeseries::{TimeLineInner, TimeSeries, TimeSeriesValues};
+        use abi_stable::{external_types::RMutex, std_types::RArc};
+
+        let ts = |start: i64, step: i64, values: TimeSeriesValues| {
+            let end = start + (values.len() as i64 - 1) * step;
+            let tl = TimeLineInner::new(start, end, step, true, vec![], "");
+            TimeSeries::new(RArc::new(RMutex::new(tl)), values)
+        };
+        let mut net = Network::from_edges(&[("a", "b"), ("c", "b")]);
+        net.try_node_by_name("a")
+            .unwrap()
+            .lock()
+            .set_ts("q", ts(0, 1, (0..10).collect::<Vec<i64>>().into()));
+        net.try_node_by_name("b")
+            .unwrap()
+            .lock()
+            .set_ts("q", ts(2, 2, vec![1.0, 2.0, 3.0, 4.0].into()));
+        assert_eq!(
+            net.align_timeseries("q").unwrap_err(),
+            "Timeseries `q` not found in nodes: c"
+        );
+        net.try_node_by_name("c")
+            .unwrap()
+            .lock()
+            .set_ts("q", ts(1, 2, vec![1.0, 2.0].into()));
+        assert_eq!(
+            net.align_timeseries("q").unwrap_err(),
+            "Timeseries `q` of node c can't be aligned to start 2 with step 2"
+        );
+        net.try_node_by_name("c")
+            .unwrap()
+            .lock()
+            .set_ts("q", ts(0, 2, vec![1.0; 10].into()));
+        let tl = net.align_timeseries("q").unwrap();
+        assert_eq!(
+            (tl.lock().start(), tl.lock().end(), tl.lock().step()),
+            (2, 8, 2)
+        );
+        for (name, values) in [
+            ("a", [2.5, 4.5, 6.5, 8.5]),
+            ("b", [1.0, 2.0, 3.0, 4.0]),
+            ("c", [1.0; 4]),
+        ] {
+            let node = net.try_node_by_name(name).unwrap().lock();
+            let q = node.ts("q").unwrap();
+            assert!(q.is_timeline(&tl));
+            assert_eq!(q.values::<f64>(), Some(&values[..]));
+        }
+    }
+
+    #[rstest]
+    fn align_timeseries_error_test() {
+        use crate::timeseries::{TimeLineInner, TimeSeries};
+        use abi_stable::{external_types::RMutex, std_types::RArc};
+
+        let mut net = Network::from_edges(&[("a", "b"), ("c", "b")]);
+        for (name, start, fmt) in [("b", 0, ""), ("a", 2, "%Y"), ("c", 0, "")] {
+            let tl = TimeLineInner::new(start, start + 9, 1, true, vec![], fmt);
+            net.try_node_by_name(name).unwrap().lock().set_ts(
+                "q",
+                TimeSeries::new(RArc::new(RMutex::new(tl)), vec![1.0; 10].into()),
+            );
+        }
+        let before: Vec<TimeLine> = net
+            .nodes()
+            .map(|n| n.lock().ts("q").unwrap().timeline().clone())
+            .collect();
+        // the first node (b) is aligned, and the second (a) fails
+        assert_eq!(
+            net.align_timeseries("q").unwrap_err(),
+            "Timeseries `q` of node a: Timeline is different from the timeseries' timeline"
+        );
+        for (node, tl) in net.nodes().zip(&before) {
+            let node = node.lock();
+            let q = node.ts("q").unwrap();
+            assert!(q.is_timeline(tl), "{}", node.name());
+            assert_eq!(q.len(), 10);
+        }
+    }
+
+    #[rstest]
+    fn report_test() {
+        let net = Network::from_edges(&[("a", "b"), ("c", "b"), ("b", "d"), ("e", "d")]);
+        assert_eq!(
+            net.report(),
+            NetworkReport {
+                node_count: 5,
+                edge_count: 4,
+                outlet_count: 1,
+                leaf_count: 3,
+                max_level: 1,
+                ordered: true,
+                outlets: vec!["d".to_string()],
+            }
+        );
+        let net = Network::from_edges(&[("a", "b"), ("c", "d")]);
+        let report = net.report();
+        assert_eq!(report.outlets, vec!["b".to_string(), "d".to_string()]);
+        assert!(!report.ordered);
+    }
+
+    #[rstest]
+    fn reorder_from_test() {
+        let mut net = Network::from_edges(&[("a", "b"), ("c", "d"), ("e", "d")]);
+        assert_eq!(net.outlet.as_ref().unwrap().lock().name(), "b");
+        net.reorder_from("d").unwrap();
+        let names: Vec<&str> = net.nodes.iter().map(|n| n.as_str()).collect();
+        assert_eq!(names, vec!["d", "c", "e", "a", "b"]);
+        assert_eq!(net.outlet.as_ref().unwrap().lock().name(), "d");
+        assert_eq!(net.node_by_name("a").unwrap().lock().index(), 3);
+        assert!(!net.ordered);
+        assert_eq!(
+            net.reorder_from("c").unwrap_err(),
+            "Node c is not an outlet"
+        );
+        assert_eq!(net.reorder_from("x").unwrap_err(), "Node x not found");
+
+        let mut net = Network::from_edges(&[("a", "b"), ("c", "b")]);
+        net.reorder_from("b").unwrap();
+        assert!(net.ordered);
+    }
+
+    #[rstest]
+    fn disconnected_order_test() {
+        let mut net = Network::from_edges(&[("a", "b"), ("c", "d"), ("e", "d")]);
+        assert!(!net.ordered);
+        let check = |net: &Network| {
+            for (i, n) in net.nodes().enumerate() {
+                assert_eq!(n.lock().index(), i);
+                assert_eq!(net.node(i).unwrap().lock().name(), net.nodes[i].as_str());
+            }
+        };
+        check(&net);
+        let c = net.node_by_name("c").unwrap().clone();
+        net.remove_node(&c);
+        check(&net);
+        let names: Vec<&str> = net.nodes.iter().map(|n| n.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "d", "e"]);
+    }
+
+    #[rstest]
+    fn parent_array_test() {
+        let net = Network::from_edges(&[("a", "b"), ("c", "b"), ("b", "d"), ("e", "d")]);
+        let (names, parents) = net.to_parent_array();
+        assert_eq!(names, vec!["d", "e", "b", "a", "c"]);
+        assert_eq!(parents, vec![-1, 0, 0, 2, 2]);
+        let net2 = Network::from_parent_array(&names, &parents).unwrap();
+        assert!(net2.same_topology(&net));
+        assert_eq!(net2.to_parent_array(), (names, parents));
+
+        assert_eq!(
+            Network::from_parent_array(&["a", "b"], &[1, 0]).unwrap_err(),
+            "Parent array should have exactly one outlet (-1), found 0"
+        );
+        assert_eq!(
+            Network::from_parent_array(&["a", "b"], &[-1, 2]).unwrap_err(),
+            "Invalid parent 2 for node b"
+        );
+        assert_eq!(
+            Network::from_parent_array(&["a", "b", "c"], &[-1, 2, 1]).unwrap_err(),
+            "Edge c -> b rejected, it makes a cycle"
+        );
+    }
+
+    #[rstest]
+    fn each_node_test() {
+        let net = Network::from_edges(&[("a", "b"), ("c", "b")]);
+        net.each_node_mut(|n| {
+            n.set_attr("x", Attribute::Integer(n.index() as i64));
+        });
+        let mut names = vec![];
+        net.each_node(|n| names.push((n.name().to_string(), n.attr("x").cloned())));
+        assert_eq!(
+            names,
+            vec![
+                ("b".to_string(), Some(Attribute::Integer(0))),
+                ("a".to_string(), Some(Attribute::Integer(1))),
+                ("c".to_string(), Some(Attribute::Integer(2))),
+            ]
+        );
+        // the nodes are not locked after the iteration
+        assert!(net.nodes().all(|n| n.try_lock().is_some()));
+    }
+
+    #[rstest]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Connections of node b changed inside each_node_mut")]
+    fn each_node_mut_links_test() {
+        let net = Network::from_edges(&[("a", "b"), ("c", "b")]);
+        net.each_node_mut(|n| n.unset_inputs());
+    }
+
+    #[rstest]
+    fn set_attr_all_test() {
+        let net = Network::from_edges(&[("a", "b"), ("b", "c"), ("d", "b")]);
+        assert_eq!(net.set_attr_all("x", Attribute::Integer(2)), 4);
+        for node in net.nodes() {
+            assert_eq!(node.lock().try_attr::<i64>("x").unwrap(), 2);
+        }
+    }
+
+    #[rstest]
+    #[case(true, vec!["b", "d", "a", "c"])]
+    #[case(false, vec!["a", "d", "b", "c"])]
+    fn sorted_propagation_test(#[case] asc: bool, #[case] order: Vec<&str>) {
+        let net = Network::from_edges(&[("a", "b"), ("b", "c"), ("d", "b")]);
+        for (name, elev) in [("a", 30.0), ("b", 10.0), ("d", 20.0)] {
+            net.try_node_by_name(name)
+                .unwrap()
+                .lock()
+                .set_attr("elevation", Attribute::Float(elev));
+        }
+        let nodes = net
+            .nodes_propagation(&Propagation::SortedBy("elevation".into(), asc))
+            .unwrap();
+        let names: Vec<String> = nodes.iter().map(|n| n.lock().name().to_string()).collect();
+        assert_eq!(names, order);
+    }
+
+    #[rstest]
+    #[case(ReorderMode::Topological, vec!["d", "c", "e", "b", "a"])]
+    #[case(ReorderMode::Preserve, vec!["a", "b", "c", "d", "e"])]
+    fn reorder_mode_test(#[case] mode: ReorderMode, #[case] order: Vec<&str>) {
+        let net = Network::from_edges_opts(&[("a", "b"), ("b", "c"), ("c", "d"), ("e", "c")], mode);
+        assert_eq!(net.node_names().collect::<Vec<&str>>(), order);
+        assert_eq!(net.node(0).unwrap().lock().index(), 0);
+        let outlet = net.outlet.as_ref().map(|n| n.lock().name().to_string());
+        assert_eq!(outlet, RSome("d".to_string()));
+        let level = |n: &str| net.try_node_by_name(n).unwrap().lock().level();
+        assert_eq!((level("a"), level("e")), (0, 1));
+    }
+}
@@ -1,14 +1,15 @@
 use crate::{
-    attrs::{AttrMap, Attribute, HasAttributes},
+    attrs::{attrs_heap_size, AttrMap, Attribute, HasAttributes},
     prelude::HasTimeSeries,
     timeseries::TsMap,
 };
 use abi_stable::{
     external_types::RMutex,
+    pointer_trait::AsPtr,
     std_types::{
         RArc,
         ROption::{self, RSome},
-        RString, RVec,
+        RString, RVec, Tuple2,
     },
     StableAbi,
 };
@@ -65,14 +66,43 @@ pub struct NodeInner {
     pub(crate) output: ROption<Node>,
 }
 
+/// Attributes that mirror the fields of the [`NodeInner`], they are
+/// read-only and only updated through the setters of the fields
+/// (e.g. [`NodeInner::set_index`])
+pub const NODE_PROPERTIES: [&str; 4] = ["NAME", "INDEX", "LEVEL", "ORDER"];
+
 impl HasAttributes for NodeInner {
     fn attr_map(&self) -> &AttrMap {
         &self.attributes
     }
 
+    /// Direct access to the attributes, the [`NODE_PROPERTIES`]
+    /// changed through this will not be reflected in the fields
     fn attr_map_mut(&mut self) -> &mut AttrMap {
         &mut self.attributes
     }
+
+    /// Set the attribute, the [`NODE_PROPERTIES`] are not changed,
+    /// use [`NodeInner::try_set_attr`] to get an error for them
+    fn set_attr(&mut self, name: &str, val: Attribute) -> Option<Attribute> {
+        self.try_set_attr(name, val).unwrap_or_default()
+    }
+
+    /// Delete the attribute, the [`NODE_PROPERTIES`] are not deleted,
+    /// use [`NodeInner::try_del_attr`] to get an error for them
+    fn del_attr(&mut self, name: &str) -> Option<Attribute> {
+        self.try_del_attr(name).unwrap_or_default()
+    }
+}
+
+/// Copy of the attributes without the [`NODE_PROPERTIES`], to merge
+/// the attributes of one node into another
+pub fn without_properties(attrs: &AttrMap) -> AttrMap {
+    attrs
+        .iter()
+        .filter(|Tuple2(k, _)| !NODE_PROPERTIES.contains(&k.as_str()))
+        .map(|Tuple2(k, v)| (k.clone(), v.clone()))
+        .collect()
 }
 
 impl HasTimeSeries for NodeInner {
@@ -92,8 +122,28 @@ impl NodeInner {
             name: name.into(),
             ..Default::default()
         };
-        node.set_attr("NAME", Attribute::String(name.into()));
-        node.set_attr("INDEX", Attribute::Integer(index as i64));
+        node.set_prop("NAME", Attribute::String(name.into()));
+        node.set_index(index);
+        node
+    }
+
+    /// Copy of the node with its attributes and timeseries, but
+    /// without the links to the inputs and output, and with the index,
+    /// level and order reset
+    ///
+    /// Use this instead of [`Clone::clone`] to move the node's data
+    /// into a new network, as the derived clone shares the
+    /// input/output nodes with the original network.
+    pub fn clone_detached(&self) -> NodeInner {
+        let mut node = Self {
+            name: self.name.clone(),
+            attributes: self.attributes.clone(),
+            timeseries: self.timeseries.clone(),
+            ..Default::default()
+        };
+        node.set_index(0);
+        node.set_level(0);
+        node.set_order(0);
         node
     }
 
@@ -107,7 +157,7 @@ impl NodeInner {
 
     pub fn set_index(&mut self, index: usize) {
         self.index = index;
-        self.set_attr("INDEX", Attribute::Integer(index as i64));
+        self.set_prop("INDEX", Attribute::Integer(index as i64));
     }
 
     pub fn level(&self) -> u64 {
@@ -120,12 +170,130 @@ impl NodeInner {
 
     pub fn set_level(&mut self, level: u64) {
         self.level = level;
-        self.set_attr("LEVEL", Attribute::Integer(level as i64));
+        self.set_prop("LEVEL", Attribute::Integer(level as i64));
     }
 
     pub fn set_order(&mut self, order: u64) {
         self.order = order;
-        self.set_attr("ORDER", Attribute::Integer(order as i64));
+        self.set_prop("ORDER", Attribute::Integer(order as i64));
+    }
+
+    /// Error if the attribute is one of the read-only [`NODE_PROPERTIES`]
+    pub fn check_writable(&self, name: &str) -> Result<(), String> {
+        if NODE_PROPERTIES.contains(&name) {
+            Err(format!(
+                "Attribute {name} of node {} is read-only",
+                self.name
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Set the attribute, errors for the [`NODE_PROPERTIES`]
+    pub fn try_set_attr(
+        &mut self,
+        name: &str,
+        val: Attribute,
+    ) -> Result<Option<Attribute>, String> {
+        self.check_writable(name)?;
+        Ok(self.attributes.insert(name.into(), val).into())
+    }
+
+    /// Delete the attribute, errors for the [`NODE_PROPERTIES`]
+    pub fn try_del_attr(&mut self, name: &str) -> Result<Option<Attribute>, String> {
+        self.check_writable(name)?;
+        Ok(self.attributes.remove(name).into())
+    }
+
+    /// Add the attributes replacing the existing ones, the
+    /// [`NODE_PROPERTIES`] in them are skipped so they stay in sync
+    /// with the fields
+    ///
+    /// Use this instead of extending [`HasAttributes::attr_map_mut`]
+    /// for the attributes from files or other nodes.
+    pub fn extend_attrs(&mut self, attrs: AttrMap) {
+        for Tuple2(k, v) in attrs {
+            if !NODE_PROPERTIES.contains(&k.as_str()) {
+                self.attributes.insert(k, v);
+            }
+        }
+    }
+
+    /// Set the attribute mirroring the field, see [`NODE_PROPERTIES`]
+    fn set_prop(&mut self, name: &str, val: Attribute) {
+        self.attributes.insert(name.into(), val);
+    }
+
+    /// Replace the string attributes of the form `@name` with the
+    /// value of the attribute `name`, keeping its type
+    ///
+    /// The references are resolved transitively (`a = @b` with
+    /// `b = @c` gets the value of `c`), only the top level
+    /// attributes are resolved and referenced. The [`NODE_PROPERTIES`]
+    /// are not resolved even if they look like references.
+    ///
+    /// # Errors
+    /// Errors if the referenced attribute is not found or the
+    /// references make a cycle, no attribute is changed in that case.
+    pub fn resolve_references(&mut self) -> Result<(), String> {
+        fn reference(val: &Attribute) -> Option<&str> {
+            match val {
+                Attribute::String(s) => s.strip_prefix('@'),
+                _ => None,
+            }
+        }
+        fn resolve(
+            attrs: &AttrMap,
+            key: &str,
+            chain: &mut Vec<String>,
+        ) -> Result<Attribute, String> {
+            let val = attrs.get(key).ok_or_else(|| {
+                format!(
+                    "Attribute {key} referenced by {} not found",
+                    chain.last().map(String::as_str).unwrap_or_default()
+                )
+            })?;
+            match reference(val) {
+                Some(r) => {
+                    chain.push(key.to_string());
+                    if chain.iter().any(|c| c == r) {
+                        return Err(format!(
+                            "Cycle in attribute references: {} -> {r}",
+                            chain.join(" -> ")
+                        ));
+                    }
+                    resolve(attrs, r, chain)
+                }
+                None => Ok(val.clone()),
+            }
+        }
+        let mut resolved = vec![];
+        for Tuple2(k, v) in &self.attributes {
+            if reference(v).is_some() && !NODE_PROPERTIES.contains(&k.as_str()) {
+                resolved.push((k.clone(), resolve(&self.attributes, k, &mut vec![])?));
+            }
+        }
+        self.extend_attrs(resolved.into_iter().collect());
+        Ok(())
+    }
+
+    /// Name, value type and length of the timeseries in the node,
+    /// sorted by the name
+    pub fn timeseries_info(&self) -> Vec<(String, &'static str, usize)> {
+        let mut info: Vec<(String, &'static str, usize)> = self
+            .timeseries
+            .iter()
+            .map(|Tuple2(k, v)| (k.to_string(), v.values_type(), v.len()))
+            .collect();
+        info.sort_by(|a, b| a.0.cmp(&b.0));
+        info
+    }
+
+    /// Approximate heap bytes used by the attributes of the node,
+    /// including the [`NODE_PROPERTIES`], see [`attrs_heap_size`]
+    pub fn attrs_heap_size(&self) -> usize {
+        attrs_heap_size(&self.attributes)
     }
 
     pub fn inputs(&self) -> &[Node] {
@@ -144,9 +312,13 @@ impl NodeInner {
         self.inputs = RVec::new();
     }
 
+    /// Sort the inputs by their order (descending), the inputs with
+    /// same order are sorted by their name for deterministic results
     pub fn order_inputs(&mut self) {
-        self.inputs
-            .sort_by(|a, b| b.lock().order.partial_cmp(&a.lock().order).unwrap());
+        self.inputs.sort_by_cached_key(|n| {
+            let n = n.lock();
+            (std::cmp::Reverse(n.order), n.name.to_string())
+        });
     }
 
     pub fn output(&self) -> ROption<&Node> {
@@ -188,8 +360,235 @@ impl NodeInner {
                 .unwrap();
             let o = out.lock().inputs.remove(i);
             self.output = out.lock().output.clone();
+            if let RSome(next) = &self.output {
+                // the current node takes the place of `out` in its output
+                let mut next = next.lock();
+                if let Some(j) = next
+                    .inputs()
+                    .iter()
+                    .position(|c| AsPtr::as_ptr(c) == AsPtr::as_ptr(&out))
+                {
+                    next.inputs[j] = o.clone();
+                }
+            }
             out.lock().set_output(o);
-            self.add_input(out.clone());
+            self.inputs.insert(0, out.clone());
+        }
+    }
+
+    /// Move the node up one step, (swap places with its first
+    /// input), inverse of [`NodeInner::move_down`]
+    pub fn move_up(&mut self) {
+        if self.inputs.is_empty() {
+            return;
+        }
+        let inp = self.inputs.remove(0);
+        // the input holds the reference to current node as its output
+        let curr = inp
+            .lock()
+            .unset_output()
+            .expect("Input should have the current node as output");
+        if let RSome(out) = &self.output {
+            // the input takes the place of current node in its output
+            let mut out = out.lock();
+            if let Some(j) = out
+                .inputs()
+                .iter()
+                .position(|c| AsPtr::as_ptr(c) == AsPtr::as_ptr(&curr))
+            {
+                out.inputs[j] = inp.clone();
+            }
+        }
+        inp.lock().output = self.output.clone();
+        inp.lock().add_input(curr);
+        self.output = RSome(inp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Network;
+    use rstest::rstest;
+
+    fn connections(net: &Network) -> Vec<(String, Option<String>, Vec<String>)> {
+        net.nodes()
+            .map(|n| {
+                let n = n.lock();
+                (
+                    n.name().to_string(),
+                    n.output().map(|o| o.lock().name().to_string()).into(),
+                    n.inputs()
+                        .iter()
+                        .map(|i| i.lock().name().to_string())
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[rstest]
+    fn node_properties_test() {
+        let mut net = Network::from_edges(&[("a", "b"), ("c", "b"), ("b", "d")]);
+        let c = net.try_node_by_name("c").unwrap().clone();
+        c.lock().set_attr("INDEX", Attribute::Integer(10));
+        c.lock().del_attr("LEVEL");
+        net.reorder_from("d").unwrap();
+        net.set_levels();
+        let b = net.try_node_by_name("b").unwrap().clone();
+        net.remove_node(&b);
+        for node in net.nodes() {
+            let n = node.lock();
+            assert_eq!(n.attr("INDEX"), Some(&Attribute::Integer(n.index() as i64)));
+            assert_eq!(n.attr("LEVEL"), Some(&Attribute::Integer(n.level() as i64)));
+            assert_eq!(n.attr("ORDER"), Some(&Attribute::Integer(n.order() as i64)));
+            assert_eq!(n.attr("NAME"), Some(&Attribute::String(n.name().into())));
         }
     }
+
+    #[rstest]
+    fn read_only_properties_test() {
+        let mut node = NodeInner::new(2, "a");
+        assert_eq!(
+            node.try_set_attr("INDEX", Attribute::Integer(5)),
+            Err("Attribute INDEX of node a is read-only".to_string())
+        );
+        assert!(node.try_del_attr("NAME").is_err());
+        assert_eq!(node.try_set_attr("x", Attribute::Integer(5)), Ok(None));
+        let attrs: AttrMap = [
+            ("INDEX", Attribute::Integer(3)),
+            ("NAME", Attribute::String("@x".into())),
+            ("y", Attribute::String("@x".into())),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.into(), v))
+        .collect();
+        node.extend_attrs(attrs);
+        node.resolve_references().unwrap();
+        assert_eq!(node.attr("INDEX"), Some(&Attribute::Integer(2)));
+        assert_eq!(node.attr("NAME"), Some(&Attribute::String("a".into())));
+        assert_eq!(node.attr("y"), Some(&Attribute::Integer(5)));
+        let attrs = without_properties(node.attr_map());
+        assert_eq!(attrs.len(), 2);
+    }
+
+    #[rstest]
+    fn attrs_heap_size_test() {
+        let entry = std::mem::size_of::<(RString, Attribute)>();
+        let attr = std::mem::size_of::<Attribute>();
+        let mut node = NodeInner::new(0, "abc");
+        // NAME and INDEX keys, and the name value
+        let base = node.attrs_heap_size();
+        assert_eq!(base, 2 * entry + 4 + 5 + 3);
+        node.set_attr("x", Attribute::Integer(1));
+        node.set_attr("text", Attribute::String("hello".into()));
+        node.set_attr(
+            "arr",
+            Attribute::Array(RVec::from(vec![
+                Attribute::Integer(1),
+                Attribute::String("ab".into()),
+            ])),
+        );
+        assert_eq!(
+            node.attrs_heap_size() - base,
+            (entry + 1) + (entry + 4 + 5) + (entry + 3 + 2 * attr + 2)
+        );
+    }
+
+    #[rstest]
+    fn clone_detached_test() {
+        let net = Network::from_edges(&[("a", "b"), ("b", "c")]);
+        let b = net.try_node_by_name("b").unwrap().lock();
+        let node = b.clone_detached();
+        assert_eq!(node.name(), "b");
+        assert_eq!(node.index(), 0);
+        assert_eq!((node.level(), node.order()), (0, 0));
+        assert_eq!(node.attr("INDEX"), Some(&Attribute::Integer(0)));
+        assert_eq!(node.attr("LEVEL"), Some(&Attribute::Integer(0)));
+        assert_eq!(node.attr("ORDER"), Some(&Attribute::Integer(0)));
+        // the original has non-zero values for them
+        assert_eq!(b.order(), 2);
+        assert!(node.inputs().is_empty());
+        assert!(node.output().is_none());
+        assert_eq!(node.attr("NAME"), b.attr("NAME"));
+        // original is still linked
+        assert_eq!(b.inputs().len(), 1);
+        assert!(b.output().is_some());
+    }
+
+    #[rstest]
+    fn move_up_test() {
+        let net = Network::from_edges(&[("a", "b"), ("b", "c"), ("c", "d")]);
+        let before = connections(&net);
+        net.try_node_by_name("b").unwrap().lock().move_down();
+        {
+            let c = net.try_node_by_name("c").unwrap().lock();
+            assert_eq!(
+                c.output().map(|o| o.lock().name().to_string()),
+                RSome("b".to_string())
+            );
+        }
+        assert_ne!(connections(&net), before);
+        net.try_node_by_name("b").unwrap().lock().move_up();
+        assert_eq!(connections(&net), before);
+    }
+
+    #[rstest]
+    fn with_ts_mut_test() {
+        use crate::timeseries::{TimeLineInner, TimeSeries};
+
+        let mut node = NodeInner::new(0, "a");
+        let tl = RArc::new(RMutex::new(TimeLineInner::new(0, 2, 1, true, vec![], "")));
+        node.set_ts("q", TimeSeries::new(tl, vec![1.0, 2.0, 3.0].into()));
+        node.with_ts_floats_mut("q", |v| v.iter_mut().for_each(|x| *x *= 2.0))
+            .unwrap();
+        assert_eq!(
+            node.ts("q").unwrap().values::<f64>(),
+            Some(&[2.0, 4.0, 6.0][..])
+        );
+        assert_eq!(
+            node.with_ts_floats_mut("x", |_| ()).unwrap_err(),
+            "Timeseries `x` not found"
+        );
+        let mut called = false;
+        assert_eq!(
+            node.with_ts_integers_mut("q", |_| called = true)
+                .unwrap_err(),
+            format!(
+                "Incorrect Type: timeseries of `Floats` cannot be converted to `{}`",
+                crate::attrs::type_name::<i64>()
+            )
+        );
+        assert!(!called);
+    }
+
+    #[rstest]
+    fn resolve_references_test() {
+        let mut node = NodeInner::new(0, "a");
+        node.set_attr("capacity", Attribute::Float(10.5));
+        node.set_attr("max", Attribute::String("@capacity".into()));
+        node.set_attr("limit", Attribute::String("@max".into()));
+        node.set_attr("email", Attribute::String("a@b".into()));
+        node.resolve_references().unwrap();
+        assert_eq!(node.attr("max"), Some(&Attribute::Float(10.5)));
+        assert_eq!(node.attr("limit"), Some(&Attribute::Float(10.5)));
+        assert_eq!(node.attr("email"), Some(&Attribute::String("a@b".into())));
+
+        node.set_attr("x", Attribute::String("@y".into()));
+        node.set_attr("y", Attribute::String("@x".into()));
+        let err = node.resolve_references().unwrap_err();
+        assert!(
+            err == "Cycle in attribute references: x -> y -> x"
+                || err == "Cycle in attribute references: y -> x -> y",
+            "{err}"
+        );
+        assert_eq!(node.attr("x"), Some(&Attribute::String("@y".into())));
+
+        let mut node = NodeInner::new(0, "a");
+        node.set_attr("x", Attribute::String("@z".into()));
+        assert_eq!(
+            node.resolve_references(),
+            Err("Attribute z referenced by x not found".to_string())
+        );
+    }
 }
@@ -3,6 +3,7 @@ use crate::parser::tasks::read_attribute;
 use crate::parser::tokenizer::{TaskToken, Token, VecTokens};
 use crate::parser::{ParseError, ParseErrorType};
 use abi_stable::std_types::{map::REntry, RString};
+use std::collections::HashMap;
 
 #[derive(Debug, PartialEq)]
 pub enum State {
@@ -14,9 +15,24 @@ pub enum State {
 }
 
 pub fn parse(tokens: Vec<Token>) -> Result<AttrMap, ParseError> {
+    parse_with(tokens, false).map(|(attrs, _)| attrs)
+}
+
+/// Parse the attributes, when `keep_comments` is set the comment
+/// lines immediately preceding a key (or a group header) are
+/// returned in a map of the dotted path of the key to the comment
+/// text (without the `#`), multiple lines are joined by newlines.
+pub fn parse_with(
+    tokens: Vec<Token>,
+    keep_comments: bool,
+) -> Result<(AttrMap, HashMap<String, String>), ParseError> {
     let mut tokens = VecTokens::new(tokens);
     let mut attrmap = AttrMap::new();
+    let mut comments = HashMap::new();
+    let mut pending: Vec<String> = vec![];
+    let mut blank_line = true;
     let mut curr_grp: Vec<String> = vec![];
+    let mut curr_path: Vec<String> = vec![];
     let mut curr_var = &mut attrmap;
     let mut state = State::None;
     let mut token;
@@ -26,18 +42,36 @@ pub fn parse(tokens: Vec<Token>) -> Result<AttrMap, ParseError> {
             None => break,
         };
         match token.ty {
-            TaskToken::WhiteSpace | TaskToken::Comment => (),
-            TaskToken::NewLine => match state {
-                State::Newline => {
-                    state = State::None;
-                    curr_var = &mut attrmap;
-                    for g in &curr_grp {
-                        curr_var = move_in(g, curr_var, &tokens)?;
+            TaskToken::WhiteSpace | TaskToken::NewLine => (),
+            _ => blank_line = false,
+        }
+        match token.ty {
+            TaskToken::WhiteSpace => (),
+            TaskToken::Comment => {
+                if keep_comments && state == State::None {
+                    let cmt = token.content.trim_start_matches('#');
+                    pending.push(cmt.strip_prefix(' ').unwrap_or(cmt).to_string());
+                }
+            }
+            TaskToken::NewLine => {
+                // empty line separates the comments from the next key
+                if blank_line {
+                    pending.clear();
+                }
+                blank_line = true;
+                match state {
+                    State::Newline => {
+                        state = State::None;
+                        curr_path.clear();
+                        curr_var = &mut attrmap;
+                        for g in &curr_grp {
+                            curr_var = move_in(g, curr_var, &tokens)?;
+                        }
                     }
+                    State::None => (),
+                    _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
                 }
-                State::None => (),
-                _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
-            },
+            }
             TaskToken::BracketStart => {
                 match state {
                     State::None => {
@@ -52,6 +86,7 @@ pub fn parse(tokens: Vec<Token>) -> Result<AttrMap, ParseError> {
                             Some(i) => i,
                             None => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
                         };
+                        attach_comment(&mut comments, &mut pending, &curr_grp, &curr_path, &s);
                         curr_var.insert(s.into(), inp);
                         state = State::Newline;
                     }
@@ -64,6 +99,7 @@ pub fn parse(tokens: Vec<Token>) -> Result<AttrMap, ParseError> {
                         Some(i) => i,
                         None => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
                     };
+                    attach_comment(&mut comments, &mut pending, &curr_grp, &curr_path, &s);
                     curr_var.insert(s.into(), inp);
                     state = State::Newline;
                 }
@@ -77,6 +113,7 @@ pub fn parse(tokens: Vec<Token>) -> Result<AttrMap, ParseError> {
                 }
                 State::Assignment(s) => {
                     curr_var = move_in(&s, curr_var, &tokens)?;
+                    curr_path.push(s);
                     state = State::None;
                 }
                 _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
@@ -85,6 +122,7 @@ pub fn parse(tokens: Vec<Token>) -> Result<AttrMap, ParseError> {
                 State::AttrGroup(Some(n)) => {
                     curr_var = move_in(&n, curr_var, &tokens)?;
                     curr_grp.push(n);
+                    attach_comment(&mut comments, &mut pending, &[], &[], &curr_grp.join("."));
                     state = State::None;
                 }
                 _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
@@ -104,12 +142,13 @@ pub fn parse(tokens: Vec<Token>) -> Result<AttrMap, ParseError> {
                         Some(i) => i,
                         None => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
                     };
+                    attach_comment(&mut comments, &mut pending, &curr_grp, &curr_path, &s);
                     curr_var.insert(s.into(), inp);
                     state = State::Newline;
                 }
                 _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
             },
-            TaskToken::Bool => (),
+            TaskToken::Bool | TaskToken::Null => (),
             TaskToken::String(s) => match state {
                 State::None => {
                     state = State::Assignment(s);
@@ -128,11 +167,27 @@ pub fn parse(tokens: Vec<Token>) -> Result<AttrMap, ParseError> {
         }
     }
     match state {
-        State::None | State::Newline => Ok(attrmap),
+        State::None | State::Newline => Ok((attrmap, comments)),
         _ => Err(tokens.parse_error(ParseErrorType::Unclosed)),
     }
 }
 
+fn attach_comment(
+    comments: &mut HashMap<String, String>,
+    pending: &mut Vec<String>,
+    group: &[String],
+    path: &[String],
+    key: &str,
+) {
+    if pending.is_empty() {
+        return;
+    }
+    let mut full: Vec<&str> = group.iter().chain(path).map(String::as_str).collect();
+    full.push(key);
+    comments.insert(full.join("."), pending.join("\n"));
+    pending.clear();
+}
+
 fn move_in<'a>(
     key: &str,
     table: &'a mut AttrMap,
@@ -149,3 +204,32 @@ fn move_in<'a>(
         _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tokenizer::get_tokens;
+    use rstest::rstest;
+
+    #[rstest]
+    fn parse_with_comments_test() {
+        let txt = "# area of the basin\n# in sq km\narea = 12.5\n\n# dropped\n\nname = \"x\" # inline\n# group\n[meta]\n# source\nsrc.url = \"a\"\n";
+        let (attrs, comments) = parse_with(get_tokens(txt).unwrap(), true).unwrap();
+        assert_eq!(attrs, parse(get_tokens(txt).unwrap()).unwrap());
+        let mut comments: Vec<(String, String)> = comments.into_iter().collect();
+        comments.sort();
+        assert_eq!(
+            comments,
+            vec![
+                (
+                    "area".to_string(),
+                    "area of the basin\nin sq km".to_string()
+                ),
+                ("meta".to_string(), "group".to_string()),
+                ("meta.src.url".to_string(), "source".to_string()),
+            ]
+        );
+        let (_, comments) = parse_with(get_tokens(txt).unwrap(), false).unwrap();
+        assert!(comments.is_empty());
+    }
+}
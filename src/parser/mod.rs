@@ -1,13 +1,17 @@
 use crate::attrs::{Date, DateTime, Time};
+use crate::colors::Colorize;
 use crate::functions::Propagation;
-use crate::network::StrPath;
-use crate::parser::tokenizer::{get_tokens, TaskToken, VecTokens};
+use crate::network::ReorderMode;
+use crate::parser::tokenizer::{get_tokens, TaskToken, TokenizerOptions};
 use crate::prelude::*;
 use crate::table::Table;
-use abi_stable::std_types::{RString, Tuple2};
+use abi_stable::std_types::{
+    ROption::{RNone, RSome},
+    RString, Tuple2,
+};
 use anyhow::Context;
-use colored::Colorize;
-use std::path::Path;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 pub mod attrs;
@@ -32,9 +36,48 @@ pub struct ParseError {
     pub ty: ParseErrorType,
     pub line: usize,
     pub col: usize,
+    /// length of the token causing the error
+    pub len: usize,
     pub linestr: String,
 }
 
+/// Severity of the [`Diagnostic`]
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Machine readable information about the errors, to be used by the
+/// editor integrations instead of the [`NadiError::user_msg`]
+///
+/// Both `line` and `col` are 0-based like in the LSP, with the
+/// `serde` feature it can be serialized into JSON.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Diagnostic {
+    pub line: usize,
+    pub col: usize,
+    pub len: usize,
+    pub severity: Severity,
+    pub message: String,
+    pub code: &'static str,
+}
+
+impl ParseError {
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic {
+            line: self.line,
+            col: self.col,
+            len: self.len,
+            severity: Severity::Error,
+            message: self.ty.message().to_string(),
+            code: self.ty.code(),
+        }
+    }
+}
+
 impl std::error::Error for ParseError {}
 
 impl std::fmt::Display for ParseError {
@@ -49,6 +92,18 @@ impl std::fmt::Display for ParseError {
     }
 }
 
+impl From<tokenizer::TokenError> for ParseError {
+    fn from(value: tokenizer::TokenError) -> Self {
+        Self {
+            ty: ParseErrorType::InvalidToken,
+            line: value.line,
+            col: value.col - 1,
+            len: 1,
+            linestr: value.linestr,
+        }
+    }
+}
+
 impl NadiError for ParseError {
     fn user_msg(&self, filename: Option<&str>) -> String {
         let mut msg = String::new();
@@ -84,9 +139,23 @@ pub enum ParseErrorType {
     InvalidPropagation,
     SyntaxError,
     InvalidToken,
+    UnknownFunction,
 }
 
 impl ParseErrorType {
+    /// Short code for the error type used in [`Diagnostic`]
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ValueError => "value-error",
+            Self::InvalidLineStart => "invalid-line-start",
+            Self::Unclosed => "unclosed",
+            Self::InvalidPropagation => "invalid-propagation",
+            Self::SyntaxError => "syntax-error",
+            Self::InvalidToken => "invalid-token",
+            Self::UnknownFunction => "unknown-function",
+        }
+    }
+
     pub fn message(&self) -> &str {
         match self {
             Self::ValueError => "Invalid Value",
@@ -95,6 +164,7 @@ impl ParseErrorType {
             Self::InvalidPropagation => "Invalid propagation value",
             Self::SyntaxError => "Invalid Syntax",
             Self::InvalidToken => "Invalid Token",
+            Self::UnknownFunction => "Function not found",
         }
     }
 }
@@ -102,7 +172,34 @@ impl ParseErrorType {
 impl std::str::FromStr for Date {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split('-');
+        Self::from_str_with(s, &TokenizerOptions::default())
+    }
+}
+
+impl Date {
+    /// Parse the date in `YYYY-mm-dd` format, or `mm/dd/YYYY` format
+    /// if [`TokenizerOptions::slash_dates`] is enabled
+    ///
+    /// ```
+    /// # use nadi_core::attrs::Date;
+    /// # use nadi_core::parser::tokenizer::TokenizerOptions;
+    /// let opts = TokenizerOptions { slash_dates: true };
+    /// assert_eq!(Date::from_str_with("12/31/2020", &opts), Ok(Date::new(2020, 12, 31)));
+    /// assert!(Date::from_str_with("12/31/2020", &TokenizerOptions::default()).is_err());
+    /// ```
+    pub fn from_str_with(s: &str, opts: &TokenizerOptions) -> Result<Self, String> {
+        let parts: Vec<&str> = if s.contains('/') {
+            if !opts.slash_dates {
+                return Err(String::from("Invalid Date use YYYY-mm-dd"));
+            }
+            // slash separated dates are in mm/dd/YYYY format
+            let mut p: Vec<&str> = s.split('/').collect();
+            p.rotate_right(1);
+            p
+        } else {
+            s.split('-').collect()
+        };
+        let mut parts = parts.into_iter();
         let year = parts
             .next()
             .ok_or("Year not present")?
@@ -167,6 +264,14 @@ impl std::str::FromStr for Time {
 impl std::str::FromStr for DateTime {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_str_with(s, &TokenizerOptions::default())
+    }
+}
+
+impl DateTime {
+    /// Parse the date and time, see [`Date::from_str_with`] for the
+    /// date formats
+    pub fn from_str_with(s: &str, opts: &TokenizerOptions) -> Result<Self, String> {
         let (d, t) = if let Some((d, t)) = s.split_once(' ') {
             (d.trim(), t.trim())
         } else {
@@ -176,58 +281,305 @@ impl std::str::FromStr for DateTime {
                 return Err(String::from("Invalid DateTime use YYYY-mm-dd HH:MM[:SS]"));
             }
         };
-        Ok(DateTime::new(Date::from_str(d)?, Time::from_str(t)?, None))
+        Ok(DateTime::new(
+            Date::from_str_with(d, opts)?,
+            Time::from_str(t)?,
+            None,
+        ))
     }
 }
 
 impl Network {
     // TODO import DOT format as well, or maybe make it work through plugin
     pub fn from_file<P: AsRef<Path>>(filename: P) -> anyhow::Result<Self> {
-        let mut network = Self::default();
-        let content =
-            std::fs::read_to_string(filename).context("Error while accessing the network file")?;
+        Self::from_file_opts(filename, ReorderMode::Topological)
+    }
+
+    /// Load the network from file, with the node order based on the
+    /// [`ReorderMode`]
+    pub fn from_file_opts<P: AsRef<Path>>(filename: P, mode: ReorderMode) -> anyhow::Result<Self> {
+        let content = read_file(filename).context("Error while accessing the network file")?;
         let tokens = tokenizer::get_tokens(&content)?;
         let paths = network::parse(tokens)?;
-        for path in paths {
-            if !network.nodes_map.contains_key(&path.start) {
-                network.insert_node_by_name(&path.start);
-            }
-            if !network.nodes_map.contains_key(&path.end) {
-                network.insert_node_by_name(&path.end);
-            }
-            let inp = network.node_by_name(&path.start).unwrap();
-            let out = network.node_by_name(&path.end).unwrap();
-            {
-                inp.lock().set_output(out.clone());
-                out.lock().add_input(inp.clone());
-            }
-        }
-        network.reorder();
-        network.set_levels();
-        Ok(network)
+        let edges: Vec<(&str, &str)> = paths
+            .iter()
+            .map(|p| (p.start.as_str(), p.end.as_str()))
+            .collect();
+        Ok(Self::from_edges_opts(&edges, mode))
     }
     pub fn load_attrs<P: AsRef<Path>>(&self, attr_dir: P) -> anyhow::Result<()> {
         self.nodes_map.iter().try_for_each(|Tuple2(name, node)| {
             // ignore the error on attribute read
             let attr_file = attr_dir.as_ref().join(format!("{}.toml", name));
-            if attr_file.exists() && attr_file.is_file() {
+            let gz_file = attr_dir.as_ref().join(format!("{}.toml.gz", name));
+            if attr_file.is_file() {
                 node.lock().load_attr(&attr_file)
+            } else if cfg!(feature = "flate2") && gz_file.is_file() {
+                node.lock().load_attr(&gz_file)
             } else {
                 Ok(())
             }
         })?;
         Ok(())
     }
+
+    /// Save the attributes of each node to `{dir}/{name}.toml`, the
+    /// inverse of [`Network::load_attrs`], returns the number of files
+    /// written
+    ///
+    /// The directory is created if it doesn't exist, and the existing
+    /// files are overwritten. The attributes mirroring the node fields
+    /// ([`crate::node::NODE_PROPERTIES`]) are not saved, use
+    /// [`Network::save_attrs_opts`] to include them.
+    pub fn save_attrs<P: AsRef<Path>>(&self, dir: P) -> anyhow::Result<usize> {
+        self.save_attrs_opts(dir, false)
+    }
+
+    /// Save the attributes of each node to `{dir}/{name}.toml`, with
+    /// the [`crate::node::NODE_PROPERTIES`] if `properties` is true
+    pub fn save_attrs_opts<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        properties: bool,
+    ) -> anyhow::Result<usize> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Error while creating the directory {dir:?}"))?;
+        let mut count = 0;
+        for node in self.nodes() {
+            let node = node.lock();
+            let attr_file = dir.join(format!("{}.toml", node.name()));
+            let mut w = std::io::BufWriter::new(
+                std::fs::File::create(&attr_file)
+                    .with_context(|| format!("Error while creating the file {attr_file:?}"))?,
+            );
+            if properties {
+                crate::attrs::write_attrs(&mut w, node.attr_map())?;
+            } else {
+                let mut attrs = node.attr_map().clone();
+                for prop in crate::node::NODE_PROPERTIES {
+                    attrs.remove(prop);
+                }
+                crate::attrs::write_attrs(&mut w, &attrs)?;
+            }
+            std::io::Write::flush(&mut w)?;
+            count += 1;
+        }
+        Ok(count)
+    }
 }
 
-impl NodeInner {
+/// Read the contents of the file as a string, files with a `.gz`
+/// extension are decompressed (requires the `flate2` feature)
+///
+/// The compression is detected from the extension only, a gzipped
+/// file without the `.gz` extension is read as it is.
+pub fn read_file<P: AsRef<Path>>(file: P) -> std::io::Result<String> {
+    let file = file.as_ref();
+    if file.extension().map_or(true, |e| e != "gz") {
+        return std::fs::read_to_string(file);
+    }
+    #[cfg(feature = "flate2")]
+    {
+        let mut contents = String::new();
+        flate2::read::GzDecoder::new(std::fs::File::open(file)?).read_to_string(&mut contents)?;
+        Ok(contents)
+    }
+    #[cfg(not(feature = "flate2"))]
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!("Reading gzipped file {file:?} requires the flate2 feature"),
+    ))
+}
+
+/// Read the contents of the attribute file, `-` as the filename reads
+/// from the stdin instead
+///
+/// The stdin is read until EOF, so the whole input is consumed by the
+/// first call; any further reads from stdin will get an empty string,
+/// which loads no attributes.
+pub fn read_attr_source<P: AsRef<Path>>(file: P) -> std::io::Result<String> {
+    if file.as_ref() == Path::new("-") {
+        let mut contents = String::new();
+        std::io::stdin().read_to_string(&mut contents)?;
+        Ok(contents)
+    } else {
+        read_file(file)
+    }
+}
+
+/// Read the attributes from the file after the attributes from the
+/// files it includes (see [`crate::attrs::INCLUDE_ATTR`]), `stack`
+/// has the files being read to detect the include cycles
+fn read_attrs_with_includes(file: &Path, stack: &mut Vec<PathBuf>) -> anyhow::Result<AttrMap> {
+    let path = if file == Path::new("-") {
+        file.to_path_buf()
+    } else {
+        file.canonicalize()
+            .with_context(|| format!("Attribute file {file:?} not found"))?
+    };
+    if let Some(pos) = stack.iter().position(|p| *p == path) {
+        let cycle: Vec<String> = stack[pos..]
+            .iter()
+            .chain([&path])
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(anyhow::Error::msg(format!(
+            "Include cycle in attribute files: {}",
+            cycle.join(" -> ")
+        )));
+    }
+    let contents = read_attr_source(file)?;
+    let tokens = tokenizer::get_tokens(&contents)?;
+    let mut local = attrs::parse(tokens)?;
+    let includes: Vec<String> = match local.remove(crate::attrs::INCLUDE_ATTR) {
+        RSome(Attribute::String(s)) => vec![s.into()],
+        RSome(a) => FromAttribute::try_from_attr(&a).map_err(anyhow::Error::msg)?,
+        RNone => vec![],
+    };
+    let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    let mut attrs = AttrMap::new();
+    stack.push(path);
+    for inc in includes {
+        attrs.extend(read_attrs_with_includes(&dir.join(inc), stack)?);
+    }
+    stack.pop();
+    attrs.extend(local);
+    Ok(attrs)
+}
+
+impl Network {
+    /// Load the network attributes from the file, `-` reads them from
+    /// stdin (see [`read_attr_source`])
     pub fn load_attr<P: AsRef<Path>>(&mut self, file: P) -> anyhow::Result<()> {
-        let contents = std::fs::read_to_string(file)?;
+        let contents = read_attr_source(file)?;
         let tokens = tokenizer::get_tokens(&contents)?;
         let attrs = attrs::parse(tokens)?;
-        self.attributes.extend(attrs);
+        self.attr_map_mut().extend(attrs);
         Ok(())
     }
+
+    /// Load the network attributes from stdin, reads until EOF
+    pub fn load_attrs_stdin(&mut self) -> anyhow::Result<()> {
+        self.load_attr("-")
+    }
+}
+
+impl NodeInner {
+    pub fn load_attr<P: AsRef<Path>>(&mut self, file: P) -> anyhow::Result<()> {
+        self.load_attr_with(file, false)
+    }
+
+    /// Load the attributes from the file along with the files it
+    /// includes with the `include` key ([`crate::attrs::INCLUDE_ATTR`])
+    ///
+    /// The included files are relative to the file including them,
+    /// and they can include other files. They are loaded in order
+    /// before the rest of the file, so the keys in the file override
+    /// the ones from the included files.
+    ///
+    /// # Errors
+    /// Errors if any of the files can't be read or parsed, or if the
+    /// files include each other in a cycle.
+    pub fn load_attr_with_includes<P: AsRef<Path>>(&mut self, file: P) -> anyhow::Result<()> {
+        let attrs = read_attrs_with_includes(file.as_ref(), &mut vec![])?;
+        self.extend_attrs(attrs);
+        Ok(())
+    }
+
+    /// Load the attributes from the file, with `keep_comments` the
+    /// comments preceding the keys are saved in the
+    /// [`crate::attrs::COMMENTS_ATTR`] table attribute so that they can be
+    /// written back when the attributes are exported.
+    ///
+    /// `-` as the file reads the attributes from stdin, see
+    /// [`read_attr_source`].
+    pub fn load_attr_with<P: AsRef<Path>>(
+        &mut self,
+        file: P,
+        keep_comments: bool,
+    ) -> anyhow::Result<()> {
+        let contents = read_attr_source(file)?;
+        let tokens = tokenizer::get_tokens(&contents)?;
+        let (attrs, comments) = attrs::parse_with(tokens, keep_comments)?;
+        self.extend_attrs(attrs);
+        if !comments.is_empty() {
+            let key = RString::from(crate::attrs::COMMENTS_ATTR);
+            let mut cmts = match self.attributes.remove(&key) {
+                RSome(Attribute::Table(t)) => t,
+                _ => AttrMap::new(),
+            };
+            cmts.extend(
+                comments
+                    .into_iter()
+                    .map(|(k, v)| (RString::from(k), Attribute::String(v.into()))),
+            );
+            self.attributes.insert(key, Attribute::Table(cmts));
+        }
+        Ok(())
+    }
+}
+
+/// Attribute with the type inferred the same way as the values in
+/// the attribute files: date/time, bool, integer, float, and `null`
+/// are read as their types, the rest are read as [`Attribute::String`]
+///
+/// A quoted string is read with its quotes removed and the escape
+/// sequences decoded, while the arrays and tables are not parsed.
+///
+/// ```
+/// # use nadi_core::attrs::{Attribute, Date};
+/// # use std::str::FromStr;
+/// assert_eq!(Attribute::from_str("2.5"), Ok(Attribute::Float(2.5)));
+/// assert_eq!(
+///     Attribute::try_from("2020-01-01"),
+///     Ok(Attribute::Date(Date::new(2020, 1, 1)))
+/// );
+/// assert_eq!(Attribute::from_str("a b"), Ok(Attribute::String("a b".into())));
+/// ```
+impl FromStr for Attribute {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let val = match get_tokens(s.trim()) {
+            Ok(tokens) if tokens.len() == 1 => {
+                let tk = &tokens[0];
+                // invalid date/time values are still tokenized as
+                // them (e.g. `1-2`), so they are checked here
+                match tk.ty {
+                    TaskToken::Date => Date::from_str(tk.content).ok().map(Attribute::Date),
+                    TaskToken::Time => Time::from_str(tk.content).ok().map(Attribute::Time),
+                    TaskToken::DateTime => {
+                        DateTime::from_str(tk.content).ok().map(Attribute::DateTime)
+                    }
+                    // numbers can overflow
+                    TaskToken::Integer => tk
+                        .content
+                        .replace('_', "")
+                        .parse()
+                        .ok()
+                        .map(Attribute::Integer),
+                    TaskToken::Float => tk
+                        .content
+                        .replace('_', "")
+                        .parse()
+                        .ok()
+                        .map(Attribute::Float),
+                    _ => tk.attribute(),
+                }
+            }
+            _ => None,
+        };
+        Ok(val.unwrap_or_else(|| Attribute::String(s.into())))
+    }
+}
+
+impl TryFrom<&str> for Attribute {
+    type Error = std::convert::Infallible;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Self::from_str(s)
+    }
 }
 
 impl FromStr for Table {
@@ -247,86 +599,271 @@ impl Table {
     }
 }
 
+/// Propagation as written in the tasks, see
+/// [`tasks::parse_propagation`], along with the older forms that only
+/// have the name of the propagation (`inverse` or `(inverse)`)
+///
+/// The conditions are parsed like in the tasks, so the comparisons
+/// like `(elevation > 100)` can be used in the render snippet headers.
 impl FromStr for Propagation {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut tokens = VecTokens::new(get_tokens(&s)?);
-        let tk = match tokens.next_no_ws(false) {
-            None => return Err(anyhow::Error::msg("No propagation")),
-            Some(t) => t,
-        };
-        match tk.ty {
-            TaskToken::Variable => Ok(propagation(tk.content)?),
-            TaskToken::ParenStart => {
-                let tt = match tokens.next_no_ws(false) {
-                    None => return Err(anyhow::Error::msg("No propagation")),
-                    Some(t) => t,
-                };
-                match tt.ty {
-                    TaskToken::Variable => Ok(propagation(tt.content)?),
-                    _ => Err(tokens
-                        .parse_error(ParseErrorType::InvalidPropagation)
-                        .into()),
-                }
-            }
-            TaskToken::BracketStart => {
-                let mut path = false;
-                let mut comma = false;
-                let mut nodes = vec![];
-                while let Some(t) = tokens.next_no_ws(false) {
-                    if comma {
-                        match t.ty {
-                            TaskToken::Comma => {
-                                comma = false;
-                                continue;
-                            }
-                            TaskToken::PathSep => {
-                                path = true;
-                                continue;
-                            }
-                            _ => {
-                                return Err(tokens
-                                    .parse_error(ParseErrorType::InvalidPropagation)
-                                    .into())
-                            }
-                        }
-                    }
-                    match t.ty {
-                        TaskToken::Variable => {
-                            nodes.push(t.content.to_string());
-                            comma = true;
-                        }
-                        TaskToken::String(s) => {
-                            nodes.push(s);
-                            comma = true;
-                        }
-                        _ => {
-                            return Err(tokens
-                                .parse_error(ParseErrorType::InvalidPropagation)
-                                .into())
-                        }
-                    }
-                    if path && nodes.len() == 2 {
-                        return Ok(Propagation::Path(StrPath::new(
-                            nodes[0].as_str().into(),
-                            nodes[1].as_str().into(),
-                        )));
-                    }
-                }
-                let nodes: Vec<RString> = nodes.into_iter().map(|s| s.into()).collect();
-                Ok(Propagation::List(nodes.into()))
-            }
-            _ => Err(anyhow::Error::msg("No propagation")),
+        let name = s.trim();
+        let name = name
+            .strip_prefix('(')
+            .and_then(|n| n.strip_suffix(')'))
+            .unwrap_or(name);
+        match tasks::propagation_name(name.trim()) {
+            Some(p) => Ok(p),
+            None => Ok(tasks::parse_propagation(s)?),
         }
     }
 }
 
-fn propagation(p: &str) -> anyhow::Result<Propagation> {
-    match p {
-        "sequential" => Ok(Propagation::Sequential),
-        "inverse" => Ok(Propagation::Inverse),
-        "inputsfirst" => Ok(Propagation::InputsFirst),
-        "outputfirst" => Ok(Propagation::OutputFirst),
-        _ => Err(anyhow::Error::msg("Invalid propagation type")),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::{CompareOp, Condition};
+    use rstest::rstest;
+
+    #[test]
+    fn propagation_from_str_test() {
+        assert_eq!(
+            Propagation::from_str("inverse").unwrap(),
+            Propagation::Inverse
+        );
+        assert_eq!(
+            Propagation::from_str("(inverse)").unwrap(),
+            Propagation::Inverse
+        );
+        assert_eq!(
+            Propagation::from_str("(=large & !dam)")
+                .unwrap()
+                .to_string(),
+            "(=large & !dam)"
+        );
+        assert_eq!(
+            Propagation::from_str("<sorted.area>").unwrap().to_string(),
+            "<sorted.area>"
+        );
+        assert_eq!(
+            Propagation::from_str("[a, b] - (dam)").unwrap().to_string(),
+            "[a, b] - (dam)"
+        );
+        assert!(Propagation::from_str("(large &").is_err());
+        assert!(Propagation::from_str("unknown").is_err());
+        assert!(Propagation::from_str("<inverse> x").is_err());
+        assert!(Propagation::from_str("[a] [b]").is_err());
+        assert_eq!(
+            Propagation::from_str("(elevation > 100)").unwrap(),
+            Propagation::Conditional(Condition::Compare(
+                "elevation".into(),
+                CompareOp::Greater,
+                Attribute::Integer(100)
+            ))
+        );
+        assert!(Propagation::from_str("(elevation >)").is_err());
+    }
+
+    #[rstest]
+    #[case(
+        "2020-01-01 10:30",
+        Attribute::DateTime(DateTime::new(Date::new(2020, 1, 1), Time::new(10, 30, 0, 0), None))
+    )]
+    #[case("2020-01-01", Attribute::Date(Date::new(2020, 1, 1)))]
+    #[case("10:30:15", Attribute::Time(Time::new(10, 30, 15, 0)))]
+    #[case("true", Attribute::Bool(true))]
+    #[case("2.5", Attribute::Float(2.5))]
+    #[case(" 12 ", Attribute::Integer(12))]
+    #[case("-5", Attribute::Integer(-5))]
+    #[case("null", Attribute::Null)]
+    #[case("\"quoted\"", Attribute::String("quoted".into()))]
+    #[case("river", Attribute::String("river".into()))]
+    #[case("a b", Attribute::String("a b".into()))]
+    #[case("1-2", Attribute::String("1-2".into()))]
+    #[case("[1, 2]", Attribute::String("[1, 2]".into()))]
+    #[case("", Attribute::String("".into()))]
+    #[case("99999999999999999999", Attribute::String("99999999999999999999".into()))]
+    #[case("1e999", Attribute::Float(f64::INFINITY))]
+    fn attribute_from_str_test(#[case] txt: &str, #[case] attr: Attribute) {
+        assert_eq!(Attribute::from_str(txt), Ok(attr.clone()));
+        assert_eq!(Attribute::try_from(txt), Ok(attr));
+    }
+
+    #[rstest]
+    #[case("2020-12-31", false, Some(Date::new(2020, 12, 31)))]
+    #[case("2020-12-31", true, Some(Date::new(2020, 12, 31)))]
+    #[case("12/31/2020", false, None)]
+    #[case("12/31/2020", true, Some(Date::new(2020, 12, 31)))]
+    fn date_from_str_with_test(
+        #[case] txt: &str,
+        #[case] slash_dates: bool,
+        #[case] date: Option<Date>,
+    ) {
+        let opts = TokenizerOptions { slash_dates };
+        assert_eq!(Date::from_str_with(txt, &opts).ok(), date);
+        let dt = DateTime::from_str_with(&format!("{txt} 10:30"), &opts);
+        assert_eq!(dt.ok().map(|d| d.date), date);
+        if !slash_dates {
+            assert_eq!(Date::from_str(txt).ok(), date);
+        }
+    }
+
+    #[test]
+    fn network_load_attr_test() {
+        let path = std::env::temp_dir().join("nadi-network-load-attr-test.toml");
+        std::fs::write(&path, "x = 12\nname = \"river\"\n").unwrap();
+        let mut net = Network::default();
+        net.load_attr(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(net.attr("x"), Some(&Attribute::Integer(12)));
+        assert_eq!(net.attr("name"), Some(&Attribute::String("river".into())));
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn gzipped_files_test() {
+        use std::io::Write;
+        fn write_gz(path: &Path, contents: &str) {
+            let file = std::fs::File::create(path).unwrap();
+            let mut enc = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            enc.write_all(contents.as_bytes()).unwrap();
+            enc.finish().unwrap();
+        }
+        let dir = std::env::temp_dir().join("nadi-gzipped-files-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        write_gz(&dir.join("network.net.gz"), "a -> b\nc -> b\nb -> d\n");
+        write_gz(&dir.join("b.toml.gz"), "x = 1\n");
+        std::fs::write(dir.join("c.toml"), "x = 2\n").unwrap();
+
+        let net = Network::from_file(dir.join("network.net.gz")).unwrap();
+        net.load_attrs(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let mut edges: Vec<(&str, &str)> = net.edges_str().collect();
+        edges.sort();
+        assert_eq!(edges, vec![("a", "b"), ("b", "d"), ("c", "b")]);
+        let x = |n: &str| net.node_by_name(n).unwrap().lock().attr("x").cloned();
+        assert_eq!(x("b"), Some(Attribute::Integer(1)));
+        assert_eq!(x("c"), Some(Attribute::Integer(2)));
+        assert_eq!(x("a"), None);
+    }
+
+    #[test]
+    fn save_attrs_test() {
+        let dir = std::env::temp_dir().join("nadi-save-attrs-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("a.toml"),
+            "# first value\nx = 1\nname = \"first\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("b.toml"), "x = 2\n").unwrap();
+        let load = |net: &Network, dir: &Path| {
+            net.load_attrs(dir).unwrap();
+            let a = net.try_node_by_name("a").unwrap();
+            a.lock().load_attr_with(dir.join("a.toml"), true).unwrap();
+        };
+        let net = Network::from_edges(&[("a", "b"), ("c", "b")]);
+        load(&net, &dir);
+        net.each_node_mut(|n| {
+            let x = n.attr("x").cloned().unwrap_or(Attribute::Integer(0));
+            n.set_attr("y", x);
+            n.set_attr("z", vec![1i64, 2].into());
+        });
+        let out = dir.join("out");
+        assert_eq!(net.save_attrs(&out).unwrap(), 3);
+        let saved = std::fs::read_to_string(out.join("c.toml")).unwrap();
+        assert_eq!(saved, "y = 0\nz = [1, 2]\n");
+        let saved = std::fs::read_to_string(out.join("a.toml")).unwrap();
+        assert!(saved.contains("# first value\nx = 1\n"), "{saved}");
+        assert!(!saved.contains(crate::attrs::COMMENTS_ATTR), "{saved}");
+
+        let other = Network::from_edges(&[("a", "b"), ("c", "b")]);
+        load(&other, &out);
+        std::fs::remove_dir_all(&dir).unwrap();
+        for (n1, n2) in net.nodes().zip(other.nodes()) {
+            assert_eq!(n1.lock().attr_map(), n2.lock().attr_map());
+        }
+    }
+
+    #[test]
+    fn load_attr_properties_test() {
+        let path = std::env::temp_dir().join("nadi-load-attr-properties-test.toml");
+        std::fs::write(&path, "INDEX = 3\nLEVEL = 2\nx = 1\n").unwrap();
+        let net = Network::from_edges(&[("a", "b")]);
+        let a = net.try_node_by_name("a").unwrap();
+        a.lock().load_attr(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let a = a.lock();
+        assert_eq!(a.attr("INDEX"), Some(&Attribute::Integer(a.index() as i64)));
+        assert_eq!(a.attr("LEVEL"), Some(&Attribute::Integer(a.level() as i64)));
+        assert_eq!(a.attr("x"), Some(&Attribute::Integer(1)));
+    }
+
+    #[test]
+    fn load_attr_with_includes_test() {
+        let dir = std::env::temp_dir().join("nadi-load-attr-includes-test");
+        std::fs::create_dir_all(dir.join("common")).unwrap();
+        std::fs::write(dir.join("common/base.toml"), "x = 1\ny = 1\nz = 1\n").unwrap();
+        std::fs::write(
+            dir.join("common/mid.toml"),
+            "include = \"base.toml\"\ny = 2\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("node.toml"),
+            "include = [\"common/mid.toml\"]\nz = 3\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("single.toml"),
+            "include = \"common/base.toml\"\nx = 4\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("a.toml"), "include = \"b.toml\"\n").unwrap();
+        std::fs::write(dir.join("b.toml"), "include = \"a.toml\"\n").unwrap();
+
+        let mut node = NodeInner::new(0, "a");
+        node.load_attr_with_includes(dir.join("single.toml"))
+            .unwrap();
+        assert_eq!(node.attr("x"), Some(&Attribute::Integer(4)));
+        assert_eq!(node.attr("y"), Some(&Attribute::Integer(1)));
+        assert!(node.attr("include").is_none());
+
+        let mut node = NodeInner::new(0, "a");
+        node.load_attr_with_includes(dir.join("node.toml")).unwrap();
+        for (k, v) in [("x", 1), ("y", 2), ("z", 3)] {
+            assert_eq!(node.attr(k), Some(&Attribute::Integer(v)));
+        }
+
+        let err = NodeInner::new(0, "a")
+            .load_attr_with_includes(dir.join("a.toml"))
+            .unwrap_err()
+            .to_string();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(
+            err.starts_with("Include cycle in attribute files:") && err.ends_with("a.toml"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn diagnostic_test() {
+        let err = attrs::parse(get_tokens("x = 1\n]\n").unwrap()).unwrap_err();
+        assert_eq!(
+            err.diagnostic(),
+            Diagnostic {
+                line: 1,
+                col: 0,
+                len: 1,
+                severity: Severity::Error,
+                message: "Invalid Syntax".to_string(),
+                code: "syntax-error",
+            }
+        );
+        let err = get_tokens("x = \"a\\qb\"").unwrap_err();
+        let diag = err.diagnostic();
+        assert_eq!((diag.line, diag.col, diag.len), (0, 6, 4));
+        assert_eq!(diag.code, "invalid-token");
     }
 }
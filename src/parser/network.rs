@@ -41,15 +41,17 @@ pub fn parse(tokens: Vec<Token>) -> Result<Vec<StrPath>, ParseError> {
             // 	    _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
             // 	}
             // },
-            TaskToken::Variable | TaskToken::Integer | TaskToken::Bool => match state {
-                State::None => {
-                    state = State::PathSep(token.content.to_string());
-                }
-                State::Output(s) => {
-                    state = State::Newline(s, token.content.to_string());
+            TaskToken::Variable | TaskToken::Integer | TaskToken::Bool | TaskToken::Null => {
+                match state {
+                    State::None => {
+                        state = State::PathSep(token.content.to_string());
+                    }
+                    State::Output(s) => {
+                        state = State::Newline(s, token.content.to_string());
+                    }
+                    _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
                 }
-                _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
-            },
+            }
             TaskToken::String(s) => match state {
                 State::None => {
                     state = State::PathSep(s);
@@ -1,23 +1,26 @@
 //! This example shows an example of how to parse an escaped string. The
 //! rules for the string are similar to JSON and rust. A string is:
 //!
-//! - Enclosed by double quotes
-//! - Can contain any raw unescaped code point besides \ and "
-//! - Matches the following escape sequences: \b, \f, \n, \r, \t, \", \\, \/
+//! - Enclosed by double quotes or single quotes
+//! - Can contain any raw unescaped code point besides \ and the quote
+//! - Matches the following escape sequences: \b, \f, \n, \r, \t, \", \', \\, \/
 //! - Matches code points like Rust: \u{XXXX}, where XXXX can be up to 6
 //!   hex characters
 //! - an escape followed by whitespace consumes all whitespace between the
 //!   escape and the next non-whitespace character
 //!
+//! Any other escape sequence is an error, the error points to the `\`
+//! of the invalid escape.
+//!
 //! Copied from https://github.com/rust-bakery/nom/blob/main/examples/string.rs
 
 use nom::branch::alt;
-use nom::bytes::complete::{is_not, take_while_m_n};
+use nom::bytes::complete::{take_till, take_while_m_n};
 use nom::character::complete::{char, multispace1};
-use nom::combinator::{map, map_opt, map_res, value, verify};
+use nom::combinator::{cut, map, map_opt, map_res, value, verify};
 use nom::error::{FromExternalError, ParseError};
 use nom::multi::fold_many0 as fold;
-use nom::sequence::{delimited, preceded};
+use nom::sequence::{delimited, preceded, terminated};
 use nom::{IResult, Parser};
 
 // parser combinators are constructed from the bottom up:
@@ -80,6 +83,7 @@ where
             value('\\', char('\\')),
             value('/', char('/')),
             value('"', char('"')),
+            value('\'', char('\'')),
         )),
     )
     .parse(input)
@@ -93,11 +97,14 @@ fn parse_escaped_whitespace<'a, E: ParseError<&'a str>>(
     preceded(char('\\'), multispace1).parse(input)
 }
 
-/// Parse a non-empty block of text that doesn't include \ or "
-fn parse_literal<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
-    // `is_not` parses a string of 0 or more characters that aren't one of the
-    // given characters.
-    let not_quote_slash = is_not("\"\\");
+/// Parse a non-empty block of text that doesn't include \ or the quote
+fn parse_literal<'a, E: ParseError<&'a str>>(
+    quote: char,
+    input: &'a str,
+) -> IResult<&'a str, &'a str, E> {
+    // `take_till` parses a string of 0 or more characters until one of
+    // the given characters.
+    let not_quote_slash = take_till(|c| c == quote || c == '\\');
 
     // `verify` runs a parser, then runs a verification function on the output of
     // the parser. The verification function accepts out output only if it
@@ -118,23 +125,31 @@ enum StringFragment<'a> {
 
 /// Combine parse_literal, parse_escaped_whitespace, and parse_escaped_char
 /// into a StringFragment.
-fn parse_fragment<'a, E>(input: &'a str) -> IResult<&'a str, StringFragment<'a>, E>
+fn parse_fragment<'a, E>(quote: char, input: &'a str) -> IResult<&'a str, StringFragment<'a>, E>
 where
     E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
 {
     alt((
         // The `map` combinator runs a parser, then applies a function to the output
         // of that parser.
-        map(parse_literal, StringFragment::Literal),
+        map(|i| parse_literal(quote, i), StringFragment::Literal),
         map(parse_escaped_char, StringFragment::EscapedChar),
         value(StringFragment::EscapedWS, parse_escaped_whitespace),
     ))
     .parse(input)
 }
 
-/// Parse a string. Use a loop of parse_fragment and push all of the fragments
-/// into an output string.
+/// Parse a string in double quotes or single quotes.
 pub fn parse_string<'a, E>(input: &'a str) -> IResult<&'a str, String, E>
+where
+    E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
+{
+    alt((|i| parse_quoted('"', i), |i| parse_quoted('\'', i))).parse(input)
+}
+
+/// Parse a string in the given quote. Use a loop of parse_fragment
+/// and push all of the fragments into an output string.
+fn parse_quoted<'a, E>(quote: char, input: &'a str) -> IResult<&'a str, String, E>
 where
     E: ParseError<&'a str> + FromExternalError<&'a str, std::num::ParseIntError>,
 {
@@ -142,7 +157,7 @@ where
     // and for each output value, calls a folding function on each output value.
     let build_string = fold(
         // Our parser function – parses a single string fragment
-        parse_fragment,
+        |i| parse_fragment(quote, i),
         // Our init value, an empty string
         String::new,
         // Our folding function. For each fragment, append the fragment to the
@@ -161,7 +176,11 @@ where
     // " character, the closing delimiter " would never match. When using
     // `delimited` with a looping parser (like fold), be sure that the
     // loop won't accidentally match your closing delimiter!
-    delimited(char('"'), build_string, char('"')).parse(input)
+    //
+    // Once the opening quote is matched, the error is not recoverable
+    // (`cut`), so that it points to the invalid escape or the
+    // unclosed string instead of the quote.
+    preceded(char(quote), cut(terminated(build_string, char(quote)))).parse(input)
 }
 
 #[cfg(test)]
@@ -1,19 +1,17 @@
-use crate::functions::{Condition, Propagation};
+use crate::functions::{CompareOp, Condition, NadiFunctions, Propagation};
 use crate::network::StrPath;
-use crate::parser::tokenizer::{TaskToken, Token, VecTokens};
+use crate::parser::tokenizer::{get_tokens, TaskToken, Token, VecTokens};
 use crate::parser::{ParseError, ParseErrorType};
 use crate::prelude::*;
 use crate::tasks::{FunctionCall, Task, TaskInput, TaskKeyword, TaskType};
 use abi_stable::std_types::{RBox, RString, RVec};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Clone, PartialEq, Debug)]
 enum State {
     None,
     Help(Option<TaskKeyword>),
     Propagation,
-    PropagationList,
-    PropagationPath,
     Attribute,
     Assignment,
     Rhs,
@@ -25,7 +23,6 @@ enum State {
 pub fn parse(tokens: Vec<Token>) -> Result<Vec<Task>, ParseError> {
     let mut tokens = VecTokens::new(tokens);
     let mut curr_keyword = None;
-    let mut data: Vec<String> = vec![];
     let mut propagation: Option<Propagation> = None;
     let mut output: Option<String> = None;
     let mut state = State::None;
@@ -187,7 +184,8 @@ pub fn parse(tokens: Vec<Token>) -> Result<Vec<Task>, ParseError> {
             },
             TaskToken::BracketStart => match state {
                 State::Propagation => {
-                    state = State::PropagationList;
+                    propagation = Some(read_list_propagation(&mut tokens)?);
+                    state = State::Attribute;
                 }
                 State::Rhs => {
                     let inp = match read_attribute(Some(token.clone()), &mut tokens, true)? {
@@ -238,14 +236,9 @@ pub fn parse(tokens: Vec<Token>) -> Result<Vec<Task>, ParseError> {
                 }
                 _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
             },
-            TaskToken::PathSep => match state {
-                State::PropagationList => {
-                    state = State::PropagationPath;
-                }
-                _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
-            },
+            TaskToken::PathSep => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
             TaskToken::Comma => match state {
-                State::PropagationList | State::FuncArgs(_) | State::FuncKeyArgs(_, _) => (),
+                State::FuncArgs(_) | State::FuncKeyArgs(_, _) => (),
                 _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
             },
             TaskToken::Dot => match state {
@@ -268,6 +261,7 @@ pub fn parse(tokens: Vec<Token>) -> Result<Vec<Task>, ParseError> {
                                 TaskType::Node(prop)
                             }
                             TaskKeyword::Network => TaskType::Network,
+                            TaskKeyword::Env => TaskType::Env,
                             _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
                         };
                         tasks.push(Task {
@@ -281,40 +275,13 @@ pub fn parse(tokens: Vec<Token>) -> Result<Vec<Task>, ParseError> {
                 }
             }
             TaskToken::BraceEnd => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
-            TaskToken::BracketEnd => {
-                match state {
-                    State::PropagationList => {
-                        let mut lst = RVec::with_capacity(data.len());
-                        data.drain(..).for_each(|s| {
-                            lst.push(RString::from(s));
-                        });
-                        propagation = Some(Propagation::List(lst));
-                        state = State::Attribute;
-                    }
-                    State::PropagationPath => {
-                        // since we only change to PropagationPath after encountering -> it should have at least 1 member
-                        let start = data[0].to_string();
-                        let end = match data.get(1) {
-                            Some(e) => e.to_string(),
-                            None => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
-                        };
-                        data = vec![];
-                        propagation =
-                            Some(Propagation::Path(StrPath::new(start.into(), end.into())));
-                        state = State::Attribute;
-                    }
-                    _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
-                }
-            }
+            TaskToken::BracketEnd => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
             TaskToken::Variable => {
                 match state {
                     State::Help(hkw) => {
                         tasks.push(Task::help(hkw, Some(token.content.to_string())));
                         state = State::None;
                     }
-                    State::PropagationList | State::PropagationPath => {
-                        data.push(token.content.to_string());
-                    }
                     State::Attribute => {
                         output = Some(token.content.to_string());
                         state = State::Assignment;
@@ -368,12 +335,16 @@ pub fn parse(tokens: Vec<Token>) -> Result<Vec<Task>, ParseError> {
             }
             TaskToken::Function => match state {
                 State::Attribute | State::Propagation | State::Rhs => {
-                    if let Some(TaskKeyword::Env) = curr_keyword {
-                        // env rhs can only be literal values
-                        return Err(tokens.parse_error(ParseErrorType::ValueError));
-                    } else {
-                        state = State::Function(token.content.to_string());
+                    match (&curr_keyword, &state, &output) {
+                        // env rhs can only be literal values or
+                        // partially applied functions
+                        (Some(TaskKeyword::Env), State::Rhs, Some(_)) => (),
+                        (Some(TaskKeyword::Env), _, _) => {
+                            return Err(tokens.parse_error(ParseErrorType::ValueError))
+                        }
+                        _ => (),
                     }
+                    state = State::Function(token.content.to_string());
                 }
                 _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
             },
@@ -388,9 +359,6 @@ pub fn parse(tokens: Vec<Token>) -> Result<Vec<Task>, ParseError> {
                 _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
             },
             TaskToken::String(ref s) => match state {
-                State::PropagationList | State::PropagationPath => {
-                    data.push(s.to_string());
-                }
                 State::Rhs => {
                     let ty = match curr_keyword {
                         Some(TaskKeyword::Node) => {
@@ -439,12 +407,6 @@ pub fn parse(tokens: Vec<Token>) -> Result<Vec<Task>, ParseError> {
                 _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
             },
             _ => match state {
-                State::PropagationList | State::PropagationPath => match token.ty {
-                    TaskToken::Bool | TaskToken::Integer => {
-                        data.push(token.content.to_string());
-                    }
-                    _ => return Err(tokens.parse_error(ParseErrorType::ValueError)),
-                },
                 State::Rhs => {
                     let ty = match curr_keyword {
                         Some(TaskKeyword::Node) => {
@@ -549,6 +511,9 @@ pub fn read_attribute(
                         _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
                     }
                 }
+                if vals.is_empty() && t.ty == TaskToken::BracketEnd {
+                    return Ok(Some(Attribute::Array(RVec::new())));
+                }
                 if let Some(a) = read_attribute(Some(t), tokens, newline)? {
                     vals.push(a);
                     want_comma = true;
@@ -582,6 +547,9 @@ pub fn read_attribute(
                         _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
                     }
                 }
+                if vals.is_empty() && name.is_none() && t.ty == TaskToken::BraceEnd {
+                    return Ok(Some(Attribute::Table(AttrMap::new())));
+                }
                 let val = name.take();
                 if let Some(val) = val {
                     // has name needs value
@@ -616,7 +584,211 @@ pub fn read_attribute(
     }
 }
 
-fn read_propagation(tokens: &mut VecTokens) -> Result<Option<Propagation>, ParseError> {
+/// Parse the tasks script without executing it, collecting all the
+/// errors instead of stopping at the first one
+///
+/// After an error the parsing continues from the next line, so a
+/// task spanning multiple lines can cause more than one error.
+pub fn validate_script(txt: &str) -> Result<Vec<Task>, Vec<ParseError>> {
+    let lines: Vec<&str> = txt.split_inclusive('\n').collect();
+    let mut tasks = vec![];
+    let mut errors = vec![];
+    let mut start = 0;
+    while start < lines.len() {
+        let rest = lines[start..].concat();
+        match get_tokens(&rest).map_err(ParseError::from).and_then(parse) {
+            Ok(t) => {
+                tasks.extend(t);
+                break;
+            }
+            Err(mut e) => {
+                e.line += start;
+                start = e.line + 1;
+                errors.push(e);
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(tasks)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Same as [`validate_script`], and also checks that all the called
+/// functions are available in `functions` or are defined as partial
+/// functions (`env name = func(..)`) in the script
+pub fn validate_script_functions(
+    txt: &str,
+    functions: &NadiFunctions,
+) -> Result<Vec<Task>, Vec<ParseError>> {
+    let tasks = validate_script(txt)?;
+    let partials: HashSet<&str> = tasks
+        .iter()
+        .filter(|t| t.ty == TaskType::Env && matches!(t.input, TaskInput::Function(_)))
+        .filter_map(|t| t.attribute.as_deref())
+        .collect();
+    // the script is already tokenized successfully above
+    let mut tokens = VecTokens::new(get_tokens(txt).map_err(|e| vec![e.into()])?);
+    let mut errors = vec![];
+    while let Some(t) = tokens.next() {
+        if t.ty != TaskToken::Function {
+            continue;
+        }
+        let name: String = t.content.split_whitespace().collect();
+        if functions.node(&name).is_none()
+            && functions.network(&name).is_none()
+            && !partials.contains(name.as_str())
+        {
+            errors.push(tokens.parse_error(ParseErrorType::UnknownFunction));
+        }
+    }
+    if errors.is_empty() {
+        Ok(tasks)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parse the propagation as written after the `node` keyword in the
+/// tasks, it can be any of:
+/// - a named one: `<sequential>`, `<inverse>`, `<sorted.attr>`, etc.
+/// - a conditional: `(attr)`, `(=attr & !other)`, `(==attr)`, etc.
+/// - a list of nodes: `[a, b, c]`,
+/// - a path between nodes: `[a -> b]`,
+/// - the ones above combined with `&` (intersect), `|` (union) and
+///   `-` (difference) from left to right, with `()` to group them:
+///   `[a, b] & (<upstream.c> | (x))`.
+///
+/// ```
+/// # use nadi_core::parser::tasks::parse_propagation;
+/// # use nadi_core::functions::Propagation;
+/// assert_eq!(parse_propagation("<inverse>").unwrap(), Propagation::Inverse);
+/// assert_eq!(parse_propagation("(=a & b)").unwrap().to_string(), "(=a & b)");
+/// assert_eq!(
+///     parse_propagation("[a] | <inverse> - [b]").unwrap().to_string(),
+///     "([a] | <inverse>) - [b]"
+/// );
+/// ```
+pub fn parse_propagation(txt: &str) -> Result<Propagation, ParseError> {
+    let mut tokens = VecTokens::new(get_tokens(txt)?);
+    let prop = read_combined_propagation(&mut tokens)?;
+    match tokens.next_no_ws(true) {
+        Some(_) => Err(tokens.parse_error(ParseErrorType::SyntaxError)),
+        None => Ok(prop),
+    }
+}
+
+/// Read the propagations combined with `&`, `|` and `-` from left to
+/// right
+fn read_combined_propagation(tokens: &mut VecTokens) -> Result<Propagation, ParseError> {
+    let mut prop = read_single_propagation(tokens)?;
+    loop {
+        let combine: fn(Propagation, Propagation) -> Propagation =
+            match tokens.peek_next_no_ws(true).map(|t| &t.ty) {
+                Some(TaskToken::And) => Propagation::intersect,
+                Some(TaskToken::Or) => Propagation::union,
+                Some(TaskToken::Minus) => Propagation::difference,
+                _ => return Ok(prop),
+            };
+        tokens.next_no_ws(true);
+        prop = combine(prop, read_single_propagation(tokens)?);
+    }
+}
+
+/// Read a single propagation, or the combined ones grouped inside `()`
+fn read_single_propagation(tokens: &mut VecTokens) -> Result<Propagation, ParseError> {
+    let prop = match tokens.next_no_ws(true) {
+        Some(t) => match t.ty {
+            TaskToken::AngleStart => read_propagation(tokens)?,
+            // a group starts with a propagation, and a conditional
+            // with the attribute name or the operators
+            TaskToken::ParenStart => match tokens.peek_next_no_ws(true).map(|t| &t.ty) {
+                Some(TaskToken::AngleStart | TaskToken::ParenStart | TaskToken::BracketStart) => {
+                    let prop = read_combined_propagation(tokens)?;
+                    match tokens.next_no_ws(true) {
+                        Some(t) if t.ty == TaskToken::ParenEnd => Some(prop),
+                        Some(_) => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
+                        None => None,
+                    }
+                }
+                _ => read_conditional(tokens)?,
+            },
+            TaskToken::BracketStart => Some(read_list_propagation(tokens)?),
+            _ => return Err(tokens.parse_error(ParseErrorType::InvalidPropagation)),
+        },
+        None => None,
+    };
+    prop.ok_or_else(|| tokens.parse_error(ParseErrorType::Unclosed))
+}
+
+/// Propagation from its name, used inside the `<>`
+pub(crate) fn propagation_name(name: &str) -> Option<Propagation> {
+    match name {
+        "sequential" => Some(Propagation::Sequential),
+        "inverse" => Some(Propagation::Inverse),
+        "inputsfirst" => Some(Propagation::InputsFirst),
+        "outputfirst" => Some(Propagation::OutputFirst),
+        _ => None,
+    }
+}
+
+/// Read the list (`[a, b]`) or path (`[a -> b]`) propagation after
+/// the opening `[`
+pub(crate) fn read_list_propagation(tokens: &mut VecTokens) -> Result<Propagation, ParseError> {
+    let mut data: Vec<String> = vec![];
+    // number of names before the `->` in a path
+    let mut path: Option<usize> = None;
+    while let Some(t) = tokens.next_no_ws(true) {
+        match t.ty {
+            TaskToken::Variable | TaskToken::Bool | TaskToken::Null | TaskToken::Integer => {
+                data.push(t.content.to_string())
+            }
+            TaskToken::String(s) => data.push(s),
+            TaskToken::Comma if path.is_none() => (),
+            TaskToken::PathSep if path.is_none() => path = Some(data.len()),
+            TaskToken::BracketEnd if path.is_some() => {
+                // path should have only the start and end nodes
+                return match (path, data.as_slice()) {
+                    (Some(1), [start, end]) => Ok(Propagation::Path(StrPath::new(
+                        start.as_str().into(),
+                        end.as_str().into(),
+                    ))),
+                    _ => Err(tokens.parse_error(ParseErrorType::SyntaxError)),
+                };
+            }
+            TaskToken::BracketEnd => {
+                return Ok(Propagation::List(
+                    data.into_iter().map(RString::from).collect(),
+                ))
+            }
+            _ => return Err(tokens.parse_error(ParseErrorType::ValueError)),
+        }
+    }
+    Err(tokens.parse_error(ParseErrorType::Unclosed))
+}
+
+/// Read the `.name` part of the propagations like `<sorted.attr>`,
+/// the name can be quoted
+fn read_dot_name(tokens: &mut VecTokens) -> Result<Option<String>, ParseError> {
+    match tokens.next_no_ws(true) {
+        Some(t) if t.ty == TaskToken::Dot => (),
+        _ => return Err(tokens.parse_error(ParseErrorType::InvalidPropagation)),
+    }
+    match tokens.next_no_ws(true) {
+        Some(t) => match t.ty {
+            // numeric node names as they are written (`<upstream.0123>`)
+            TaskToken::Variable | TaskToken::Integer | TaskToken::Float => {
+                Ok(Some(t.content.to_string()))
+            }
+            TaskToken::String(s) => Ok(Some(s)),
+            _ => Err(tokens.parse_error(ParseErrorType::InvalidPropagation)),
+        },
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn read_propagation(tokens: &mut VecTokens) -> Result<Option<Propagation>, ParseError> {
     let tk = match tokens.next_no_ws(true) {
         None => return Ok(None),
         Some(t) => t,
@@ -627,11 +799,21 @@ fn read_propagation(tokens: &mut VecTokens) -> Result<Option<Propagation>, Parse
         _ => return Err(tokens.parse_error(ParseErrorType::InvalidPropagation)),
     };
     let prop = match prop.as_str() {
-        "sequential" => Propagation::Sequential,
-        "inverse" => Propagation::Inverse,
-        "inputsfirst" => Propagation::InputsFirst,
-        "outputfirst" => Propagation::OutputFirst,
-        _ => return Err(tokens.parse_error(ParseErrorType::InvalidPropagation)),
+        // <sorted.attr> or <sorted_desc.attr>
+        "sorted" | "sorted_desc" => match read_dot_name(tokens)? {
+            Some(attr) => Propagation::SortedBy(attr.into(), prop == "sorted"),
+            None => return Ok(None),
+        },
+        // <upstream.node> or <downstream.node>
+        "upstream" | "downstream" => match read_dot_name(tokens)? {
+            Some(node) if prop == "upstream" => Propagation::Upstream(node.into()),
+            Some(node) => Propagation::Downstream(node.into()),
+            None => return Ok(None),
+        },
+        p => match propagation_name(p) {
+            Some(p) => p,
+            None => return Err(tokens.parse_error(ParseErrorType::InvalidPropagation)),
+        },
     };
     match tokens.next_no_ws(true) {
         None => Ok(None),
@@ -649,7 +831,7 @@ enum CondState {
     SecondVar(Condition, bool),
 }
 
-fn read_conditional(tokens: &mut VecTokens) -> Result<Option<Propagation>, ParseError> {
+pub(crate) fn read_conditional(tokens: &mut VecTokens) -> Result<Option<Propagation>, ParseError> {
     let mut state = CondState::FirstVar(0);
     let mut strict = 0;
     let cond = loop {
@@ -667,6 +849,17 @@ fn read_conditional(tokens: &mut VecTokens) -> Result<Option<Propagation>, Parse
                 }
                 _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
             },
+            TaskToken::Equal => match state {
+                CondState::FirstVar(0) => state = CondState::FirstVar(2),
+                _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
+            },
+            // the comparisons are read along with the attribute names
+            // in read_comparison, they can't be anywhere else
+            TaskToken::NotEqual
+            | TaskToken::LessEqual
+            | TaskToken::GreaterEqual
+            | TaskToken::AngleStart
+            | TaskToken::AngleEnd => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
             TaskToken::And => match state {
                 CondState::Cond(s) => {
                     state = CondState::SecondVar(s, true);
@@ -687,11 +880,11 @@ fn read_conditional(tokens: &mut VecTokens) -> Result<Option<Propagation>, Parse
                 CondState::SecondVar(f, a) => match tokens.next_no_ws(true) {
                     Some(t) => {
                         let var = match t.ty {
-                            TaskToken::Variable => tk.content.to_string(),
+                            TaskToken::Variable => t.content.to_string(),
                             TaskToken::String(s) => s,
                             _ => return Err(tokens.parse_error(ParseErrorType::SyntaxError)),
                         };
-                        let cv = Condition::Not(RBox::new(Condition::Single(var.into())));
+                        let cv = Condition::Not(RBox::new(read_comparison(var, tokens)?));
                         let cond = if a {
                             Condition::And(RBox::new(f), RBox::new(cv))
                         } else {
@@ -711,25 +904,25 @@ fn read_conditional(tokens: &mut VecTokens) -> Result<Option<Propagation>, Parse
                 }
             }
             ty => {
-                let s = match ty {
-                    TaskToken::Variable => tk.content.to_string(),
-                    TaskToken::String(s) => s,
+                let c = match ty {
+                    TaskToken::Variable => read_comparison(tk.content.to_string(), tokens)?,
+                    TaskToken::String(s) => read_comparison(s, tokens)?,
+                    TaskToken::Bool => Condition::Literal(tk.content == "true"),
                     _ => return Err(tokens.parse_error(ParseErrorType::InvalidPropagation)),
                 };
                 match state {
                     CondState::FirstVar(i) => {
                         strict = i;
-                        state = CondState::Cond(Condition::Single(s.into()));
+                        state = CondState::Cond(c);
                     }
                     CondState::Not => {
-                        state =
-                            CondState::Cond(Condition::Not(RBox::new(Condition::Single(s.into()))));
+                        state = CondState::Cond(Condition::Not(RBox::new(c)));
                     }
                     CondState::SecondVar(f, a) => {
                         let cond = if a {
-                            Condition::And(RBox::new(f), RBox::new(Condition::Single(s.into())))
+                            Condition::And(RBox::new(f), RBox::new(c))
                         } else {
-                            Condition::Or(RBox::new(f), RBox::new(Condition::Single(s.into())))
+                            Condition::Or(RBox::new(f), RBox::new(c))
                         };
                         state = CondState::Cond(cond);
                     }
@@ -745,3 +938,130 @@ fn read_conditional(tokens: &mut VecTokens) -> Result<Option<Propagation>, Parse
     };
     Ok(Some(prop))
 }
+
+/// Comparison of the attribute with the value after it if there is
+/// one (`elevation > 100`), otherwise the attribute itself is the
+/// condition
+fn read_comparison(name: String, tokens: &mut VecTokens) -> Result<Condition, ParseError> {
+    let op = match tokens.peek_next_no_ws(true).map(|t| &t.ty) {
+        Some(TaskToken::Equal) => CompareOp::Equal,
+        Some(TaskToken::NotEqual) => CompareOp::NotEqual,
+        Some(TaskToken::AngleStart) => CompareOp::Less,
+        Some(TaskToken::LessEqual) => CompareOp::LessEqual,
+        Some(TaskToken::AngleEnd) => CompareOp::Greater,
+        Some(TaskToken::GreaterEqual) => CompareOp::GreaterEqual,
+        _ => return Ok(Condition::Single(name.into())),
+    };
+    tokens.next_no_ws(true);
+    match read_attribute(None, tokens, true)? {
+        Some(val) => Ok(Condition::Compare(name.into(), op, val)),
+        None => Err(tokens.parse_error(ParseErrorType::Unclosed)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("<sequential>", "<sequential>")]
+    #[case("<inverse>", "<inverse>")]
+    #[case("<sorted.area>", "<sorted.area>")]
+    #[case("<sorted_desc . area>", "<sorted_desc.area>")]
+    #[case("<upstream.a>", "<upstream.a>")]
+    #[case("<downstream . \"b\">", "<downstream.b>")]
+    #[case("<upstream.0123>", "<upstream.\"0123\">")]
+    #[case("<upstream.\"0123\">", "<upstream.\"0123\">")]
+    #[case("<downstream.1e5>", "<downstream.\"1e5\">")]
+    #[case("<upstream.\"a b\">", "<upstream.\"a b\">")]
+    #[case("(a & !b)", "(a & !b)")]
+    #[case("(=a | b)", "(=a | b)")]
+    #[case("(==a)", "(==a)")]
+    #[case("(= =a)", "(==a)")]
+    #[case("(elevation > 100)", "(elevation > 100)")]
+    #[case("(a>=1.5 & !b == \"x\")", "(a >= 1.5 & !b == \"x\")")]
+    #[case("(=a < 2 | b <= -1)", "(=a < 2 | b <= -1)")]
+    #[case("(a != true & c)", "(a != true & c)")]
+    #[case("(==d > 2020-01-01)", "(==d > 2020-01-01)")]
+    #[case("[a, b, \"c d\"]", "[a, b, c d]")]
+    #[case("[a -> b]", "[a -> b]")]
+    #[case("[a] & <upstream.c>", "[a] & <upstream.c>")]
+    #[case(
+        "[a] | <inverse> - <downstream.d>",
+        "([a] | <inverse>) - <downstream.d>"
+    )]
+    #[case("[a] & (<inverse> | [b])", "[a] & (<inverse> | [b])")]
+    #[case("(x) - (<inverse> & <sorted.y>)", "(x) - (<inverse> & <sorted.y>)")]
+    #[case("((x & y) | [a -> b])", "(x & y) | [a -> b]")]
+    fn parse_propagation_test(#[case] txt: &str, #[case] prop: &str) {
+        assert_eq!(parse_propagation(txt).unwrap().to_string(), prop);
+    }
+
+    #[rstest]
+    #[case(Propagation::list(["a"]).intersect(Propagation::Inverse.union(Propagation::list(["b"]))))]
+    #[case(Propagation::list(["a"]).intersect(Propagation::Inverse).union(Propagation::list(["b"])))]
+    #[case(Propagation::Upstream("c".into()).difference(Propagation::Downstream("d".into())))]
+    #[case(Propagation::Upstream("river mouth".into()))]
+    #[case(Propagation::Downstream("0123".into()))]
+    #[case(Propagation::Upstream("a->b".into()))]
+    #[case(Propagation::Upstream("node".into()))]
+    #[case(Propagation::Sequential.union(
+        Propagation::Inverse.difference(Propagation::SortedBy("x".into(), false))
+    ))]
+    fn propagation_round_trip_test(#[case] prop: Propagation) {
+        assert_eq!(parse_propagation(&prop.to_string()).unwrap(), prop);
+    }
+
+    #[rstest]
+    fn validate_script_test() {
+        let txt = "node x = 1\nnode y = )\nnode z = 2\nnode w = )\n";
+        let errors = validate_script(txt).unwrap_err();
+        let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![1, 3]);
+        assert_eq!(
+            validate_script("node x = 1\nnode y = 2\n").unwrap().len(),
+            2
+        );
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn validate_script_functions_test() {
+        let mut funcs = NadiFunctions::default();
+        crate::internal::register_internal(&mut funcs);
+        let txt = "env f = set_attrs(x = 1)\nnode f()\nnode render(\"x\")\nnode  set_atrs(y = 2)\n";
+        let errors = validate_script_functions(txt, &funcs).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        let diag = errors[0].diagnostic();
+        assert_eq!((diag.line, diag.col, diag.len), (3, 6, 8));
+        assert_eq!(diag.code, "unknown-function");
+    }
+
+    #[rstest]
+    #[case("<unknown>", ParseErrorType::InvalidPropagation)]
+    #[case("<upstream a>", ParseErrorType::InvalidPropagation)]
+    #[case("[a, b", ParseErrorType::Unclosed)]
+    #[case("[a ->]", ParseErrorType::SyntaxError)]
+    #[case("[-> a]", ParseErrorType::SyntaxError)]
+    #[case("[a, b -> c]", ParseErrorType::SyntaxError)]
+    #[case("[a -> b c]", ParseErrorType::SyntaxError)]
+    #[case("[a b -> c]", ParseErrorType::SyntaxError)]
+    #[case("[a -> b, c]", ParseErrorType::ValueError)]
+    #[case("<inverse> x", ParseErrorType::SyntaxError)]
+    #[case("inverse", ParseErrorType::InvalidPropagation)]
+    #[case("", ParseErrorType::Unclosed)]
+    #[case("(===a)", ParseErrorType::SyntaxError)]
+    #[case("(<=a)", ParseErrorType::SyntaxError)]
+    #[case("(a != b)", ParseErrorType::ValueError)]
+    #[case("(a > )", ParseErrorType::ValueError)]
+    #[case("(a >", ParseErrorType::Unclosed)]
+    #[case("(a > 1 > 2)", ParseErrorType::SyntaxError)]
+    #[case("(true == 1)", ParseErrorType::SyntaxError)]
+    #[case("[a] &", ParseErrorType::Unclosed)]
+    #[case("([a] | [b]", ParseErrorType::Unclosed)]
+    #[case("([a] | [b] x)", ParseErrorType::SyntaxError)]
+    fn parse_propagation_error_test(#[case] txt: &str, #[case] err: ParseErrorType) {
+        assert_eq!(parse_propagation(txt).unwrap_err().ty, err);
+    }
+}
@@ -1,14 +1,14 @@
+use crate::colors::Colorize;
 use crate::parser::string::parse_string;
 use crate::parser::NadiError;
-use crate::parser::{ParseError as TaskParseError, ParseErrorType};
+use crate::parser::{Diagnostic, ParseError as TaskParseError, ParseErrorType, Severity};
 use crate::tasks::TaskKeyword;
-use colored::Colorize;
 use nadi_core::attrs::{Attribute, Date, DateTime, Time};
 use nom::{
     branch::alt,
     bytes::complete::{is_not, tag},
-    character::complete::{alpha1, alphanumeric1, char, digit1, one_of},
-    combinator::{cut, map, opt, recognize},
+    character::complete::{alpha1, alphanumeric1, char, digit1, one_of, satisfy},
+    combinator::{cond, cut, map, map_opt, not, opt, recognize},
     error::{context, VerboseError},
     multi::{many0, many1},
     sequence::{pair, preceded, terminated, tuple},
@@ -35,6 +35,20 @@ impl std::fmt::Display for TokenError {
     }
 }
 
+impl TokenError {
+    /// The erroneous part spans from the column to the end of the line
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic {
+            line: self.line,
+            col: self.col - 1,
+            len: self.linestr.chars().count().saturating_sub(self.col - 1),
+            severity: Severity::Error,
+            message: "Invalid Token".to_string(),
+            code: ParseErrorType::InvalidToken.code(),
+        }
+    }
+}
+
 impl NadiError for TokenError {
     fn user_msg(&self, filename: Option<&str>) -> String {
         let mut msg = String::new();
@@ -51,7 +65,13 @@ impl NadiError for TokenError {
                 format!("{}:{}:{}", fname, self.line, self.col).blue()
             ));
         }
-        let off = self.col - 1;
+        // col is in characters, convert it to byte offset for slicing
+        let off = self
+            .linestr
+            .char_indices()
+            .nth(self.col - 1)
+            .map(|(i, _)| i)
+            .unwrap_or(self.linestr.len());
         msg.push_str(&format!(
             "  {}{}\n",
             &self.linestr[..off],
@@ -79,6 +99,28 @@ impl<'a> Token<'a> {
     }
 }
 
+/// Token that owns its content, unlike [`Token`] that borrows from
+/// the input string.
+///
+/// [`Token`] is used in the parsers as it doesn't need any extra
+/// allocation, but it can't outlive the source text. Use
+/// [`get_tokens_owned`] when the tokens need to be kept around
+/// (e.g. editor tooling) after the source is dropped.
+#[derive(Clone, PartialEq, Debug)]
+pub struct OwnedToken {
+    pub ty: TaskToken,
+    pub content: String,
+    /// byte range of the token in the source text
+    pub span: std::ops::Range<usize>,
+}
+
+impl OwnedToken {
+    /// Borrowed [`Token`] to use with the parsers
+    pub fn as_token(&self) -> Token<'_> {
+        Token::new(self.ty.clone(), &self.content)
+    }
+}
+
 pub struct VecTokens<'a> {
     tokens: Vec<Token<'a>>,
     pub line: usize,
@@ -98,7 +140,7 @@ impl<'a> Iterator for VecTokens<'a> {
             self.linestr.clear();
         } else {
             self.colstart = self.colend;
-            self.colend += t.content.len();
+            self.colend += t.content.chars().count();
             self.linestr.push_str(&t.colored());
         }
         Some(t)
@@ -154,6 +196,7 @@ impl<'a> VecTokens<'a> {
             ty,
             line: self.line,
             col: self.colstart,
+            len: self.colend - self.colstart,
             linestr: self.linestr_eol(),
         }
     }
@@ -170,6 +213,7 @@ pub enum TaskToken {
     BraceStart,   // {}
     BracketStart, // []
     PathSep,      // ->
+    Minus,        // -
     Comma,
     Dot,
     And,
@@ -182,8 +226,13 @@ pub enum TaskToken {
     Variable,
     Function,
     Assignment,
+    Equal,        // ==
+    NotEqual,     // !=
+    LessEqual,    // <=
+    GreaterEqual, // >=
     Bool,
-    String(String), // might need new value instead of slice (think escape seq)
+    Null,
+    String(String), // value with the escape sequences decoded
     Integer,
     Float,
     Date,
@@ -206,6 +255,7 @@ impl<'a> Token<'a> {
             TaskToken::BraceStart => format!("{}", self.content.blue()),
             TaskToken::BracketStart => format!("{}", self.content.blue()),
             TaskToken::PathSep => format!("{}", self.content.blue()),
+            TaskToken::Minus => format!("{}", self.content.yellow()),
             TaskToken::Comma => format!("{}", self.content.blue()),
             TaskToken::Dot => format!("{}", self.content.blue()),
             TaskToken::And => format!("{}", self.content.yellow()),
@@ -218,7 +268,12 @@ impl<'a> Token<'a> {
             TaskToken::Variable => format!("{}", self.content.green()),
             TaskToken::Function => format!("{}", self.content.magenta()),
             TaskToken::Assignment => format!("{}", self.content.blue()),
+            TaskToken::Equal => format!("{}", self.content.yellow()),
+            TaskToken::NotEqual => format!("{}", self.content.yellow()),
+            TaskToken::LessEqual => format!("{}", self.content.yellow()),
+            TaskToken::GreaterEqual => format!("{}", self.content.yellow()),
             TaskToken::Bool => format!("{}", self.content.yellow()),
+            TaskToken::Null => format!("{}", self.content.yellow()),
             TaskToken::String(_) => format!("{}", self.content.yellow()),
             TaskToken::Integer => format!("{}", self.content.yellow()),
             TaskToken::Float => format!("{}", self.content.yellow()),
@@ -236,12 +291,18 @@ impl<'a> Token<'a> {
                 _ => panic!("Invalid Boolean"),
             }
             .into(),
+            TaskToken::Null => Attribute::Null,
             TaskToken::String(ref s) => s.to_string().into(),
-            TaskToken::Integer => self.content.parse::<i64>().unwrap().into(),
-            TaskToken::Float => self.content.parse::<f64>().unwrap().into(),
-            TaskToken::Date => Attribute::Date(Date::from_str(self.content).unwrap()),
+            TaskToken::Integer => self.content.replace('_', "").parse::<i64>().unwrap().into(),
+            TaskToken::Float => self.content.replace('_', "").parse::<f64>().unwrap().into(),
+            // slash dates are only tokenized when they are enabled
+            TaskToken::Date => {
+                Attribute::Date(Date::from_str_with(self.content, &SLASH_DATES).unwrap())
+            }
             TaskToken::Time => Attribute::Time(Time::from_str(self.content).unwrap()),
-            TaskToken::DateTime => Attribute::DateTime(DateTime::from_str(self.content).unwrap()),
+            TaskToken::DateTime => {
+                Attribute::DateTime(DateTime::from_str_with(self.content, &SLASH_DATES).unwrap())
+            }
             _ => return None,
         };
         Some(val)
@@ -272,7 +333,13 @@ fn comment<'a>(i: &'a str) -> TokenRes<'a> {
 }
 
 fn symbols<'a>(i: &'a str) -> TokenRes<'a> {
+    // multi-character operators need to be tried before their single
+    // character prefixes, so `<=` isn't read as `<` followed by `=`
     alt((
+        map(tag("=="), |s| Token::new(TaskToken::Equal, s)),
+        map(tag("!="), |s| Token::new(TaskToken::NotEqual, s)),
+        map(tag("<="), |s| Token::new(TaskToken::LessEqual, s)),
+        map(tag(">="), |s| Token::new(TaskToken::GreaterEqual, s)),
         map(tag("<"), |s| Token::new(TaskToken::AngleStart, s)),
         map(tag(">"), |s| Token::new(TaskToken::AngleEnd, s)),
         map(tag("("), |s| Token::new(TaskToken::ParenStart, s)),
@@ -284,6 +351,10 @@ fn symbols<'a>(i: &'a str) -> TokenRes<'a> {
         map(tag("."), |s| Token::new(TaskToken::Dot, s)),
         map(tag(","), |s| Token::new(TaskToken::Comma, s)),
         map(tag("->"), |s| Token::new(TaskToken::PathSep, s)),
+        // `-5` is a negative number
+        map(terminated(tag("-"), not(digit1)), |s| {
+            Token::new(TaskToken::Minus, s)
+        }),
         map(tag("="), |s| Token::new(TaskToken::Assignment, s)),
         map(tag("&"), |s| Token::new(TaskToken::And, s)),
         map(tag("|"), |s| Token::new(TaskToken::Or, s)),
@@ -315,8 +386,12 @@ fn variable<'a>(i: &'a str) -> TokenRes<'a> {
             if rest.trim_start().starts_with('(') {
                 TaskToken::Function
             } else {
-                if let Some(re) = rest.trim_start().strip_prefix('.') {
-                    let (r, _) = get_var(re)?;
+                // the part after the dot can be a number (`a.01`)
+                let after_dot = rest
+                    .trim_start()
+                    .strip_prefix('.')
+                    .and_then(|re| get_var(re).ok());
+                if let Some((r, _)) = after_dot {
                     if r.trim_start().starts_with('(') {
                         rest = r;
                         var = &i[..(i.len() - r.len())];
@@ -347,6 +422,17 @@ fn boolean<'a>(i: &'a str) -> TokenRes<'a> {
     })(i)
 }
 
+fn null<'a>(i: &'a str) -> TokenRes<'a> {
+    // variables like `nullable` shouldn't be split
+    map(
+        terminated(
+            tag("null"),
+            not(satisfy(|c| c.is_alphanumeric() || c == '_' || c == '-')),
+        ),
+        |s| Token::new(TaskToken::Null, s),
+    )(i)
+}
+
 fn integer<'a>(i: &'a str) -> TokenRes<'a> {
     map(
         alt((
@@ -360,20 +446,32 @@ fn integer<'a>(i: &'a str) -> TokenRes<'a> {
     )(i)
 }
 
+/// Floats are numbers with a decimal point, an exponent, or both
+/// (`1.5`, `.5`, `5.`, `1e10`, `1.5E-3`), or the special values
+/// `inf` and `nan` with a sign (`+inf`, `-nan`), the bare ones are
+/// variables. Every float printed by [`Attribute::to_string`] is
+/// accepted back by this grammar.
 fn float<'a>(i: &'a str) -> TokenRes<'a> {
     map(
         alt((
             recognize(tuple((
                 integer,
-                preceded(char('.'), cut(digit1)),
+                char('.'),
+                opt(digit1),
                 opt(tuple((one_of("eE"), integer))),
             ))),
-            // even if there is no decimal 1e10 is float.
             recognize(tuple((
-                integer,
-                opt(preceded(char('.'), cut(digit1))),
-                tuple((one_of("eE"), integer)),
+                opt(one_of("+-")),
+                char('.'),
+                digit1,
+                opt(tuple((one_of("eE"), integer))),
             ))),
+            // even if there is no decimal 1e10 is float.
+            recognize(tuple((integer, one_of("eE"), integer))),
+            recognize(terminated(
+                pair(one_of("+-"), alt((tag("inf"), tag("NaN"), tag("nan")))),
+                not(satisfy(|c| c.is_alphanumeric() || c == '_' || c == '-')),
+            )),
         )),
         |s| Token::new(TaskToken::Float, s),
     )(i)
@@ -399,18 +497,120 @@ fn datetime<'a>(i: &'a str) -> TokenRes<'a> {
     })(i)
 }
 
-fn task_script<'a>(i: &'a str) -> VecTokenRes<'a> {
+/// Date in `mm/dd/YYYY` format
+fn slash_date<'a>(i: &'a str) -> TokenRes<'a> {
+    map(
+        recognize(tuple((digit1, char('/'), digit1, char('/'), digit1))),
+        |s| Token::new(TaskToken::Date, s),
+    )(i)
+}
+
+fn slash_datetime<'a>(i: &'a str) -> TokenRes<'a> {
+    map(recognize(tuple((slash_date, one_of(" T"), time))), |s| {
+        Token::new(TaskToken::DateTime, s)
+    })(i)
+}
+
+/// Options for the tokenizer to accept alternative input formats,
+/// the values are stored the same way irrespective of the input
+/// format (e.g. [`Date`] is same for `2020-12-31` and `12/31/2020`)
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct TokenizerOptions {
+    /// Accept the dates in `mm/dd/YYYY` format
+    pub slash_dates: bool,
+}
+
+const SLASH_DATES: TokenizerOptions = TokenizerOptions { slash_dates: true };
+
+fn task_script<'a>(i: &'a str, opts: &TokenizerOptions) -> VecTokenRes<'a> {
     context(
         "task script",
         many0(alt((
-            whitespace, newline, comment, symbols, string, datetime, date, time, boolean, float,
-            integer, variable,
+            whitespace,
+            newline,
+            comment,
+            // before symbols so `.5` isn't read as a dot
+            float,
+            symbols,
+            string,
+            map_opt(
+                cond(opts.slash_dates, alt((slash_datetime, slash_date))),
+                |t| t,
+            ),
+            datetime,
+            date,
+            time,
+            boolean,
+            null,
+            integer,
+            variable,
         ))),
     )(i)
 }
 
 pub fn get_tokens(txt: &str) -> Result<Vec<Token>, TokenError> {
-    let (res, tokens) = match task_script(txt) {
+    get_tokens_with(txt, &TokenizerOptions::default())
+}
+
+/// Get the tokens that own their contents, see [`OwnedToken`]
+pub fn get_tokens_owned(txt: &str) -> Result<Vec<OwnedToken>, TokenError> {
+    let mut start = 0;
+    Ok(get_tokens(txt)?
+        .into_iter()
+        .map(|t| {
+            let end = start + t.content.len();
+            let tk = OwnedToken {
+                ty: t.ty,
+                content: t.content.to_string(),
+                span: start..end,
+            };
+            start = end;
+            tk
+        })
+        .collect())
+}
+
+/// Get the tokens with the given [`TokenizerOptions`]
+/// The floats starting with a dot (`.5`) right after a name or a
+/// closing bracket are the dot of a path with a numeric part
+/// (`a.01`, `<upstream.0123>`), so they are split into a dot and the
+/// number after it
+fn split_dot_floats(tokens: Vec<Token>) -> Vec<Token> {
+    let mut res: Vec<Token> = Vec::with_capacity(tokens.len());
+    for tk in tokens {
+        let after_name = matches!(
+            res.last().map(|t| &t.ty),
+            Some(
+                TaskToken::Variable
+                    | TaskToken::String(_)
+                    | TaskToken::Integer
+                    | TaskToken::Float
+                    | TaskToken::ParenEnd
+                    | TaskToken::BracketEnd
+                    | TaskToken::BraceEnd
+            )
+        );
+        if after_name && tk.ty == TaskToken::Float && tk.content.starts_with('.') {
+            let (dot, num) = tk.content.split_at(1);
+            let ty = if num.contains(['e', 'E']) {
+                TaskToken::Float
+            } else {
+                TaskToken::Integer
+            };
+            res.push(Token::new(TaskToken::Dot, dot));
+            res.push(Token::new(ty, num));
+        } else {
+            res.push(tk);
+        }
+    }
+    res
+}
+
+pub fn get_tokens_with<'a>(
+    txt: &'a str,
+    opts: &TokenizerOptions,
+) -> Result<Vec<Token<'a>>, TokenError> {
+    let (res, tokens) = match task_script(txt, opts) {
         Ok(v) => v,
         Err(e) => {
             let er = match e {
@@ -430,20 +630,27 @@ pub fn get_tokens(txt: &str) -> Result<Vec<Token>, TokenError> {
             let res = &txt[off..];
             let line = pre.lines().count() - 1;
             let linestr = txt.lines().nth(line).unwrap_or_default().to_string();
-            let col = linestr.len() - res.lines().next().unwrap_or_default().len() + 1;
+            let col = char_col(&linestr, res);
             return Err(TokenError { line, col, linestr });
         }
     };
     if res.is_empty() {
-        Ok(tokens)
+        Ok(split_dot_floats(tokens))
     } else {
         let line = txt.lines().count() - res.lines().count();
         let linestr = txt.lines().nth(line).unwrap_or_default().to_string();
-        let col = linestr.len() - res.lines().next().unwrap_or_default().len() + 1;
+        let col = char_col(&linestr, res);
         Err(TokenError { line, col, linestr })
     }
 }
 
+/// Column (1-based) in characters where the remaining text `res`
+/// starts in the line, counting bytes would misalign the error
+/// pointers for lines with multi-byte characters
+fn char_col(linestr: &str, res: &str) -> usize {
+    linestr.chars().count() - res.lines().next().unwrap_or_default().chars().count() + 1
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -456,4 +663,188 @@ mod tests {
         assert_eq!(rest, reminder);
         assert_eq!(n.ty, value);
     }
+
+    #[rstest]
+    #[case("12/31/2020", TaskToken::Date)]
+    #[case("1/2/2020", TaskToken::Date)]
+    #[case("12/31/2020 10:00", TaskToken::DateTime)]
+    #[case("\"data/12/31/2020.csv\"", TaskToken::String("data/12/31/2020.csv".into()))]
+    fn slash_date_test(#[case] txt: &str, #[case] value: TaskToken) {
+        let opts = TokenizerOptions { slash_dates: true };
+        let tokens = get_tokens_with(txt, &opts).unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].ty, value);
+    }
+
+    #[rstest]
+    fn slash_date_value_test() {
+        let opts = TokenizerOptions { slash_dates: true };
+        let tokens = get_tokens_with("12/31/2020", &opts).unwrap();
+        assert_eq!(
+            tokens[0].attribute(),
+            Some(Attribute::Date(Date::new(2020, 12, 31)))
+        );
+        // the slash is not a valid token without the option, or when
+        // it's not a full date
+        assert!(get_tokens("12/31/2020").is_err());
+        assert!(get_tokens_with("12/31", &opts).is_err());
+        // path separator is unaffected
+        let tokens = get_tokens_with("a -> b", &opts).unwrap();
+        assert_eq!(tokens[2].ty, TaskToken::PathSep);
+    }
+
+    #[rstest]
+    #[case("==", vec![TaskToken::Equal])]
+    #[case("!=", vec![TaskToken::NotEqual])]
+    #[case("<=", vec![TaskToken::LessEqual])]
+    #[case(">=", vec![TaskToken::GreaterEqual])]
+    #[case("x<=1", vec![TaskToken::Variable, TaskToken::LessEqual, TaskToken::Integer])]
+    #[case("x >= y", vec![TaskToken::Variable, TaskToken::WhiteSpace, TaskToken::GreaterEqual, TaskToken::WhiteSpace, TaskToken::Variable])]
+    #[case("< =", vec![TaskToken::AngleStart, TaskToken::WhiteSpace, TaskToken::Assignment])]
+    #[case("!x", vec![TaskToken::Not, TaskToken::Variable])]
+    #[case("x=!y", vec![TaskToken::Variable, TaskToken::Assignment, TaskToken::Not, TaskToken::Variable])]
+    #[case("===", vec![TaskToken::Equal, TaskToken::Assignment])]
+    #[case("a - b", vec![TaskToken::Variable, TaskToken::WhiteSpace, TaskToken::Minus, TaskToken::WhiteSpace, TaskToken::Variable])]
+    #[case("- 5", vec![TaskToken::Minus, TaskToken::WhiteSpace, TaskToken::Integer])]
+    #[case("-5", vec![TaskToken::Integer])]
+    fn operator_tokens_test(#[case] txt: &str, #[case] value: Vec<TaskToken>) {
+        let tokens: Vec<TaskToken> = get_tokens(txt).unwrap().into_iter().map(|t| t.ty).collect();
+        assert_eq!(tokens, value);
+    }
+
+    #[rstest]
+    fn owned_tokens_test() {
+        let txt = String::from("node.x = \"a\"");
+        let tokens = get_tokens_owned(&txt).unwrap();
+        drop(txt);
+        let contents: Vec<&str> = tokens.iter().map(|t| t.content.as_str()).collect();
+        assert_eq!(contents, vec!["node", ".", "x", " ", "=", " ", "\"a\""]);
+        assert_eq!(tokens[2].span, 5..6);
+        assert_eq!(tokens[6].span, 9..12);
+        assert_eq!(tokens[6].as_token().ty, TaskToken::String("a".into()));
+    }
+
+    #[rstest]
+    #[case(r#""a\tb""#, "a\tb")]
+    #[case(r#""a\nb""#, "a\nb")]
+    #[case(r#""a\rb""#, "a\rb")]
+    #[case(r#""a\"b""#, "a\"b")]
+    #[case(r#""a\'b""#, "a'b")]
+    #[case(r#""a\\b""#, "a\\b")]
+    #[case(r#""a\/b""#, "a/b")]
+    #[case(r#""a\u{e9}b""#, "a\u{e9}b")]
+    #[case(r#"'a\tb'"#, "a\tb")]
+    #[case(r#"'a\'b'"#, "a'b")]
+    #[case(r#"'a"b'"#, "a\"b")]
+    fn string_escape_test(#[case] txt: &str, #[case] value: &str) {
+        let tokens = get_tokens(txt).unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].ty, TaskToken::String(value.into()));
+        assert_eq!(tokens[0].content, txt);
+    }
+
+    #[rstest]
+    #[case(r#"x = "a\qb""#, 7)]
+    #[case(r#"x = 'ab\z'"#, 8)]
+    #[case(r#"x = "abc"#, 9)]
+    fn string_escape_error_test(#[case] txt: &str, #[case] col: usize) {
+        let err = get_tokens(txt).unwrap_err();
+        assert_eq!(err.line, 0);
+        assert_eq!(err.col, col);
+    }
+
+    #[rstest]
+    fn multibyte_col_test() {
+        let err = get_tokens(r#"x = "é\qb""#).unwrap_err();
+        assert_eq!(err.col, 7);
+        let msg = err.user_msg(None);
+        // the part before the error is not highlighted
+        assert!(msg.lines().nth(1).unwrap().starts_with(r#"  x = "é"#));
+
+        let tokens = get_tokens("\"é\" ]").unwrap();
+        let err = crate::parser::attrs::parse(tokens).unwrap_err();
+        assert_eq!((err.line, err.col, err.len), (0, 4, 1));
+    }
+
+    #[rstest]
+    #[case("1.5", "", 1.5)]
+    #[case(".5", "", 0.5)]
+    #[case("-.5", "", -0.5)]
+    #[case("5.", "", 5.0)]
+    #[case("5.)", ")", 5.0)]
+    #[case("1E-3", "", 1e-3)]
+    #[case("1.5e+10", "", 1.5e10)]
+    #[case("1_000.5", "", 1000.5)]
+    #[case("-inf", "", f64::NEG_INFINITY)]
+    #[case("+inf)", ")", f64::INFINITY)]
+    fn float_test(#[case] txt: &str, #[case] reminder: &str, #[case] value: f64) {
+        let (rest, n) = float(txt).unwrap();
+        assert_eq!(rest, reminder);
+        assert_eq!(n.ty, TaskToken::Float);
+        assert_eq!(n.attribute(), Some(Attribute::Float(value)));
+    }
+
+    #[rstest]
+    #[case("x=.5", vec![TaskToken::Variable, TaskToken::Assignment, TaskToken::Float])]
+    #[case("a.b", vec![TaskToken::Variable, TaskToken::Dot, TaskToken::Variable])]
+    #[case("infinite", vec![TaskToken::Variable])]
+    #[case("a.01", vec![TaskToken::Variable, TaskToken::Dot, TaskToken::Integer])]
+    #[case("a.0.5", vec![TaskToken::Variable, TaskToken::Dot, TaskToken::Integer, TaskToken::Dot, TaskToken::Integer])]
+    #[case("<upstream.0123>", vec![TaskToken::AngleStart, TaskToken::Variable, TaskToken::Dot, TaskToken::Integer, TaskToken::AngleEnd])]
+    #[case("a.1e5", vec![TaskToken::Variable, TaskToken::Dot, TaskToken::Float])]
+    #[case("a .5", vec![TaskToken::Variable, TaskToken::WhiteSpace, TaskToken::Float])]
+    #[case("(a>.5)", vec![TaskToken::ParenStart, TaskToken::Variable, TaskToken::AngleEnd, TaskToken::Float, TaskToken::ParenEnd])]
+    #[case("inf", vec![TaskToken::Variable])]
+    #[case("nan", vec![TaskToken::Variable])]
+    #[case("NaN", vec![TaskToken::Variable])]
+    #[case("x=inf", vec![TaskToken::Variable, TaskToken::Assignment, TaskToken::Variable])]
+    #[case("+nan", vec![TaskToken::Float])]
+    #[case("12", vec![TaskToken::Integer])]
+    fn float_boundary_test(#[case] txt: &str, #[case] types: Vec<TaskToken>) {
+        let tokens = get_tokens(txt).unwrap();
+        assert_eq!(tokens.into_iter().map(|t| t.ty).collect::<Vec<_>>(), types);
+    }
+
+    #[rstest]
+    fn dot_integer_test() {
+        let tokens = get_tokens("a.01").unwrap();
+        let contents: Vec<&str> = tokens.iter().map(|t| t.content).collect();
+        assert_eq!(contents, vec!["a", ".", "01"]);
+    }
+
+    #[rstest]
+    #[case(0.5)]
+    #[case(-2.0)]
+    #[case(1e20)]
+    #[case(1.5e-7)]
+    #[case(f64::MAX)]
+    #[case(f64::MIN_POSITIVE)]
+    #[case(f64::INFINITY)]
+    #[case(f64::NEG_INFINITY)]
+    fn float_roundtrip_test(#[case] value: f64) {
+        let txt = Attribute::Float(value).to_string();
+        let tokens = get_tokens(&txt).unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].attribute(), Some(Attribute::Float(value)));
+    }
+
+    #[rstest]
+    fn float_nan_roundtrip_test() {
+        let txt = Attribute::Float(f64::NAN).to_string();
+        let tokens = get_tokens(&txt).unwrap();
+        assert_eq!(tokens.len(), 1);
+        match tokens[0].attribute() {
+            Some(Attribute::Float(v)) => assert!(v.is_nan()),
+            a => panic!("expected nan, got {a:?}"),
+        }
+    }
+
+    #[rstest]
+    #[case("null", vec![TaskToken::Null])]
+    #[case("nullable", vec![TaskToken::Variable])]
+    #[case("x = null", vec![TaskToken::Variable, TaskToken::WhiteSpace, TaskToken::Assignment, TaskToken::WhiteSpace, TaskToken::Null])]
+    fn null_test(#[case] txt: &str, #[case] types: Vec<TaskToken>) {
+        let tokens = get_tokens(txt).unwrap();
+        assert_eq!(tokens.into_iter().map(|t| t.ty).collect::<Vec<_>>(), types);
+    }
 }
@@ -17,9 +17,29 @@ pub struct NadiExternalPlugin {
     pub plugin_name: extern "C" fn() -> RString,
 }
 
+/// Plugin that provides functions to nadi
+///
+/// When loaded through [`NadiFunctions::register_plugin`] (as done
+/// by [`NadiFunctions::load_plugins`]), [`NadiPlugin::register`] is
+/// called first, and [`NadiPlugin::on_load`] is called right after
+/// it, so all the functions of the plugin are available inside
+/// `on_load`. The internal plugins are registered the same way.
+///
+/// [`NadiPlugin::on_unload`] is only called through
+/// [`NadiFunctions::unload_plugin`], before the functions of the
+/// plugin are removed. Dropping the [`NadiFunctions`] does not call
+/// it, as the functions do not keep the plugins around.
+///
+/// The external plugins do not have the hooks in their ABI, the
+/// `register_functions` of the library should do its own
+/// initialization after registering the functions.
 pub trait NadiPlugin {
     fn register(&self, func: &mut NadiFunctions);
     fn name(&self) -> RString;
+    /// One time initialization after the functions are registered
+    fn on_load(&self, _funcs: &mut NadiFunctions) {}
+    /// Cleanup before the plugin is unloaded
+    fn on_unload(&self) {}
 }
 
 impl NadiPlugin for NadiExternalPlugin_Ref {
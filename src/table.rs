@@ -1,5 +1,5 @@
 use crate::{
-    attrs::{Attribute, FromAttribute, HasAttributes},
+    attrs::{type_name, Attribute, FromAttribute, HasAttributes},
     network::Network,
 };
 use abi_stable::{
@@ -179,6 +179,42 @@ impl FromAttribute for Table {
 }
 
 impl Table {
+    /// Headers of the columns
+    pub fn column_names(&self) -> impl Iterator<Item = &str> {
+        self.columns.iter().map(|c| c.header.as_str())
+    }
+
+    /// Render the column with the given header for all the nodes in
+    /// the network and parse them into the given type
+    pub fn column_as<T: FromStr>(&self, net: &Network, name: &str) -> Result<Vec<T>, String>
+    where
+        T::Err: ToString,
+    {
+        let col = self
+            .columns
+            .iter()
+            .find(|c| c.header.as_str() == name)
+            .ok_or_else(|| format!("Column {name} not found"))?;
+        let templ = Template::parse_template(&col.template).map_err(|e| e.to_string())?;
+        net.nodes()
+            .enumerate()
+            .map(|(i, n)| {
+                let n = n.lock();
+                let val = n
+                    .render(&templ)
+                    .map_err(|e| format!("Row {i} ({}): {e}", n.name()))?;
+                val.trim().parse::<T>().map_err(|e| {
+                    format!(
+                        "Row {i} ({}): cannot parse {val:?} as {}: {}",
+                        n.name(),
+                        type_name::<T>(),
+                        e.to_string()
+                    )
+                })
+            })
+            .collect()
+    }
+
     pub fn render_contents(
         &self,
         net: &Network,
@@ -284,3 +320,38 @@ fn align_fmt_fn(col: &str, align: &ColumnAlign, width: &usize) -> String {
         ColumnAlign::Center => format!(" {:^1$} ", col, width),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn column_as_test() {
+        let net = Network::from_edges(&[("a", "b"), ("b", "c")]);
+        for (i, n) in net.nodes().enumerate() {
+            let mut n = n.lock();
+            n.set_attr("area", Attribute::Float(i as f64 * 1.5));
+            n.set_attr("label", Attribute::String(format!("x{i}").into()));
+        }
+        let table = Table {
+            columns: vec![
+                Column::new("Index", "{INDEX}", None),
+                Column::new("Area", "{area}", None),
+                Column::new("Label", "{_label}", None),
+            ]
+            .into(),
+        };
+        assert_eq!(
+            table.column_names().collect::<Vec<&str>>(),
+            vec!["Index", "Area", "Label"]
+        );
+        let ind: Vec<i64> = table.column_as(&net, "Index").unwrap();
+        assert_eq!(ind, vec![0, 1, 2]);
+        let area: Vec<f64> = table.column_as(&net, "Area").unwrap();
+        assert_eq!(area, vec![0.0, 1.5, 3.0]);
+        let err = table.column_as::<i64>(&net, "Label").unwrap_err();
+        assert!(err.starts_with("Row 0 (c): cannot parse \"x0\""));
+        assert!(table.column_as::<i64>(&net, "None").is_err());
+    }
+}
@@ -1,39 +1,275 @@
+use crate::attrs::{sorted_attrs, table_key, write_attrs};
+use crate::colors::Colorize;
 use crate::functions::{
-    FuncArg, FuncArgType, FunctionCtx, FunctionRet, NadiFunctions, Propagation,
+    FuncArg, FuncArgType, FunctionCtx, FunctionKind, FunctionRet, NadiFunctions, NodeFunctionBox,
+    Propagation, RegistrationOptions,
 };
+use crate::network::NetworkEvent;
+use crate::node::{without_properties, NODE_PROPERTIES};
 use crate::prelude::*;
-use abi_stable::std_types::{RString, Tuple2};
-use colored::Colorize;
+use abi_stable::std_types::{RSome, RString};
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Env variable with the value returned by the last function task,
+/// see [`TaskContext::execute`]
+pub const LAST_RESULT: &str = "_last";
 
 pub struct TaskContext {
     pub network: Network,
     pub functions: NadiFunctions,
     pub env: AttrMap,
+    /// Partially applied functions defined in env, see
+    /// [`TaskContext::resolve_partial`]
+    pub partials: HashMap<String, FunctionCall>,
+    timings: HashMap<String, Duration>,
 }
 
 impl TaskContext {
     pub fn new(net: Option<Network>) -> Self {
+        Self::with_functions(net, NadiFunctions::new())
+    }
+
+    fn with_functions(net: Option<Network>, functions: NadiFunctions) -> Self {
         Self {
             network: net.unwrap_or(Network::default()),
-            functions: NadiFunctions::new(),
+            functions,
             env: AttrMap::new(),
+            partials: HashMap::new(),
+            timings: HashMap::new(),
+        }
+    }
+
+    /// Context with the functions registered without the ones
+    /// excluded in the options, see [`NadiFunctions::new_with`]
+    pub fn new_with(net: Option<Network>, opts: &RegistrationOptions) -> Self {
+        Self::with_functions(net, NadiFunctions::new_with(opts))
+    }
+
+    /// Merge the arguments of the call with the partially applied
+    /// function of the same name defined in env (e.g. `env greet =
+    /// concat("Hello, ")`), the stored args come before the new ones
+    /// and the new kwargs override the stored ones.
+    ///
+    /// The arguments are stored as written, so the variables in them
+    /// are only resolved when the function is finally called.
+    /// Functions without any partials defined are returned as it is,
+    /// and the registered functions are never replaced by the
+    /// partials, even when they are loaded after the partial is
+    /// defined.
+    pub fn resolve_partial(&self, fc: &FunctionCall) -> FunctionCall {
+        if self.is_function(&fc.name) {
+            return fc.clone();
+        }
+        match self.partials.get(&fc.name) {
+            Some(p) => {
+                let mut func = p.clone();
+                func.args.extend(fc.args.iter().cloned());
+                func.kwargs
+                    .extend(fc.kwargs.iter().map(|(k, v)| (k.clone(), v.clone())));
+                func
+            }
+            None => fc.clone(),
+        }
+    }
+
+    /// Check if there is a node or network function with the name
+    fn is_function(&self, name: &str) -> bool {
+        self.functions.node(name).is_some() || self.functions.network(name).is_some()
+    }
+
+    /// Total time taken by the calls to each function (`node name` or
+    /// `network name`), only recorded when the env variable `profile`
+    /// is set
+    pub fn timings(&self) -> &HashMap<String, Duration> {
+        &self.timings
+    }
+
+    /// Summary of the function timings, slowest first
+    ///
+    /// The program running the script should print it at the end,
+    /// only the `exit` task prints it as it ends the process.
+    pub fn timings_summary(&self) -> String {
+        let mut timings: Vec<(&String, &Duration)> = self.timings.iter().collect();
+        timings.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        timings
+            .into_iter()
+            .map(|(k, v)| format!("{k}: {v:?}"))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Whether to record the function timings, set with the env
+    /// variable `profile`
+    fn profile(&self) -> bool {
+        self.env
+            .get("profile")
+            .and_then(bool::from_attr_relaxed)
+            .unwrap_or(false)
+    }
+
+    fn record_time(&mut self, name: String, elapsed: Duration) {
+        if self.profile() {
+            *self.timings.entry(name).or_default() += elapsed;
+        }
+    }
+
+    /// Save the network (nodes, edges and attributes) and the env
+    /// variables in a single file, that can be loaded back with
+    /// [`TaskContext::load_state`].
+    ///
+    /// The file uses the same syntax as the attribute files, with
+    /// `nodes` and `edges` at the top and the attributes in the
+    /// `[network_attrs]`, `[env_vars]` and `[node_attrs.<name>]`
+    /// sections. Timeseries are not saved, keep their file paths as
+    /// attributes to load them again.
+    pub fn save_state<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        let nodes: Vec<String> = self.network.node_names().map(String::from).collect();
+        let edges: Vec<Attribute> = self
+            .network
+            .edges_str()
+            .map(|(s, e)| vec![s.to_string(), e.to_string()].into())
+            .collect();
+        writeln!(w, "nodes = {}", Attribute::from(nodes).to_string())?;
+        writeln!(w, "edges = {}", Attribute::from(edges).to_string())?;
+        write_attrs_section(&mut w, "network_attrs", self.network.attr_map())?;
+        write_attrs_section(&mut w, "env_vars", &self.env)?;
+        for node in self.network.nodes() {
+            let node = node.lock();
+            let section = format!("node_attrs.{}", table_key(node.name()));
+            write_attrs_section(&mut w, &section, &without_properties(node.attr_map()))?;
+        }
+        Ok(())
+    }
+
+    /// Load the network and env variables saved with
+    /// [`TaskContext::save_state`], replacing the current ones
+    #[cfg(feature = "parser")]
+    pub fn load_state<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
+        let contents = std::fs::read_to_string(path)?;
+        let tokens = crate::parser::tokenizer::get_tokens(&contents)?;
+        let state = crate::parser::attrs::parse(tokens)?;
+        let get_table = |name: &str| -> anyhow::Result<AttrMap> {
+            match state.get(name) {
+                Some(a) => AttrMap::try_from_attr(a).map_err(anyhow::Error::msg),
+                None => Ok(AttrMap::new()),
+            }
+        };
+        let nodes: Vec<String> = match state.get("nodes") {
+            Some(a) => FromAttribute::try_from_attr(a).map_err(anyhow::Error::msg)?,
+            None => vec![],
+        };
+        let edges: Vec<(String, String)> = match state.get("edges") {
+            Some(a) => FromAttribute::try_from_attr(a).map_err(anyhow::Error::msg)?,
+            None => vec![],
+        };
+        let mut network = Network::from_edges(&edges);
+        for name in &nodes {
+            if network.node_by_name(name).is_none() {
+                network.insert_node_by_name(name);
+            }
+        }
+        for (name, attrs) in sorted_attrs(&get_table("node_attrs")?) {
+            let node = network.try_node_by_name(name).map_err(anyhow::Error::msg)?;
+            let attrs = AttrMap::try_from_attr(attrs).map_err(anyhow::Error::msg)?;
+            node.lock().extend_attrs(attrs);
+        }
+        network.attr_map_mut().extend(get_table("network_attrs")?);
+        self.network = network;
+        self.env = get_table("env_vars")?;
+        Ok(())
+    }
+
+    /// Listing of the functions of the `kind` with `pattern` in their
+    /// names, for the help on the functions that are not found
+    fn list_matching(&self, kind: FunctionKind, pattern: &str) -> Option<String> {
+        let list = self
+            .functions
+            .list_functions_filtered(Some(kind), Some(pattern));
+        (!list.is_empty()).then_some(list)
+    }
+
+    fn set_last(&mut self, val: Option<Attribute>) {
+        match val {
+            Some(v) => {
+                self.env.insert(LAST_RESULT.into(), v);
+            }
+            None => {
+                self.env.remove(LAST_RESULT);
+            }
         }
     }
 
-    pub fn execute(&mut self, task: Task) -> Result<Option<String>, String> {
+    /// Whether to warn when the node propagation selects no nodes,
+    /// set with the env variable `warn_empty`
+    fn warn_empty(&self) -> bool {
+        self.env
+            .get("warn_empty")
+            .and_then(bool::from_attr_relaxed)
+            .unwrap_or(false)
+    }
+
+    /// Execute the task and return the output to show, if any
+    ///
+    /// The function in a network task is resolved as a network
+    /// function first, and if there is none with the name, as a node
+    /// function that is called on all the nodes in the sequential
+    /// order. Node tasks only resolve node functions. The functions
+    /// defined with `nadi_func` are both node and network functions
+    /// so they resolve in either task.
+    ///
+    /// Every function task updates the env variable [`LAST_RESULT`]
+    /// (`_last`) with the returned value so it can be used in later
+    /// tasks; the node functions store a table of the node names and
+    /// their values. It is removed when the function doesn't return
+    /// any value or fails.
+    pub fn execute(&mut self, mut task: Task) -> Result<Option<String>, String> {
+        if let TaskInput::Function(fc) = &task.input {
+            task.input = TaskInput::Function(self.resolve_partial(fc));
+        }
         match &task.ty {
-            TaskType::Exit => std::process::exit(0),
+            TaskType::Exit => {
+                if self.profile() {
+                    eprintln!("{}", self.timings_summary());
+                }
+                std::process::exit(0)
+            }
             TaskType::Env => {
                 if let Some(var) = task.attribute {
                     match task.input {
                         TaskInput::Literal(val) => {
+                            self.partials.remove(&var);
                             self.env.insert(var.into(), val);
                             Ok(None)
                         }
+                        TaskInput::Function(fc) => {
+                            if self.is_function(&var) {
+                                return Err(format!(
+                                    "Partial function {var} would shadow the function of the same name"
+                                ));
+                            }
+                            self.env.remove(var.as_str());
+                            self.partials.insert(var, fc);
+                            Ok(None)
+                        }
+                        TaskInput::Variable(v) => match self.env.get(v.as_str()) {
+                            Some(val) => {
+                                let val = val.clone();
+                                self.partials.remove(&var);
+                                self.env.insert(var.into(), val);
+                                Ok(None)
+                            }
+                            None => Err(format!("Env variable {v} doesn't exist")),
+                        },
                         TaskInput::None => {
                             if let Some(v) = self.env.get(var.as_str()) {
                                 Ok(Some(v.to_colored_string()))
+                            } else if let Some(fc) = self.partials.get(&var) {
+                                Ok(Some(fc.to_colored_string()))
                             } else {
                                 Err(format!("Env variable {var} doesn't exist"))
                             }
@@ -41,15 +277,26 @@ impl TaskContext {
                         _ => Err(String::from("Couldn't set env variable")),
                     }
                 } else {
-                    let mut list = String::new();
-                    for Tuple2(k, v) in &self.env {
-                        list.push_str(&format!("{k}={}", v.to_colored_string()));
-                    }
-                    Ok(Some(list))
+                    let mut list: Vec<String> = sorted_attrs(&self.env)
+                        .into_iter()
+                        .map(|(k, v)| format!("{k}={}", v.to_pretty_colored_string(2)))
+                        .collect();
+                    let mut partials: Vec<(&String, &FunctionCall)> =
+                        self.partials.iter().collect();
+                    partials.sort_by_key(|(k, _)| *k);
+                    list.extend(
+                        partials
+                            .into_iter()
+                            .map(|(k, fc)| format!("{k}={}", fc.to_colored_string())),
+                    );
+                    Ok(Some(list.join("\n")))
                 }
             }
             TaskType::Node(p) => {
                 let nodes: Vec<Node> = self.network.nodes_propagation(p)?;
+                if nodes.is_empty() && self.warn_empty() {
+                    eprintln!("WARN: No nodes selected by propagation {}", p.to_string());
+                }
                 match task.input {
                     TaskInput::None => {
                         if let Some(attr) = task.attribute {
@@ -76,9 +323,12 @@ impl TaskContext {
                     }
                     TaskInput::Literal(v) => {
                         if let Some(attr) = task.attribute {
-                            nodes.iter().for_each(|n| {
-                                n.lock().set_attr(&attr, v.clone());
-                            });
+                            nodes.iter().try_for_each(|n| {
+                                let mut n = n.lock();
+                                n.try_set_attr(&attr, v.clone())?;
+                                notify_attr(&self.network, n.name(), &attr);
+                                Ok::<(), String>(())
+                            })?;
                             Ok(None)
                         } else {
                             Err("Invalid operation, no attribute to assign".to_string())
@@ -91,7 +341,8 @@ impl TaskContext {
                                 let a = n.attr(&v).cloned();
                                 match a {
                                     Some(v) => {
-                                        n.set_attr(&attr, v);
+                                        n.try_set_attr(&attr, v)?;
+                                        notify_attr(&self.network, n.name(), &attr);
                                         Ok(())
                                     }
                                     None => {
@@ -106,42 +357,26 @@ impl TaskContext {
                     }
                     TaskInput::Function(fc) => match self.functions.node(&fc.name) {
                         Some(f) => {
-                            let attrs = nodes
-                                .iter()
-                                .map(|n| {
-                                    let mut node = n.lock();
-                                    let ctx = fc
-                                        .node_ctx(&node)
-                                        .map_err(|e| format!("{}: {e}", node.name()))?;
-                                    match f.call(&mut node, &ctx) {
-                                        FunctionRet::None => Ok(None),
-                                        FunctionRet::Some(a) => {
-                                            if let Some(attr) = &task.attribute {
-                                                node.set_attr(&attr, a);
-                                                Ok(None)
-                                            } else {
-                                                Ok(Some(format!(
-                                                    "  {} = {}",
-                                                    node.name(),
-                                                    a.to_colored_string()
-                                                )))
-                                            }
-                                        }
-                                        FunctionRet::Error(e) => {
-                                            Err(format!("{}: {e}", node.name()))
-                                        }
-                                    }
-                                })
-                                .collect::<Result<Vec<Option<String>>, String>>()?;
-                            let attrs =
-                                attrs.into_iter().filter_map(|v| v).collect::<Vec<String>>();
-                            if attrs.is_empty() {
-                                Ok(None)
-                            } else {
-                                Ok(Some(format!("{{\n{}\n}}", attrs.join(",\n"))))
-                            }
+                            let mut results = AttrMap::new();
+                            let (res, elapsed) = apply_node_func(
+                                f,
+                                &fc,
+                                &nodes,
+                                &self.network,
+                                &self.env,
+                                task.attribute.as_deref(),
+                                &mut results,
+                            );
+                            self.record_time(format!("node {}", fc.name), elapsed);
+                            self.set_last(
+                                (res.is_ok() && !results.is_empty())
+                                    .then_some(Attribute::Table(results)),
+                            );
+                            res
                         }
-                        None => Err(format!("Node Function {} not found", fc.name)),
+                        None => Err(self
+                            .functions
+                            .unavailable_error(FunctionKind::Node, &fc.name)),
                     },
                 }
             }
@@ -177,13 +412,24 @@ impl TaskContext {
                 }
                 TaskInput::Function(fc) => match self.functions.network(&fc.name) {
                     Some(f) => {
-                        let ctx = fc.network_ctx(&self.network)?;
-                        match f.call(&mut self.network, &ctx) {
+                        self.set_last(None);
+                        let mut ctx = fc.network_ctx(&self.network, &self.env)?;
+                        ctx.coerce(&f.args())?;
+                        let start = Instant::now();
+                        let ret = f.call(&mut self.network, &ctx);
+                        self.record_time(format!("network {}", fc.name), start.elapsed());
+                        if let FunctionRet::Some(a) = &ret {
+                            self.set_last(Some(a.clone()));
+                        }
+                        match ret {
                             FunctionRet::None => Ok(None),
                             FunctionRet::Some(a) => {
                                 if let Some(attr) = task.attribute {
                                     self.network.set_attr(&attr, a);
                                     Ok(None)
+                                } else if let Some(tbl) = multi_attrs(&fc.name, &a) {
+                                    self.network.attr_map_mut().extend(tbl.clone());
+                                    Ok(None)
                                 } else {
                                     Ok(Some(a.to_colored_string()))
                                 }
@@ -191,7 +437,30 @@ impl TaskContext {
                             FunctionRet::Error(e) => Err(e.to_string()),
                         }
                     }
-                    None => Err(format!("Network Function {} not found", fc.name)),
+                    // node function in network context runs on all nodes
+                    None => match self.functions.node(&fc.name) {
+                        Some(f) => {
+                            let nodes: Vec<Node> = self.network.nodes().cloned().collect();
+                            let mut results = AttrMap::new();
+                            let (res, elapsed) = apply_node_func(
+                                f,
+                                &fc,
+                                &nodes,
+                                &self.env,
+                                task.attribute.as_deref(),
+                                &mut results,
+                            );
+                            self.record_time(format!("node {}", fc.name), elapsed);
+                            self.set_last(
+                                (res.is_ok() && !results.is_empty())
+                                    .then_some(Attribute::Table(results)),
+                            );
+                            res
+                        }
+                        None => Err(self
+                            .functions
+                            .unavailable_error(FunctionKind::Network, &fc.name)),
+                    },
                 },
             },
             TaskType::Help(None, Some(var)) => {
@@ -208,10 +477,16 @@ impl TaskContext {
                         &f.help(),
                     ));
                 }
+                if let Some(h) = self.functions.plugin_help(var) {
+                    helpstr.push_str(&format!("Functions in plugin {}:\n{h}", var.red()));
+                }
+                if helpstr.is_empty() {
+                    helpstr = self.functions.list_functions_filtered(None, Some(var));
+                }
                 if !helpstr.is_empty() {
                     Ok(Some(helpstr))
                 } else {
-                    Err(format!("Function {} not found", var))
+                    Err(format!("Function or plugin {} not found", var))
                 }
             }
             TaskType::Help(Some(TaskKeyword::Node), Some(var)) => {
@@ -224,7 +499,9 @@ impl TaskContext {
                         &f.help(),
                     )))
                 } else {
-                    Err(format!("Node Function {} not found", var))
+                    self.list_matching(FunctionKind::Node, var)
+                        .map(Some)
+                        .ok_or_else(|| self.functions.unavailable_error(FunctionKind::Node, var))
                 }
             }
             TaskType::Help(Some(TaskKeyword::Network), Some(var)) => {
@@ -237,12 +514,25 @@ impl TaskContext {
                         &f.help(),
                     )))
                 } else {
-                    Err(format!("Network Function {} not found", var))
+                    self.list_matching(FunctionKind::Network, var)
+                        .map(Some)
+                        .ok_or_else(|| self.functions.unavailable_error(FunctionKind::Network, var))
                 }
             }
             TaskType::Help(Some(TaskKeyword::Env), None) => {
                 Ok(Some(format!("Set Environmental Variable")))
             }
+            TaskType::Help(None, None) => {
+                Ok(Some(self.functions.list_functions_filtered(None, None)))
+            }
+            TaskType::Help(Some(TaskKeyword::Node), None) => Ok(Some(
+                self.functions
+                    .list_functions_filtered(Some(FunctionKind::Node), None),
+            )),
+            TaskType::Help(Some(TaskKeyword::Network), None) => Ok(Some(
+                self.functions
+                    .list_functions_filtered(Some(FunctionKind::Network), None),
+            )),
             _ => todo!(),
         }
     }
@@ -389,78 +679,150 @@ impl FunctionCall {
         )
     }
 
-    pub fn node_ctx(&self, node: &NodeInner) -> Result<FunctionCtx, String> {
+    /// Arguments for the function call on the node, the variables
+    /// are the node attributes, or the env variables if the node
+    /// doesn't have the attribute
+    pub fn node_ctx(&self, node: &NodeInner, env: &AttrMap) -> Result<FunctionCtx, String> {
+        self.ctx(|v| node.attr(v), env)
+    }
+
+    /// Arguments for the function call on the network, the variables
+    /// are the network attributes, or the env variables if the
+    /// network doesn't have the attribute
+    pub fn network_ctx(&self, net: &Network, env: &AttrMap) -> Result<FunctionCtx, String> {
+        self.ctx(|v| net.attr(v), env)
+    }
+
+    fn ctx<'a>(
+        &self,
+        attr: impl Fn(&str) -> Option<&'a Attribute>,
+        env: &'a AttrMap,
+    ) -> Result<FunctionCtx, String> {
+        let value = |inp: &TaskInput| match inp {
+            TaskInput::Literal(v) => Ok(v.clone()),
+            TaskInput::Variable(v) => attr(v)
+                .or_else(|| env.get(v.as_str()))
+                .cloned()
+                .ok_or(format!("Attribute {v} not found")),
+            a => Err(invalid_arg(a)),
+        };
         let args = self
             .args
             .iter()
-            .map(|a| match a {
-                TaskInput::Literal(v) => Ok(v.clone()),
-                TaskInput::Variable(v) => node
-                    .attr(v)
-                    .cloned()
-                    .ok_or(format!("Attribute {v} not found")),
-                _ => Err(String::from("Invalid output")),
-            })
+            .map(&value)
             .collect::<Result<Vec<Attribute>, String>>()?
             .into();
         let kwargs = self
             .kwargs
             .iter()
-            .map(|(k, a)| {
-                let k = RString::from(k.as_str());
-                match a {
-                    TaskInput::Literal(v) => Ok((k, v.clone())),
-                    TaskInput::Variable(v) => Ok((
-                        k,
-                        node.attr(v)
-                            .cloned()
-                            .ok_or(format!("Attribute {v} not found"))?,
-                    )),
-                    _ => Err(String::from("Invalid output")),
-                }
-            })
+            .map(|(k, a)| Ok((RString::from(k.as_str()), value(a)?)))
             .collect::<Result<HashMap<RString, Attribute>, String>>()?
             .into();
         Ok(FunctionCtx { args, kwargs })
     }
+}
 
-    // TODO this and above is duplicate, maybe use some trait for things with Attribute
-    pub fn network_ctx(&self, net: &Network) -> Result<FunctionCtx, String> {
-        let args = self
-            .args
-            .iter()
-            .map(|a| match a {
-                TaskInput::Literal(v) => Ok(v.clone()),
-                TaskInput::Variable(v) => net
-                    .attr(v)
-                    .cloned()
-                    .ok_or(format!("Attribute {v} not found")),
-                _ => Err(String::from("Invalid output")),
-            })
-            .collect::<Result<Vec<Attribute>, String>>()?
-            .into();
-        let kwargs = self
-            .kwargs
-            .iter()
-            .map(|(k, a)| {
-                let k = RString::from(k.as_str());
-                match a {
-                    TaskInput::Literal(v) => Ok((k, v.clone())),
-                    TaskInput::Variable(v) => Ok((
-                        k,
-                        net.attr(v)
-                            .cloned()
-                            .ok_or(format!("Attribute {v} not found"))?,
-                    )),
-                    _ => Err(String::from("Invalid output")),
+/// Send the change in the node attribute to the network observer,
+/// the changes made inside the functions are not sent
+fn notify_attr(network: &Network, node: &str, attr: &str) {
+    network.notify(|| NetworkEvent::AttrChanged(RSome(node.into()), attr.into()));
+}
+
+/// Apply the node function on the nodes, returns the output to show
+/// (if any) and the time spent in the function calls
+fn apply_node_func(
+    f: &NodeFunctionBox,
+    fc: &FunctionCall,
+    nodes: &[Node],
+    network: &Network,
+    env: &AttrMap,
+    attribute: Option<&str>,
+    results: &mut AttrMap,
+) -> (Result<Option<String>, String>, Duration) {
+    let fargs = f.args();
+    let mut elapsed = Duration::ZERO;
+    let attrs = nodes
+        .iter()
+        .map(|n| {
+            let mut node = n.lock();
+            let mut ctx = fc
+                .node_ctx(&node, env)
+                .map_err(|e| format!("{}: {e}", node.name()))?;
+            ctx.coerce(&fargs)
+                .map_err(|e| format!("{}: {e}", node.name()))?;
+            let start = Instant::now();
+            let ret = f.call(&mut node, &ctx);
+            elapsed += start.elapsed();
+            match ret {
+                FunctionRet::None => Ok(None),
+                FunctionRet::Some(a) => {
+                    results.insert(node.name().into(), a.clone());
+                    if let Some(attr) = attribute {
+                        node.try_set_attr(attr, a)
+                            .map_err(|e| format!("{}: {e}", node.name()))?;
+                        notify_attr(network, node.name(), attr);
+                        Ok(None)
+                    } else if let Some(tbl) = multi_attrs(&fc.name, &a) {
+                        node.extend_attrs(tbl.clone());
+                        for k in tbl
+                            .keys()
+                            .filter(|k| !NODE_PROPERTIES.contains(&k.as_str()))
+                        {
+                            notify_attr(network, node.name(), k);
+                        }
+                        Ok(None)
+                    } else {
+                        Ok(Some(format!(
+                            "  {} = {}",
+                            node.name(),
+                            a.to_colored_string()
+                        )))
+                    }
                 }
-            })
-            .collect::<Result<HashMap<RString, Attribute>, String>>()?
-            .into();
-        Ok(FunctionCtx { args, kwargs })
+                FunctionRet::Error(e) => Err(format!("{}: {e}", node.name())),
+            }
+        })
+        .collect::<Result<Vec<Option<String>>, String>>();
+    let res = attrs.map(|attrs| {
+        let attrs = attrs.into_iter().flatten().collect::<Vec<String>>();
+        if attrs.is_empty() {
+            None
+        } else {
+            Some(format!("{{\n{}\n}}", attrs.join(",\n")))
+        }
+    });
+    (res, elapsed)
+}
+
+/// Error for the inputs that can't be function arguments, the nested
+/// function calls are not evaluated (so there is no recursion) and
+/// are rejected at the top level however deep they are nested
+fn invalid_arg(inp: &TaskInput) -> String {
+    match inp {
+        TaskInput::Function(fc) => format!(
+            "Function call {}(..) can't be used as an argument, assign it to an attribute first",
+            fc.name
+        ),
+        _ => String::from("Invalid output"),
     }
 }
 
+/// Functions with names ending in `_attrs` can return a Table to set
+/// each of its keys as separate attributes when the output attribute
+/// is not given
+fn multi_attrs<'a>(func: &str, ret: &'a Attribute) -> Option<&'a AttrMap> {
+    if func.ends_with("_attrs") {
+        ret.get_table()
+    } else {
+        None
+    }
+}
+
+fn write_attrs_section<W: Write>(w: &mut W, section: &str, attrs: &AttrMap) -> std::io::Result<()> {
+    writeln!(w, "\n[{section}]")?;
+    write_attrs(w, attrs)
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub enum TaskKeyword {
     Node,
@@ -523,6 +885,7 @@ fn format_help(prefix: &str, name: &str, signature: &str, args: &[FuncArg], help
     )
 }
 
+#[cfg(feature = "pretty")]
 fn format_md(txt: &str) -> String {
     let mut skin = termimad::MadSkin::default_dark();
     for h in &mut skin.headers {
@@ -530,3 +893,343 @@ fn format_md(txt: &str) -> String {
     }
     skin.text(txt, None).to_string()
 }
+
+#[cfg(not(feature = "pretty"))]
+fn format_md(txt: &str) -> String {
+    txt.to_string()
+}
+
+#[cfg(all(test, feature = "parser"))]
+mod tests {
+    use super::*;
+    use abi_stable::std_types::RVec;
+    use rstest::rstest;
+
+    /// Context with the internal functions registered, without the
+    /// external plugins
+    fn test_ctx(network: Network) -> TaskContext {
+        #[allow(unused_mut)]
+        let mut functions = NadiFunctions::default();
+        #[cfg(feature = "functions")]
+        crate::internal::register_internal(&mut functions);
+        TaskContext::with_functions(Some(network), functions)
+    }
+
+    #[rstest]
+    fn save_load_state_test() {
+        let mut ctx = test_ctx(Network::from_edges(&[("a", "b"), ("c", "b"), ("b", "d")]));
+        let mut tbl = AttrMap::new();
+        tbl.insert("x y".into(), Attribute::Float(1.5));
+        tbl.insert("empty".into(), Attribute::Array(RVec::new()));
+        ctx.network
+            .try_node_by_name("a")
+            .unwrap()
+            .lock()
+            .set_attr("tbl", Attribute::Table(tbl));
+        ctx.network
+            .try_node_by_name("d")
+            .unwrap()
+            .lock()
+            .set_attr("name with \"quotes\"", Attribute::String("a\nb".into()));
+        ctx.network
+            .set_attr("title", Attribute::String("test".into()));
+        ctx.env.insert("node".into(), Attribute::Integer(2));
+
+        let path = std::env::temp_dir().join("nadi-save-load-state-test.txt");
+        ctx.save_state(&path).unwrap();
+        let mut loaded = test_ctx(Network::default());
+        loaded.load_state(&path).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        for prop in crate::node::NODE_PROPERTIES {
+            assert!(!saved.contains(&format!("\n{prop} = ")), "{saved}");
+        }
+
+        assert_eq!(
+            loaded.network.edges_str().collect::<Vec<_>>(),
+            ctx.network.edges_str().collect::<Vec<_>>()
+        );
+        for (n1, n2) in ctx.network.nodes().zip(loaded.network.nodes()) {
+            assert!(n1.lock().attr_map() == n2.lock().attr_map());
+        }
+        assert!(loaded.network.attr_map() == ctx.network.attr_map());
+        assert!(loaded.env == ctx.env);
+    }
+
+    #[rstest]
+    fn nested_function_arg_test() {
+        let mut fc = FunctionCall {
+            name: "f0".into(),
+            args: vec![],
+            kwargs: HashMap::new(),
+        };
+        for i in 1..200 {
+            fc = FunctionCall {
+                name: format!("f{i}"),
+                args: vec![TaskInput::Function(fc)],
+                kwargs: HashMap::new(),
+            };
+        }
+        let top = FunctionCall {
+            name: "top".into(),
+            args: vec![],
+            kwargs: HashMap::from([("x".to_string(), TaskInput::Function(fc))]),
+        };
+        let net = Network::default();
+        let err = top.network_ctx(&net, &AttrMap::new()).unwrap_err();
+        assert!(err.starts_with("Function call f199(..) can't be used"));
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn network_node_fallback_test() {
+        let mut ctx = test_ctx(Network::from_edges(&[("a", "b"), ("c", "b")]));
+        let run = |ctx: &mut TaskContext, txt: &str| {
+            let tokens = crate::parser::tokenizer::get_tokens(txt).unwrap();
+            crate::parser::tasks::parse(tokens)
+                .unwrap()
+                .into_iter()
+                .try_for_each(|t| ctx.execute(t).map(|_| ()))
+        };
+        // network function is used when both exist
+        run(&mut ctx, "network set_attrs(y = 1)\n").unwrap();
+        assert_eq!(ctx.network.attr("y"), Some(&Attribute::Integer(1)));
+        assert!(ctx.network.nodes().all(|n| n.lock().attr("y").is_none()));
+        // node only function runs on all nodes
+        run(
+            &mut ctx,
+            "network set_attr_template(\"label\", \"{_NAME}!\")\n",
+        )
+        .unwrap();
+        for n in ctx.network.nodes() {
+            let n = n.lock();
+            assert_eq!(
+                n.attr("label"),
+                Some(&Attribute::String(format!("{}!", n.name()).into()))
+            );
+        }
+        assert_eq!(
+            run(&mut ctx, "network nonexistent()\n"),
+            Err("Network Function nonexistent not found".to_string())
+        );
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn disabled_functions_test() {
+        let opts = RegistrationOptions::default()
+            .no_shell()
+            .exclude("debug.echo");
+        let mut ctx = TaskContext::new_with(Some(Network::from_edges(&[("a", "b")])), &opts);
+        for name in [
+            "command",
+            "run",
+            "parallel",
+            "command.run",
+            "echo",
+            "debug.echo",
+        ] {
+            assert!(ctx.functions.is_disabled(name), "{name}");
+        }
+        assert!(!ctx.functions.is_disabled("clip"));
+        let mut run = |txt: &str| {
+            let tokens = crate::parser::tokenizer::get_tokens(txt).unwrap();
+            let mut tasks = crate::parser::tasks::parse(tokens).unwrap();
+            ctx.execute(tasks.remove(0))
+        };
+        assert_eq!(
+            run("network parallel()\n"),
+            Err("Network Function parallel is disabled".to_string())
+        );
+        assert_eq!(
+            run("node command.run(\"true\")\n"),
+            Err("Node Function command.run is disabled".to_string())
+        );
+        assert_eq!(
+            run("help network parallel\n"),
+            Err("Network Function parallel is disabled".to_string())
+        );
+        assert_eq!(
+            run("network nonexistent()\n"),
+            Err("Network Function nonexistent not found".to_string())
+        );
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn timings_test() {
+        let mut ctx = test_ctx(Network::from_edges(&[("a", "b")]));
+        let run = |ctx: &mut TaskContext, txt: &str| {
+            let tokens = crate::parser::tokenizer::get_tokens(txt).unwrap();
+            for task in crate::parser::tasks::parse(tokens).unwrap() {
+                ctx.execute(task).unwrap();
+            }
+        };
+        run(&mut ctx, "network set_attrs(x = 1)\n");
+        assert!(ctx.timings().is_empty());
+        run(
+            &mut ctx,
+            "env profile = true\nnetwork set_attrs(x = 1)\nnode set_attrs(y = 1)\nnode set_attrs(y = 2)\n",
+        );
+        let mut names: Vec<&String> = ctx.timings().keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["network set_attrs", "node set_attrs"]);
+        let summary = ctx.timings_summary();
+        assert_eq!(summary.lines().count(), 2);
+        assert!(summary.contains("node set_attrs: "), "{summary}");
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn partial_function_test() {
+        let mut ctx = test_ctx(Network::default());
+        let txt =
+            "env setx = set_attrs(x = 1, y = 1)\nenv setxz = setx(z = 3)\nnetwork setxz(y = 2)\n";
+        let tokens = crate::parser::tokenizer::get_tokens(txt).unwrap();
+        for task in crate::parser::tasks::parse(tokens).unwrap() {
+            ctx.execute(task).unwrap();
+        }
+        assert_eq!(ctx.partials["setxz"].name, "set_attrs");
+        for (k, v) in [("x", 1), ("y", 2), ("z", 3)] {
+            assert_eq!(ctx.network.attr(k), Some(&Attribute::Integer(v)));
+        }
+        let run = |ctx: &mut TaskContext, txt: &str| {
+            let tokens = crate::parser::tokenizer::get_tokens(txt).unwrap();
+            crate::parser::tasks::parse(tokens)
+                .unwrap()
+                .into_iter()
+                .try_for_each(|task| ctx.execute(task).map(|_| ()))
+        };
+        assert_eq!(
+            run(&mut ctx, "env set_attrs = setx(z = 3)\n"),
+            Err("Partial function set_attrs would shadow the function of the same name".into())
+        );
+        assert!(!ctx.partials.contains_key("set_attrs"));
+        // reassigning the name replaces the partial or the variable
+        run(&mut ctx, "env setxz = 1\nenv y = 2\nenv y = setx()\n").unwrap();
+        assert!(!ctx.partials.contains_key("setxz"));
+        assert_eq!(ctx.env.get("setxz"), Some(&Attribute::Integer(1)));
+        assert!(ctx.partials.contains_key("y"));
+        assert!(ctx.env.get("y").is_none());
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn timeseries_functions_test() {
+        use crate::timeseries::{HasTimeSeries, TimeLineInner, TimeSeries};
+        use abi_stable::{external_types::RMutex, std_types::RArc};
+
+        let mut ctx = test_ctx(Network::from_edges(&[("a", "b")]));
+        let tl = RArc::new(RMutex::new(TimeLineInner::new(0, 4, 1, true, vec![], "")));
+        ctx.network.try_node_by_name("a").unwrap().lock().set_ts(
+            "q",
+            TimeSeries::new(tl, vec![1.0f64, 2.0, 3.0, 4.0, 5.0].into()),
+        );
+        let run = |ctx: &mut TaskContext, txt: &str| {
+            let tokens = crate::parser::tokenizer::get_tokens(txt).unwrap();
+            crate::parser::tasks::parse(tokens)
+                .unwrap()
+                .into_iter()
+                .try_for_each(|t| ctx.execute(t).map(|_| ()))
+        };
+        run(
+            &mut ctx,
+            "node[a] ts_resample(\"q\", 2, \"sum\", \"q2\")\nnode[a] ts_slice(\"q\", 1, 3, \"qs\")\nnode[a] st = ts_stats(\"qs\")\nnode[a] ts_quantile(\"q\", 0.5, \"med\")\nnode[a] flag_outliers(\"q\", 1.0, \"qo\")\n",
+        )
+        .unwrap();
+        let a = ctx.network.try_node_by_name("a").unwrap();
+        let a = a.lock();
+        assert_eq!(
+            a.ts("q2").unwrap().values::<f64>(),
+            Some(&[3.0, 7.0, 5.0][..])
+        );
+        assert_eq!(a.ts("qs").unwrap().values::<f64>(), Some(&[2.0, 3.0][..]));
+        let Some(Attribute::Table(st)) = a.attr("st") else {
+            panic!("ts_stats should give a table");
+        };
+        assert_eq!(st.get("count"), Some(&Attribute::Integer(2)));
+        assert_eq!(st.get("mean"), Some(&Attribute::Float(2.5)));
+        assert_eq!(a.attr("med"), Some(&Attribute::Float(3.0)));
+        assert_eq!(
+            a.ts("qo").unwrap().values::<bool>(),
+            Some(&[true, false, false, false, true][..])
+        );
+        drop(a);
+        let err = run(&mut ctx, "node[b] ts_stats(\"q\")\n").unwrap_err();
+        assert!(err.contains("Timeseries `q` not found"), "{err}");
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn env_variable_arg_test() {
+        let mut ctx = test_ctx(Network::from_edges(&[("a", "b")]));
+        ctx.network
+            .try_node_by_name("a")
+            .unwrap()
+            .lock()
+            .set_attr("threshold", Attribute::Integer(1));
+        let txt =
+            "env threshold = 5\nnode set_attrs(x = threshold)\nnetwork set_attrs(x = threshold)\n";
+        let tokens = crate::parser::tokenizer::get_tokens(txt).unwrap();
+        for task in crate::parser::tasks::parse(tokens).unwrap() {
+            ctx.execute(task).unwrap();
+        }
+        // node attribute is used before the env variable
+        for (n, v) in [("a", 1), ("b", 5)] {
+            let node = ctx.network.try_node_by_name(n).unwrap().lock();
+            assert_eq!(node.attr("x"), Some(&Attribute::Integer(v)));
+        }
+        assert_eq!(ctx.network.attr("x"), Some(&Attribute::Integer(5)));
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn observer_test() {
+        use std::sync::{Arc, Mutex};
+        let mut ctx = test_ctx(Network::from_edges(&[("a", "b")]));
+        let events = Arc::new(Mutex::new(vec![]));
+        let ev = events.clone();
+        ctx.network
+            .set_observer(move |e| ev.lock().unwrap().push(e.clone()));
+        let txt = "node[a] x = 1\nnode[a] y = x\nnode[b] z = round(1.25, 1)\nnetwork w = 2\n";
+        let tokens = crate::parser::tokenizer::get_tokens(txt).unwrap();
+        for task in crate::parser::tasks::parse(tokens).unwrap() {
+            ctx.execute(task).unwrap();
+        }
+        let attr = |n: Option<&str>, a: &str| {
+            NetworkEvent::AttrChanged(n.map(RString::from).into(), a.into())
+        };
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                attr(Some("a"), "x"),
+                attr(Some("a"), "y"),
+                attr(Some("b"), "z"),
+                attr(None, "w")
+            ]
+        );
+    }
+
+    #[cfg(feature = "functions")]
+    #[rstest]
+    fn last_result_test() {
+        let mut ctx = test_ctx(Network::from_edges(&[("a", "b")]));
+        let run = |ctx: &mut TaskContext, txt: &str| {
+            let tokens = crate::parser::tokenizer::get_tokens(txt).unwrap();
+            for task in crate::parser::tasks::parse(tokens).unwrap() {
+                ctx.execute(task).unwrap();
+            }
+        };
+        run(&mut ctx, "network round(2.345, 1)\nenv y = _last\n");
+        assert_eq!(ctx.env.get("y"), Some(&Attribute::Float(2.3)));
+        assert_eq!(ctx.env.get(LAST_RESULT), Some(&Attribute::Float(2.3)));
+        run(&mut ctx, "node z = round(1.25, 1)\n");
+        let expected: AttrMap = [("a", 1.2), ("b", 1.2)]
+            .into_iter()
+            .map(|(k, v)| (k.into(), Attribute::Float(v)))
+            .collect();
+        assert_eq!(ctx.env.get(LAST_RESULT), Some(&Attribute::Table(expected)));
+        run(&mut ctx, "network set_attrs(x = 1)\n");
+        assert!(ctx.env.get(LAST_RESULT).is_none());
+    }
+}
@@ -27,6 +27,40 @@ pub trait HasTimeSeries {
             .get(name)
             .ok_or(format!("Timeseries `{name}` not found"))
     }
+
+    fn try_ts_mut(&mut self, name: &str) -> Result<&mut TimeSeries, String> {
+        self.ts_map_mut()
+            .get_mut(name)
+            .ok_or(format!("Timeseries `{name}` not found"))
+    }
+
+    /// Run the function on the mutable values of the timeseries
+    ///
+    /// # Errors
+    /// Errors if the timeseries is not found or its values are not of
+    /// type `T`, the function is not called in that case.
+    fn with_ts_mut<T, F>(&mut self, name: &str, f: F) -> Result<(), String>
+    where
+        T: for<'a> FromTimeSeries<'a>,
+        F: FnOnce(&mut [T]),
+    {
+        f(self.try_ts_mut(name)?.try_values_mut()?);
+        Ok(())
+    }
+
+    /// [`HasTimeSeries::with_ts_mut`] for timeseries of floats
+    fn with_ts_floats_mut(&mut self, name: &str, f: impl FnOnce(&mut [f64])) -> Result<(), String> {
+        self.with_ts_mut(name, f)
+    }
+
+    /// [`HasTimeSeries::with_ts_mut`] for timeseries of integers
+    fn with_ts_integers_mut(
+        &mut self,
+        name: &str,
+        f: impl FnOnce(&mut [i64]),
+    ) -> Result<(), String> {
+        self.with_ts_mut(name, f)
+    }
 }
 
 #[repr(C)]
@@ -92,6 +126,10 @@ impl<'a> TimeLineInner {
         self.step
     }
 
+    pub fn regular(&self) -> bool {
+        self.regular
+    }
+
     pub fn str_values(&'a self) -> impl Iterator<Item = &'a str> {
         self.str_values.iter().map(|s| s.as_str())
     }
@@ -153,19 +191,354 @@ impl TimeSeries {
         FromTimeSeries::try_from_ts_mut(&mut self.values)
     }
 
-    pub fn values_type(&self) -> &str {
+    pub fn values_type(&self) -> &'static str {
         self.values.type_name()
     }
 
+    /// Set the value at the index, the value should be of the same
+    /// type as the timeseries values (any value is accepted for
+    /// timeseries of `Attributes`)
+    ///
+    /// # Errors
+    /// Errors if the index is out of range or the type doesn't match.
+    pub fn set_value<T: Into<Attribute>>(&mut self, index: usize, value: T) -> Result<(), String> {
+        let len = self.len();
+        if index >= len {
+            return Err(format!(
+                "Index Error: index {index} out of range for timeseries of length {len}"
+            ));
+        }
+        self.values.set(index, value.into())
+    }
+
+    /// Number of values in the timeseries
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
     pub fn same_timeline(&self, other: &Self) -> bool {
         self.is_timeline(&other.timeline)
     }
 
+    /// Use the given timeline instead of the current one, so that
+    /// the timeseries with equal timelines can share the same one
+    ///
+    /// # Errors
+    /// Errors if the timeline is not equal to the current one.
+    pub fn share_timeline(&mut self, tl: &TimeLine) -> Result<(), String> {
+        if !self.is_timeline(tl) && *self.timeline.lock() != *tl.lock() {
+            return Err("Timeline is different from the timeseries' timeline".to_string());
+        }
+        self.timeline = tl.clone();
+        Ok(())
+    }
+
     pub fn is_timeline(&self, tl: &TimeLine) -> bool {
         // counting on RArc PartialEq to compare properly
         abi_stable::pointer_trait::AsPtr::as_ptr(&self.timeline)
             == abi_stable::pointer_trait::AsPtr::as_ptr(tl)
     }
+
+    /// Concatenate the timeseries of adjacent periods into a single
+    /// timeseries on a new timeline
+    ///
+    /// The timeseries must have the same step and values type, and
+    /// each one should start exactly one step after the end of the
+    /// previous one.
+    ///
+    /// # Errors
+    /// Errors if there are no timeseries, or if any adjacent pair has
+    /// a gap, an overlap, or a mismatch in step or values type.
+    pub fn concat(series: &[TimeSeries]) -> Result<TimeSeries, String> {
+        let first = series.first().ok_or("No timeseries to concatenate")?;
+        let mut values = first.values.clone();
+        let (start, step, fmt) = {
+            let tl = first.timeline.lock();
+            (tl.start, tl.step, tl.datetimefmt.clone())
+        };
+        let mut str_values = first.timeline.lock().str_values.clone();
+        for (i, pair) in series.windows(2).enumerate() {
+            let (prev, next) = (&pair[0], &pair[1]);
+            if prev.same_timeline(next) {
+                return Err(format!(
+                    "Timeseries {i} and {} overlap: they share the same timeline",
+                    i + 1
+                ));
+            }
+            let prev_tl = prev.timeline.lock();
+            let next_tl = next.timeline.lock();
+            if prev_tl.step != next_tl.step {
+                return Err(format!(
+                    "Timeseries {i} and {} have different steps: {} and {}",
+                    i + 1,
+                    prev_tl.step,
+                    next_tl.step
+                ));
+            }
+            if prev.values_type() != next.values_type() {
+                return Err(format!(
+                    "Timeseries {i} and {} have different types: {} and {}",
+                    i + 1,
+                    prev.values_type(),
+                    next.values_type()
+                ));
+            }
+            let expected = prev_tl.end + prev_tl.step;
+            if next_tl.start > expected {
+                return Err(format!(
+                    "Timeseries {i} and {} have a gap: {} expected to start at {expected}, starts at {}",
+                    i + 1,
+                    i + 1,
+                    next_tl.start
+                ));
+            } else if next_tl.start < expected {
+                return Err(format!(
+                    "Timeseries {i} and {} overlap: {} expected to start at {expected}, starts at {}",
+                    i + 1,
+                    i + 1,
+                    next_tl.start
+                ));
+            }
+            values.extend(&next.values);
+            str_values.extend(next_tl.str_values.iter().cloned());
+        }
+        let end = series
+            .last()
+            .map(|s| s.timeline.lock().end)
+            .unwrap_or(start);
+        let timeline = TimeLineInner {
+            start,
+            end,
+            step,
+            regular: true,
+            str_values,
+            datetimefmt: fmt,
+        };
+        Ok(TimeSeries::new(RArc::new(RMutex::new(timeline)), values))
+    }
+
+    /// Values from index `start` up to (excluding) `end` as a new
+    /// timeseries on a new timeline
+    ///
+    /// # Errors
+    /// Errors if the range is empty or goes beyond the length.
+    pub fn slice(&self, start: usize, end: usize) -> Result<TimeSeries, String> {
+        let len = self.len();
+        if start >= end || end > len {
+            return Err(format!(
+                "Index Error: invalid range {start}..{end} for timeseries of length {len}"
+            ));
+        }
+        let tl = self.timeline.lock();
+        let timeline = TimeLineInner {
+            start: tl.start + start as i64 * tl.step,
+            end: tl.start + (end as i64 - 1) * tl.step,
+            step: tl.step,
+            regular: tl.regular,
+            str_values: tl
+                .str_values
+                .iter()
+                .skip(start)
+                .take(end - start)
+                .cloned()
+                .collect(),
+            datetimefmt: tl.datetimefmt.clone(),
+        };
+        Ok(TimeSeries::new(
+            RArc::new(RMutex::new(timeline)),
+            self.values.slice(start, end),
+        ))
+    }
+
+    /// Aggregate every `n` values into one, giving a new timeseries
+    /// of floats with `n` times the step; the last group can have
+    /// fewer values
+    ///
+    /// # Errors
+    /// Errors if `n` is zero, the timeline is not regular, or the
+    /// values are not numeric.
+    pub fn resample(&self, n: usize, agg: Aggregation) -> Result<TimeSeries, String> {
+        if n == 0 {
+            return Err("Resample step should be greater than zero".to_string());
+        }
+        let values = self.float_values()?;
+        let tl = self.timeline.lock();
+        if !tl.regular {
+            return Err("Only regular timeseries can be resampled".to_string());
+        }
+        let values: Vec<f64> = values.chunks(n).map(|c| agg.apply(c)).collect();
+        let step = tl.step * n as i64;
+        let timeline = TimeLineInner {
+            start: tl.start,
+            end: tl.start + (values.len() as i64 - 1).max(0) * step,
+            step,
+            regular: true,
+            str_values: tl.str_values.iter().step_by(n).cloned().collect(),
+            datetimefmt: tl.datetimefmt.clone(),
+        };
+        Ok(TimeSeries::new(
+            RArc::new(RMutex::new(timeline)),
+            values.into(),
+        ))
+    }
+
+    /// Summary statistics of the numeric values, `NaN` values are
+    /// skipped
+    ///
+    /// # Errors
+    /// Errors if the values are not numeric.
+    pub fn stats(&self) -> Result<TsStats, String> {
+        let values: Vec<f64> = self
+            .float_values()?
+            .into_iter()
+            .filter(|v| !v.is_nan())
+            .collect();
+        let mean = Aggregation::Mean.apply(&values);
+        let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+        Ok(TsStats {
+            count: values.len(),
+            mean,
+            std: var.sqrt(),
+            min: Aggregation::Min.apply(&values),
+            max: Aggregation::Max.apply(&values),
+        })
+    }
+
+    /// Quantile `q` (between 0 and 1) of the numeric values, `NaN`
+    /// values are skipped
+    ///
+    /// The value is linearly interpolated between the two nearest
+    /// values when `q` falls in between them, so `0.5` gives the
+    /// median.
+    ///
+    /// # Errors
+    /// Errors if `q` is out of range, the values are not numeric, or
+    /// there are no values.
+    pub fn quantile(&self, q: f64) -> Result<f64, String> {
+        if !(0.0..=1.0).contains(&q) {
+            return Err(format!("Quantile should be between 0 and 1, got {q}"));
+        }
+        let mut values: Vec<f64> = self
+            .float_values()?
+            .into_iter()
+            .filter(|v| !v.is_nan())
+            .collect();
+        if values.is_empty() {
+            return Err("No values in the timeseries to calculate quantile".to_string());
+        }
+        values.sort_by(f64::total_cmp);
+        let h = (values.len() - 1) as f64 * q;
+        let lo = h.floor() as usize;
+        let hi = h.ceil() as usize;
+        Ok(values[lo] + (h - lo as f64) * (values[hi] - values[lo]))
+    }
+
+    /// Mask of the values outside `mean ± n_sigma * std`, the mean and
+    /// standard deviation are calculated skipping the `NaN` values,
+    /// which are not flagged
+    ///
+    /// # Errors
+    /// Errors if `n_sigma` is negative or the values are not numeric.
+    pub fn outlier_mask(&self, n_sigma: f64) -> Result<Vec<bool>, String> {
+        if n_sigma.is_nan() || n_sigma < 0.0 {
+            return Err(format!(
+                "Number of standard deviations should not be negative, got {n_sigma}"
+            ));
+        }
+        let stats = self.stats()?;
+        let limit = n_sigma * stats.std;
+        Ok(self
+            .float_values()?
+            .into_iter()
+            .map(|v| (v - stats.mean).abs() > limit)
+            .collect())
+    }
+
+    /// Values as floats if they are floats or integers
+    fn float_values(&self) -> Result<Vec<f64>, String> {
+        match &self.values {
+            TimeSeriesValues::Floats(v) => Ok(v.to_vec()),
+            TimeSeriesValues::Integers(v) => Ok(v.iter().map(|i| *i as f64).collect()),
+            v => Err(format!(
+                "Incorrect Type: timeseries of `{}` is not numeric",
+                v.type_name()
+            )),
+        }
+    }
+}
+
+impl std::cmp::PartialEq for TimeSeries {
+    fn eq(&self, other: &Self) -> bool {
+        // same timeline can't be locked twice, and doesn't need to be
+        // compared either
+        (self.same_timeline(other) || *self.timeline.lock() == *other.timeline.lock())
+            && self.values == other.values
+    }
+}
+
+/// Function used to combine the values of a group into one, `NaN`
+/// values are skipped except for `first` and `last`
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Aggregation {
+    #[default]
+    Mean,
+    Sum,
+    Min,
+    Max,
+    First,
+    Last,
+}
+
+impl Aggregation {
+    /// Aggregate the values, gives `NaN` if there are no values
+    pub fn apply(&self, values: &[f64]) -> f64 {
+        let mut valid = values.iter().copied().filter(|v| !v.is_nan());
+        let res = match self {
+            Self::Mean => {
+                let (sum, count) = valid.fold((0.0, 0), |(s, c), v| (s + v, c + 1));
+                (count > 0).then_some(sum / count as f64)
+            }
+            Self::Sum => valid.next().map(|v| v + valid.sum::<f64>()),
+            Self::Min => valid.reduce(f64::min),
+            Self::Max => valid.reduce(f64::max),
+            Self::First => values.first().copied(),
+            Self::Last => values.last().copied(),
+        };
+        res.unwrap_or(f64::NAN)
+    }
+}
+
+impl std::str::FromStr for Aggregation {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mean" => Ok(Self::Mean),
+            "sum" => Ok(Self::Sum),
+            "min" => Ok(Self::Min),
+            "max" => Ok(Self::Max),
+            "first" => Ok(Self::First),
+            "last" => Ok(Self::Last),
+            _ => Err(format!(
+                "Invalid aggregation {s:?}, should be one of mean, sum, min, max, first or last"
+            )),
+        }
+    }
+}
+
+/// Summary statistics of a numeric timeseries, see [`TimeSeries::stats`]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TsStats {
+    /// Number of values that are not `NaN`
+    pub count: usize,
+    pub mean: f64,
+    /// Population standard deviation
+    pub std: f64,
+    pub min: f64,
+    pub max: f64,
 }
 
 #[repr(C)]
@@ -224,7 +597,61 @@ impl TimeSeriesValues {
         self.len() == 0
     }
 
-    pub fn type_name(&self) -> &str {
+    /// Set the value at the index if the types match, the index
+    /// should be in range
+    fn set(&mut self, index: usize, value: Attribute) -> Result<(), String> {
+        match (&mut *self, value) {
+            (Self::Floats(v), Attribute::Float(a)) => v[index] = a,
+            (Self::Integers(v), Attribute::Integer(a)) => v[index] = a,
+            (Self::Strings(v), Attribute::String(a)) => v[index] = a,
+            (Self::Booleans(v), Attribute::Bool(a)) => v[index] = a,
+            (Self::Dates(v), Attribute::Date(a)) => v[index] = a,
+            (Self::Times(v), Attribute::Time(a)) => v[index] = a,
+            (Self::DateTimes(v), Attribute::DateTime(a)) => v[index] = a,
+            (Self::Attributes(v), a) => v[index] = a,
+            (_, a) => {
+                return Err(format!(
+                    "Incorrect Type: value of `{}` cannot be set in timeseries of `{}`",
+                    a.type_name(),
+                    self.type_name()
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Append the values from another of the same type, values of
+    /// different types are ignored
+    fn extend(&mut self, other: &Self) {
+        match (self, other) {
+            (Self::Floats(a), Self::Floats(b)) => a.extend_from_slice(b),
+            (Self::Integers(a), Self::Integers(b)) => a.extend_from_slice(b),
+            (Self::Strings(a), Self::Strings(b)) => a.extend_from_slice(b),
+            (Self::Booleans(a), Self::Booleans(b)) => a.extend_from_slice(b),
+            (Self::Dates(a), Self::Dates(b)) => a.extend_from_slice(b),
+            (Self::Times(a), Self::Times(b)) => a.extend_from_slice(b),
+            (Self::DateTimes(a), Self::DateTimes(b)) => a.extend_from_slice(b),
+            (Self::Attributes(a), Self::Attributes(b)) => a.extend_from_slice(b),
+            _ => (),
+        }
+    }
+
+    /// Values from index `start` up to (excluding) `end`, the range
+    /// should be valid
+    fn slice(&self, start: usize, end: usize) -> Self {
+        match self {
+            Self::Floats(v) => Self::Floats(v[start..end].into()),
+            Self::Integers(v) => Self::Integers(v[start..end].into()),
+            Self::Strings(v) => Self::Strings(v[start..end].into()),
+            Self::Booleans(v) => Self::Booleans(v[start..end].into()),
+            Self::Dates(v) => Self::Dates(v[start..end].into()),
+            Self::Times(v) => Self::Times(v[start..end].into()),
+            Self::DateTimes(v) => Self::DateTimes(v[start..end].into()),
+            Self::Attributes(v) => Self::Attributes(v[start..end].into()),
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
         match self {
             Self::Floats(_) => "Floats",
             Self::Integers(_) => "Integers",
@@ -297,3 +724,183 @@ impl_from_ts!(Date, TimeSeriesValues::Dates);
 impl_from_ts!(Time, TimeSeriesValues::Times);
 impl_from_ts!(DateTime, TimeSeriesValues::DateTimes);
 impl_from_ts!(Attribute, TimeSeriesValues::Attributes);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn timeline(start: i64) -> TimeLine {
+        RArc::new(RMutex::new(TimeLineInner::new(
+            start,
+            start + 2,
+            1,
+            true,
+            vec![],
+            "",
+        )))
+    }
+
+    #[rstest]
+    fn len_test() {
+        let ts = TimeSeries::new(timeline(0), vec![1.0f64, 2.0, 3.0].into());
+        assert_eq!(ts.len(), 3);
+        assert!(!ts.is_empty());
+        let ts = TimeSeries::new(timeline(0), TimeSeriesValues::Integers(RVec::new()));
+        assert_eq!(ts.len(), 0);
+        assert!(ts.is_empty());
+    }
+
+    #[rstest]
+    fn eq_test() {
+        let tl = timeline(0);
+        let ts1 = TimeSeries::new(tl.clone(), vec![1i64, 2, 3].into());
+        let ts2 = TimeSeries::new(tl, vec![1i64, 2, 3].into());
+        assert!(ts1 == ts2);
+        let ts3 = TimeSeries::new(timeline(0), vec![1i64, 2, 3].into());
+        assert!(ts1 == ts3);
+        let ts4 = TimeSeries::new(timeline(1), vec![1i64, 2, 3].into());
+        assert!(ts1 != ts4);
+        let ts5 = TimeSeries::new(timeline(0), vec![1.0f64, 2.0, 3.0].into());
+        assert!(ts1 != ts5);
+    }
+
+    #[rstest]
+    fn concat_test() {
+        let ts1 = TimeSeries::new(timeline(0), vec![1i64, 2, 3].into());
+        let ts2 = TimeSeries::new(timeline(3), vec![4i64, 5, 6].into());
+        let ts = TimeSeries::concat(&[ts1, ts2]).unwrap();
+        assert_eq!(ts.start(), 0);
+        assert_eq!(ts.timeline().lock().end(), 5);
+        assert_eq!(ts.values::<i64>().unwrap(), &[1, 2, 3, 4, 5, 6]);
+    }
+
+    #[rstest]
+    #[case(
+        4,
+        "Timeseries 0 and 1 have a gap: 1 expected to start at 3, starts at 4"
+    )]
+    #[case(2, "Timeseries 0 and 1 overlap: 1 expected to start at 3, starts at 2")]
+    fn concat_gap_test(#[case] start: i64, #[case] err: &str) {
+        let ts1 = TimeSeries::new(timeline(0), vec![1i64, 2, 3].into());
+        let ts2 = TimeSeries::new(timeline(start), vec![4i64, 5, 6].into());
+        assert_eq!(TimeSeries::concat(&[ts1, ts2]).err().unwrap(), err);
+    }
+
+    #[rstest]
+    fn concat_type_test() {
+        let ts1 = TimeSeries::new(timeline(0), vec![1i64, 2, 3].into());
+        let ts2 = TimeSeries::new(timeline(3), vec![4i64, 5, 6].into());
+        let ts3 = TimeSeries::new(timeline(6), vec![1.0f64, 2.0, 3.0].into());
+        assert_eq!(
+            TimeSeries::concat(&[ts1, ts2, ts3]).err().unwrap(),
+            "Timeseries 1 and 2 have different types: Integers and Floats"
+        );
+    }
+
+    #[rstest]
+    fn set_value_test() {
+        let mut ts = TimeSeries::new(timeline(0), vec![1.0f64, 2.0, 3.0].into());
+        ts.set_value(1, 5.0).unwrap();
+        assert_eq!(ts.values::<f64>(), Some(&[1.0, 5.0, 3.0][..]));
+        assert_eq!(
+            ts.set_value(3, 1.0).unwrap_err(),
+            "Index Error: index 3 out of range for timeseries of length 3"
+        );
+        assert_eq!(
+            ts.set_value(0, 1i64).unwrap_err(),
+            "Incorrect Type: value of `Integer` cannot be set in timeseries of `Floats`"
+        );
+        assert_eq!(ts.values::<f64>(), Some(&[1.0, 5.0, 3.0][..]));
+    }
+
+    #[rstest]
+    fn slice_test() {
+        let ts = TimeSeries::new(timeline(0), vec![1i64, 2, 3].into());
+        let sl = ts.slice(1, 3).unwrap();
+        assert_eq!(sl.start(), 1);
+        assert_eq!(sl.timeline().lock().end(), 2);
+        assert_eq!(sl.values::<i64>(), Some(&[2, 3][..]));
+        assert_eq!(
+            ts.slice(2, 4).unwrap_err(),
+            "Index Error: invalid range 2..4 for timeseries of length 3"
+        );
+    }
+
+    #[rstest]
+    #[case(Aggregation::Mean, &[1.5, 3.0])]
+    #[case(Aggregation::Sum, &[3.0, 3.0])]
+    #[case(Aggregation::Min, &[1.0, 3.0])]
+    #[case(Aggregation::Max, &[2.0, 3.0])]
+    #[case(Aggregation::First, &[1.0, 3.0])]
+    #[case(Aggregation::Last, &[2.0, 3.0])]
+    fn resample_test(#[case] agg: Aggregation, #[case] values: &[f64]) {
+        let ts = TimeSeries::new(timeline(0), vec![1i64, 2, 3].into());
+        let rs = ts.resample(2, agg).unwrap();
+        assert_eq!(rs.step(), 2);
+        assert_eq!(rs.timeline().lock().end(), 2);
+        assert_eq!(rs.values::<f64>(), Some(values));
+    }
+
+    #[rstest]
+    fn stats_test() {
+        let ts = TimeSeries::new(timeline(0), vec![1.0f64, f64::NAN, 3.0].into());
+        let stats = ts.stats().unwrap();
+        assert_eq!(
+            stats,
+            TsStats {
+                count: 2,
+                mean: 2.0,
+                std: 1.0,
+                min: 1.0,
+                max: 3.0
+            }
+        );
+        let ts = TimeSeries::new(timeline(0), vec![true, false, true].into());
+        assert_eq!(
+            ts.stats().unwrap_err(),
+            "Incorrect Type: timeseries of `Booleans` is not numeric"
+        );
+    }
+
+    #[rstest]
+    #[case(0.0, 1.0)]
+    #[case(0.5, 3.0)]
+    #[case(1.0, 5.0)]
+    #[case(0.25, 2.0)]
+    #[case(0.9, 4.6)]
+    fn quantile_test(#[case] q: f64, #[case] value: f64) {
+        let ts = TimeSeries::new(
+            RArc::new(RMutex::new(TimeLineInner::new(0, 5, 1, true, vec![], ""))),
+            vec![5i64, 3, 1, 4, 2].into(),
+        );
+        assert!((ts.quantile(q).unwrap() - value).abs() < 1e-9);
+    }
+
+    #[rstest]
+    fn outlier_mask_test() {
+        let mut values = vec![1.0f64; 10];
+        values[4] = 100.0;
+        values[7] = f64::NAN;
+        let ts = TimeSeries::new(
+            RArc::new(RMutex::new(TimeLineInner::new(0, 9, 1, true, vec![], ""))),
+            values.into(),
+        );
+        let mask = ts.outlier_mask(2.0).unwrap();
+        let flagged: Vec<usize> = (0..mask.len()).filter(|i| mask[*i]).collect();
+        assert_eq!(flagged, vec![4]);
+        assert!(ts.outlier_mask(-1.0).is_err());
+    }
+
+    #[rstest]
+    fn quantile_error_test() {
+        let ts = TimeSeries::new(timeline(0), vec![1.0f64, f64::NAN, 2.0].into());
+        assert_eq!(ts.quantile(0.5).unwrap(), 1.5);
+        assert_eq!(
+            ts.quantile(1.5).unwrap_err(),
+            "Quantile should be between 0 and 1, got 1.5"
+        );
+        let ts = TimeSeries::new(timeline(0), vec![f64::NAN; 3].into());
+        assert!(ts.quantile(0.5).is_err());
+    }
+}